@@ -1,4 +1,10 @@
+use std::{env, path::PathBuf};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("protos/crypto.proto")?;
+    // The descriptor set is served over gRPC reflection
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("orderbook_descriptor.bin"))
+        .compile(&["protos/crypto.proto"], &["protos"])?;
     Ok(())
 }
@@ -1,28 +1,115 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
 use anyhow::Result;
 use orderbook::orderbook_aggregator_client::OrderbookAggregatorClient;
-use orderbook::Empty;
+use orderbook::{BookSummaryRequest, LagPolicy, Summary};
+use tokio::time::{sleep_until, Instant};
+use tonic::{
+    transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity},
+    Streaming,
+};
+
+use crate::models::{
+    config::{read_pem, ClientConfig},
+    mapper::SummaryOutput,
+};
 
-use crate::models::consts::{IP_ADDRESS, SERVER_PORT};
+use super::output::SummaryWriter;
 
 pub mod orderbook {
     tonic::include_proto!("orderbook");
 }
 
-pub async fn listen() -> Result<()> {
-    println!("Hello I'm a gRPC CLient TO BE implemented!");
+/// Streams the summaries described by `config` from the server it points at to stdout
+pub async fn listen(config: ClientConfig) -> Result<()> {
+    let mut writer = SummaryWriter::new(config.output, io::stdout().lock());
+    let received = stream_summaries(&config, &mut writer).await?;
+    log::info!("Received {} summaries", received);
+
+    Ok(())
+}
 
-    let server_url = format!("http://{}:{}", IP_ADDRESS, SERVER_PORT);
-    let mut client = OrderbookAggregatorClient::connect(server_url).await?;
+/// Connects to the server of `config` and requests the summaries it describes
+pub async fn subscribe(config: &ClientConfig) -> Result<Streaming<Summary>> {
+    let request = BookSummaryRequest {
+        symbols: config.symbols.clone(),
+        depth: config.depth,
+        exchanges: config
+            .exchanges
+            .iter()
+            .map(|exchange| exchange.to_string())
+            .collect(),
+        throttle_ms: config.throttle_ms,
+    };
 
-    let empty = Empty {};
+    let mut client = connect(config).await?;
+    Ok(client.book_summary(request).await?.into_inner())
+}
 
-    let mut stream = client.book_summary(empty).await?.into_inner();
+/// Connects to the server of `config`. "https" URLs are connected to over TLS, verifying the
+/// server against `tls.ca` if set, and presenting `tls.cert` to servers that require it
+pub async fn connect(config: &ClientConfig) -> Result<OrderbookAggregatorClient<Channel>> {
+    let mut endpoint = Endpoint::from_shared(config.server_url())?;
 
-    while let Some(summary) = stream.message().await? {
-        // Uncomment me to beautify output. Note: it does add some latency to the client, which is why it's commented by default
-        // let summary = SummaryOutput::from(summary);
-        log::info!("\n{:#?}", summary);
+    if config.tls.is_set() {
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca) = &config.tls.ca {
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(read_pem(ca)?));
+        }
+        if let (Some(cert), Some(key)) = (&config.tls.cert, &config.tls.key) {
+            tls_config = tls_config.identity(Identity::from_pem(read_pem(cert)?, read_pem(key)?));
+        }
+        if let Some(domain) = &config.tls.domain {
+            tls_config = tls_config.domain_name(domain);
+        }
+        endpoint = endpoint.tls_config(tls_config)?;
     }
 
-    Ok(())
+    Ok(OrderbookAggregatorClient::new(endpoint.connect().await?))
+}
+
+/// Writes the summaries described by `config` with `writer` until the stream ends or its `count` or
+/// `duration_secs` is reached, and returns how many were written
+pub async fn stream_summaries<W: Write>(
+    config: &ClientConfig,
+    writer: &mut SummaryWriter<W>,
+) -> Result<u64> {
+    let mut stream = subscribe(config).await?;
+
+    let deadline = match config.duration_secs {
+        0 => None,
+        secs => Some(Instant::now() + Duration::from_secs(secs)),
+    };
+    let mut received = 0;
+
+    while config.count == 0 || received < config.count {
+        let summary = tokio::select! {
+            summary = stream.message() => match summary? {
+                Some(summary) => summary,
+                None => break,
+            },
+            // Only polled when a duration was given
+            _ = async { sleep_until(deadline.unwrap()).await }, if deadline.is_some() => break,
+        };
+
+        if let Some(lag) = &summary.lag {
+            let caught_up = match lag.policy() {
+                LagPolicy::Skip => "Resumed with the latest updates",
+                LagPolicy::Snapshot => "Books were rebuilt from a fresh snapshot",
+            };
+            log::warn!(
+                "Fell behind the server by {} updates of {} ({} in total). {}",
+                lag.skipped,
+                summary.symbol,
+                lag.total_skipped,
+                caught_up
+            );
+        }
+
+        writer.write(SummaryOutput::from(summary))?;
+        received += 1;
+    }
+
+    Ok(received)
 }
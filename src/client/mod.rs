@@ -1 +1,3 @@
 pub mod grpc_client;
+pub mod output;
+pub mod tui;
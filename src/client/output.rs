@@ -0,0 +1,123 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::models::mapper::{LevelOutput, SummaryOutput};
+
+/// How the client writes the summaries it receives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Aligned table per summary, for humans
+    #[default]
+    Table,
+    /// One JSON object per summary and line
+    Json,
+    /// One row per level, after a header row
+    Csv,
+    /// `SummaryOutput` debug view
+    Debug,
+}
+
+/// Writes summaries to `out` in the chosen format
+pub struct SummaryWriter<W: Write> {
+    format: OutputFormat,
+    out: W,
+    /// Whether the CSV header was already written
+    header_written: bool,
+}
+
+impl<W: Write> SummaryWriter<W> {
+    pub fn new(format: OutputFormat, out: W) -> Self {
+        SummaryWriter {
+            format,
+            out,
+            header_written: false,
+        }
+    }
+
+    /// Writes a single summary and flushes it so that pipelines get it right away
+    pub fn write(&mut self, summary: SummaryOutput) -> Result<()> {
+        match self.format {
+            OutputFormat::Table => self.write_table(&summary)?,
+            OutputFormat::Json => writeln!(self.out, "{}", serde_json::to_string(&summary)?)?,
+            OutputFormat::Csv => self.write_csv(&summary)?,
+            OutputFormat::Debug => writeln!(self.out, "{:#?}", summary)?,
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Gives the underlying writer back
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Helper to write a summary as a table with the asks on top of the bids, both best first
+    fn write_table(&mut self, summary: &SummaryOutput) -> Result<()> {
+        let rows: Vec<(&str, &LevelOutput)> = summary
+            .asks
+            .iter()
+            .map(|level| ("ask", level))
+            .chain(summary.bids.iter().map(|level| ("bid", level)))
+            .collect();
+
+        let width = |header: &str, column: fn(&LevelOutput) -> &str| {
+            rows.iter()
+                .map(|(_, level)| column(level).len())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        };
+        let exchange_width = width("EXCHANGE", |level| &level.exchange);
+        let price_width = width("PRICE", |level| &level.price);
+        let amount_width = width("AMOUNT", |level| &level.amount);
+
+        writeln!(self.out, "{} spread: {}", summary.symbol, summary.spread)?;
+        writeln!(
+            self.out,
+            "SIDE  {:<exchange_width$}  {:>price_width$}  {:>amount_width$}",
+            "EXCHANGE", "PRICE", "AMOUNT"
+        )?;
+        for (side, level) in rows {
+            writeln!(
+                self.out,
+                "{:<4}  {:<exchange_width$}  {:>price_width$}  {:>amount_width$}",
+                side, level.exchange, level.price, level.amount
+            )?;
+        }
+        writeln!(self.out)?;
+
+        Ok(())
+    }
+
+    /// Helper to write a row per level, ranked from the best of each side
+    fn write_csv(&mut self, summary: &SummaryOutput) -> Result<()> {
+        if !self.header_written {
+            writeln!(self.out, "symbol,spread,side,rank,exchange,price,amount")?;
+            self.header_written = true;
+        }
+
+        let sides = [("ask", &summary.asks), ("bid", &summary.bids)];
+        for (side, levels) in sides {
+            for (rank, level) in levels.iter().enumerate() {
+                writeln!(
+                    self.out,
+                    "{},{},{},{},{},{},{}",
+                    summary.symbol,
+                    summary.spread,
+                    side,
+                    rank + 1,
+                    level.exchange,
+                    level.price,
+                    level.amount
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
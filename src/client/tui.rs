@@ -0,0 +1,312 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, Table, Tabs},
+    DefaultTerminal, Frame,
+};
+use tokio::time::{interval, Instant};
+use tonic::Streaming;
+
+use crate::models::{
+    config::ClientConfig,
+    mapper::{LevelOutput, SummaryOutput},
+};
+
+use super::grpc_client::{orderbook::Summary, subscribe};
+
+/// How often the screen is redrawn and the keyboard polled, even without new summaries
+const TICK: Duration = Duration::from_millis(100);
+
+/// Updates per second of a symbol, measured over windows of about a second
+#[derive(Debug)]
+pub struct UpdateRate {
+    /// Summaries received since the client started
+    pub total: u64,
+    /// Summaries per second over the last full window
+    pub per_sec: f64,
+    window_start: Instant,
+    window_count: u64,
+}
+
+impl UpdateRate {
+    pub fn new(now: Instant) -> Self {
+        UpdateRate {
+            total: 0,
+            per_sec: 0.0,
+            window_start: now,
+            window_count: 0,
+        }
+    }
+
+    /// Counts a summary received at `now`
+    pub fn record(&mut self, now: Instant) {
+        self.total += 1;
+        self.window_count += 1;
+        self.tick(now);
+    }
+
+    /// Closes the window once it's a second old so that the rate drops when updates stop
+    pub fn tick(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.per_sec = self.window_count as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_count = 0;
+        }
+    }
+}
+
+/// State of the terminal UI. Keeps the latest summary of every symbol and shows one at a time
+pub struct LadderApp {
+    /// Latest summary and update rate of each symbol, sorted by symbol
+    symbols: BTreeMap<String, (SummaryOutput, UpdateRate)>,
+    /// Index of the symbol on screen
+    selected: usize,
+}
+
+impl Default for LadderApp {
+    fn default() -> Self {
+        LadderApp::new()
+    }
+}
+
+impl LadderApp {
+    pub fn new() -> Self {
+        LadderApp {
+            symbols: BTreeMap::new(),
+            selected: 0,
+        }
+    }
+
+    /// Keeps `summary` as the latest of its symbol
+    pub fn on_summary(&mut self, summary: SummaryOutput, now: Instant) {
+        match self.symbols.get_mut(&summary.symbol) {
+            Some((latest, rate)) => {
+                *latest = summary;
+                rate.record(now);
+            }
+            None => {
+                let mut rate = UpdateRate::new(now);
+                rate.record(now);
+                self.symbols.insert(summary.symbol.clone(), (summary, rate));
+            }
+        }
+    }
+
+    /// Refreshes the update rates
+    pub fn on_tick(&mut self, now: Instant) {
+        for (_, rate) in self.symbols.values_mut() {
+            rate.tick(now);
+        }
+    }
+
+    /// Shows the next symbol, wrapping around
+    pub fn next_symbol(&mut self) {
+        if !self.symbols.is_empty() {
+            self.selected = (self.selected + 1) % self.symbols.len();
+        }
+    }
+
+    /// Shows the previous symbol, wrapping around
+    pub fn previous_symbol(&mut self) {
+        if !self.symbols.is_empty() {
+            self.selected = (self.selected + self.symbols.len() - 1) % self.symbols.len();
+        }
+    }
+
+    /// Handles a key press, returning whether the user quit. Ctrl+C comes in as a key press since
+    /// the terminal is in raw mode
+    pub fn on_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Right | KeyCode::Tab => self.next_symbol(),
+            KeyCode::Left | KeyCode::BackTab => self.previous_symbol(),
+            _ => {}
+        }
+
+        false
+    }
+
+    /// Symbol on screen, if we got any summary yet
+    pub fn selected_symbol(&self) -> Option<&str> {
+        self.symbols.keys().nth(self.selected).map(String::as_str)
+    }
+
+    /// Draws the symbol tabs and rates on top, the ladder on the left and the venues on the right
+    pub fn render(&self, frame: &mut Frame) {
+        let [header, body] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(frame.area());
+        let [ladder, venues] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(28)]).areas(body);
+
+        let titles: Vec<String> = self.symbols.keys().cloned().collect();
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.selected)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .block(Block::bordered().title(" Symbols (←/→ to switch, q to quit) ")),
+            header,
+        );
+
+        match self.selected_symbol().map(|symbol| &self.symbols[symbol]) {
+            Some((summary, rate)) => {
+                self.render_ladder(frame, ladder, summary, rate);
+                self.render_venues(frame, venues, summary);
+            }
+            None => frame.render_widget(
+                Paragraph::new("Waiting for summaries...").block(Block::bordered()),
+                body,
+            ),
+        }
+    }
+
+    /// Helper to draw asks above bids so that the best levels of both sides meet at the spread
+    fn render_ladder(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        summary: &SummaryOutput,
+        rate: &UpdateRate,
+    ) {
+        let level_row = |side: &'static str, level: &LevelOutput| {
+            let style = Style::default().fg(exchange_color(&level.exchange));
+            Row::new(vec![
+                Cell::from(side),
+                Cell::from(level.exchange.clone()),
+                Cell::from(Line::from(level.price.clone()).right_aligned()),
+                Cell::from(Line::from(level.amount.clone()).right_aligned()),
+            ])
+            .style(style)
+        };
+
+        let spread_row = Row::new(vec![
+            Cell::from(""),
+            Cell::from("spread"),
+            Cell::from(Line::from(summary.spread.clone()).right_aligned()),
+            Cell::from(""),
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = summary
+            .asks
+            .iter()
+            .rev()
+            .map(|level| level_row("ask", level))
+            .chain([spread_row])
+            .chain(summary.bids.iter().map(|level| level_row("bid", level)))
+            .collect();
+
+        let title = format!(
+            " {} | spread {} | {:.1} updates/s | {} total ",
+            summary.symbol, summary.spread, rate.per_sec, rate.total
+        );
+        let widths = [
+            Constraint::Length(4),
+            Constraint::Length(10),
+            Constraint::Min(12),
+            Constraint::Min(12),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(
+                    Row::new(vec!["SIDE", "EXCHANGE", "PRICE", "AMOUNT"])
+                        .style(Style::default().add_modifier(Modifier::BOLD)),
+                )
+                .block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    /// Helper to draw the connection status of every venue, along with how long ago it last
+    /// updated its book
+    fn render_venues(&self, frame: &mut Frame, area: Rect, summary: &SummaryOutput) {
+        let lines: Vec<Line> = summary
+            .exchanges
+            .iter()
+            .map(|status| {
+                let status_color = match status.status.as_str() {
+                    "CONNECTED" => Color::Green,
+                    "DISCONNECTED" => Color::Red,
+                    _ => Color::Yellow,
+                };
+                let mut spans = vec![
+                    Span::styled(
+                        format!("{:<10}", status.exchange),
+                        Style::default().fg(exchange_color(&status.exchange)),
+                    ),
+                    Span::styled(status.status.clone(), Style::default().fg(status_color)),
+                ];
+                if let Some(age_ms) = status.last_update_age_ms {
+                    spans.push(Span::raw(format!(" {:.1}s", age_ms as f64 / 1000.0)));
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Venues ")),
+            area,
+        );
+    }
+}
+
+/// Color of the levels of each exchange
+pub fn exchange_color(exchange: &str) -> Color {
+    match exchange.to_lowercase().as_str() {
+        "binance" => Color::Yellow,
+        "bitstamp" => Color::Green,
+        "coinbase" => Color::Blue,
+        "kraken" => Color::Magenta,
+        "okx" => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+/// Full screen client. Streams the summaries described by `config` into a live ladder until the
+/// user quits or the server closes the stream
+pub async fn run(config: ClientConfig) -> Result<()> {
+    let stream = subscribe(&config).await?;
+
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, stream).await;
+    // Always give the terminal back, even if the stream failed
+    ratatui::restore();
+
+    result
+}
+
+/// Helper to draw and handle events until the user quits
+async fn run_app(terminal: &mut DefaultTerminal, mut stream: Streaming<Summary>) -> Result<()> {
+    let mut app = LadderApp::new();
+    let mut ticker = interval(TICK);
+
+    loop {
+        tokio::select! {
+            summary = stream.message() => match summary? {
+                Some(summary) => app.on_summary(SummaryOutput::from(summary), Instant::now()),
+                None => return Ok(()),
+            },
+            _ = ticker.tick() => {
+                app.on_tick(Instant::now());
+
+                // Only drain the keys that are already there so that we never block the runtime
+                while event::poll(Duration::ZERO)? {
+                    if let Event::Key(key) = event::read()? {
+                        if key.kind == KeyEventKind::Press && app.on_key(key) {
+                            return Ok(());
+                        }
+                    }
+                }
+
+                terminal.draw(|frame| app.render(frame))?;
+            }
+        }
+    }
+}
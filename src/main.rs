@@ -1,6 +1,18 @@
+use std::{net::IpAddr, path::PathBuf};
+
 use anyhow::Result;
 use clap::Parser;
-use crypto_streamer::{client::grpc_client, server::grpc_server};
+use crypto_streamer::{
+    client::{grpc_client, output::OutputFormat, tui},
+    models::{
+        config::{ClientConfig, ServerConfig},
+        errors::ConfigError,
+        mapper::Exchange,
+        replay::ReplaySpeed,
+        stream_service::LagPolicy,
+    },
+    server::grpc_server,
+};
 
 // Command line argument processing config.
 #[derive(Parser)]
@@ -12,7 +24,7 @@ struct Opts {
 
 #[derive(Parser)]
 enum SubCommand {
-    /// gRPC Server. It also spawns threads to listen for Binance and Bitstamp
+    /// gRPC Server. It also spawns threads to listen for the selected exchanges
     #[clap(version = "1.0", author = "Igor Braga <higorb1@gmail.com>")]
     Server(ServerArgs),
 
@@ -23,13 +35,208 @@ enum SubCommand {
 
 #[derive(Parser)]
 pub(crate) struct ServerArgs {
-    /// Symbol (currency pair) to which we'll stream
-    #[clap(short = 's')]
-    symbol: Option<String>,
+    /// TOML configuration file. Environment variables prefixed with ORDERBOOK_ and flags take precedence
+    #[clap(short = 'c', long)]
+    config: Option<PathBuf>,
+    /// IP address to listen on
+    #[clap(long)]
+    address: Option<IpAddr>,
+    /// Port to listen on
+    #[clap(long)]
+    port: Option<u16>,
+    /// Port to serve Prometheus metrics on, at /metrics
+    #[clap(long)]
+    metrics_port: Option<u16>,
+    /// Comma separated list of symbols (currency pairs) to which we'll stream, e.g. ethbtc,btcusdt
+    #[clap(short = 's', value_delimiter = ',')]
+    symbols: Vec<String>,
+    /// Comma separated list of exchanges to stream from: binance, bitstamp, coinbase, kraken and okx.
+    /// Defaults to binance,bitstamp
+    #[clap(short = 'e', long, value_delimiter = ',')]
+    exchanges: Vec<Exchange>,
+    /// What happens to clients that fall behind: skip, snapshot or terminate. Defaults to skip
+    #[clap(long, value_enum)]
+    lag_policy: Option<LagPolicy>,
+    /// Stream Binance's full depth from its diff stream instead of the top 20 levels
+    #[clap(long)]
+    binance_full_depth: bool,
+    /// Stream Bitstamp's diff_order_book channel instead of full orderbook snapshots
+    #[clap(long)]
+    bitstamp_diff: bool,
+    /// Record every raw exchange frame to rotating gzip files in this directory
+    #[clap(long)]
+    record_dir: Option<PathBuf>,
+    /// Comma separated list of recordings to replay instead of streaming from the exchanges
+    #[clap(long, value_delimiter = ',')]
+    replay: Vec<PathBuf>,
+    /// Replay speed: realtime, max or a factor like 10x. Defaults to realtime
+    #[clap(long)]
+    replay_speed: Option<ReplaySpeed>,
+    /// PEM certificate chain to serve over TLS with. Requires --tls-key
+    #[clap(long)]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of --tls-cert
+    #[clap(long)]
+    tls_key: Option<PathBuf>,
+    /// PEM CA certificate that client certificates must be signed by, enabling mutual TLS
+    #[clap(long)]
+    tls_client_ca: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    /// Loads the configuration and overrides it with the flags that were given
+    fn config(self) -> Result<ServerConfig, ConfigError> {
+        let mut config = ServerConfig::load(self.config.as_deref())?;
+
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if self.metrics_port.is_some() {
+            config.metrics_port = self.metrics_port;
+        }
+        if !self.symbols.is_empty() {
+            config.symbols = self.symbols;
+        }
+        if !self.exchanges.is_empty() {
+            config.exchanges = self.exchanges;
+        }
+        if let Some(lag_policy) = self.lag_policy {
+            config.lag_policy = lag_policy;
+        }
+        config.binance.full_depth |= self.binance_full_depth;
+        config.bitstamp.diff |= self.bitstamp_diff;
+        if self.record_dir.is_some() {
+            config.recorder.dir = self.record_dir;
+        }
+        if !self.replay.is_empty() {
+            config.replay.files = self.replay;
+        }
+        if let Some(replay_speed) = self.replay_speed {
+            config.replay.speed = replay_speed;
+        }
+        if self.tls_cert.is_some() {
+            config.tls.cert = self.tls_cert;
+        }
+        if self.tls_key.is_some() {
+            config.tls.key = self.tls_key;
+        }
+        if self.tls_client_ca.is_some() {
+            config.tls.client_ca = self.tls_client_ca;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
 }
 
 #[derive(Parser)]
-pub(crate) struct ClientArgs {}
+pub(crate) struct ClientArgs {
+    /// TOML configuration file. Environment variables prefixed with ORDERBOOK_CLIENT_ and flags take precedence
+    #[clap(short = 'c', long)]
+    config: Option<PathBuf>,
+    /// IP address of the server
+    #[clap(long)]
+    address: Option<IpAddr>,
+    /// Port of the server
+    #[clap(long)]
+    port: Option<u16>,
+    /// URL of the server, e.g. http://127.0.0.1:50505 or https://example.com:50505. Takes precedence
+    /// over --address and --port
+    #[clap(short = 'u', long)]
+    url: Option<String>,
+    /// Comma separated list of symbols to receive. Defaults to every symbol the server streams
+    #[clap(short = 's', value_delimiter = ',')]
+    symbols: Vec<String>,
+    /// Levels per side of the merged orderbook. Defaults to the server's default depth
+    #[clap(short = 'd', long)]
+    depth: Option<u32>,
+    /// Comma separated list of exchanges to merge. Defaults to every exchange the server streams
+    #[clap(short = 'e', long, value_delimiter = ',')]
+    exchanges: Vec<Exchange>,
+    /// Minimum milliseconds between summaries. Defaults to streaming every update
+    #[clap(long)]
+    throttle_ms: Option<u32>,
+    /// Output format of the summaries. Defaults to table
+    #[clap(short = 'o', long, value_enum)]
+    output: Option<OutputFormat>,
+    /// Stop after receiving this many summaries
+    #[clap(short = 'n', long)]
+    count: Option<u64>,
+    /// Stop after this many seconds
+    #[clap(long)]
+    duration_secs: Option<u64>,
+    /// Full screen live ladder instead of writing summaries to stdout
+    #[clap(long)]
+    tui: bool,
+    /// PEM CA certificate to verify the server with over TLS. Defaults to the system's roots
+    #[clap(long)]
+    tls_ca: Option<PathBuf>,
+    /// PEM client certificate chain for servers that require mutual TLS. Requires --tls-key
+    #[clap(long)]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of --tls-cert
+    #[clap(long)]
+    tls_key: Option<PathBuf>,
+    /// Name to verify the server certificate against. Defaults to the host of the URL
+    #[clap(long)]
+    tls_domain: Option<String>,
+}
+
+impl ClientArgs {
+    /// Loads the configuration and overrides it with the flags that were given
+    fn config(self) -> Result<ClientConfig, ConfigError> {
+        let mut config = ClientConfig::load(self.config.as_deref())?;
+
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if !self.symbols.is_empty() {
+            config.symbols = self.symbols;
+        }
+        if let Some(depth) = self.depth {
+            config.depth = depth;
+        }
+        if !self.exchanges.is_empty() {
+            config.exchanges = self.exchanges;
+        }
+        if let Some(throttle_ms) = self.throttle_ms {
+            config.throttle_ms = throttle_ms;
+        }
+        if self.url.is_some() {
+            config.url = self.url;
+        }
+        if let Some(output) = self.output {
+            config.output = output;
+        }
+        if let Some(count) = self.count {
+            config.count = count;
+        }
+        if let Some(duration_secs) = self.duration_secs {
+            config.duration_secs = duration_secs;
+        }
+        if self.tls_ca.is_some() {
+            config.tls.ca = self.tls_ca;
+        }
+        if self.tls_cert.is_some() {
+            config.tls.cert = self.tls_cert;
+        }
+        if self.tls_key.is_some() {
+            config.tls.key = self.tls_key;
+        }
+        if self.tls_domain.is_some() {
+            config.tls.domain = self.tls_domain;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -39,12 +246,19 @@ async fn main() -> Result<()> {
 
     match opts.subcmd {
         SubCommand::Server(args) => {
-            grpc_server::serve(args.symbol)
+            let config = args.config()?;
+            grpc_server::serve(config)
                 .await
                 .expect("Failed to run gRPC server");
         }
-        SubCommand::Client(_args) => {
-            grpc_client::listen().await?;
+        SubCommand::Client(args) => {
+            let full_screen = args.tui;
+            let config = args.config()?;
+            if full_screen {
+                tui::run(config).await?;
+            } else {
+                grpc_client::listen(config).await?;
+            }
         }
     }
 
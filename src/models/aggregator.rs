@@ -0,0 +1,260 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::server::grpc_server::orderbook::{self, ExchangeStatus, Level, Summary};
+
+use super::{
+    consts::MAX_PAIR_EXCHANGE,
+    mapper::{Exchange, OfferData},
+    messages::{ConnectionStatus, Orders},
+};
+
+/// Keeps the latest orderbook of each exchange and merges them into a single price-sorted ladder
+#[derive(Debug)]
+pub struct Aggregator {
+    /// Sorted top asks and bids of the latest orderbook per exchange
+    books: HashMap<Exchange, (Vec<Level>, Vec<Level>)>,
+    /// Latest known connection status per exchange
+    statuses: HashMap<Exchange, ConnectionStatus>,
+    /// When each exchange last sent an orderbook
+    last_updates: HashMap<Exchange, Instant>,
+    /// When each exchange last connected, which counts as an update until it sends an orderbook
+    connected_at: HashMap<Exchange, Instant>,
+    /// Levels per side of the merged ladder
+    depth: usize,
+    /// Exchanges to merge. Every exchange is merged when None
+    exchanges: Option<HashSet<Exchange>>,
+    /// Time without an orderbook after which a connected exchange is stale. Never when None
+    stale_after: Option<Duration>,
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Aggregator::with_view(MAX_PAIR_EXCHANGE, None)
+    }
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Aggregator::default()
+    }
+
+    /// Aggregator that merges the top `depth` levels of `exchanges` only, or of every exchange
+    /// when None
+    pub fn with_view(depth: usize, exchanges: Option<HashSet<Exchange>>) -> Self {
+        Aggregator {
+            books: HashMap::new(),
+            statuses: HashMap::new(),
+            last_updates: HashMap::new(),
+            connected_at: HashMap::new(),
+            depth,
+            exchanges,
+            stale_after: None,
+        }
+    }
+
+    /// Leaves out exchanges that sent no orderbook for `stale_after`, flagged as stale
+    pub fn with_stale_after(mut self, stale_after: Option<Duration>) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    /// Whether the client asked for the orderbooks of `exchange`
+    fn is_selected(&self, exchange: &Exchange) -> bool {
+        self.exchanges
+            .as_ref()
+            .is_none_or(|exchanges| exchanges.contains(exchange))
+    }
+
+    /// Replaces the book of the exchange that sent `orders`
+    pub fn update(&mut self, mut orders: Orders) {
+        if !self.is_selected(&orders.exchange) {
+            return;
+        }
+
+        self.statuses
+            .insert(orders.exchange, ConnectionStatus::Connected);
+        self.last_updates.insert(
+            orders.exchange,
+            orders.received_at.unwrap_or_else(Instant::now),
+        );
+        let levels = Aggregator::sort_and_convert(
+            &mut orders.asks,
+            &mut orders.bids,
+            &orders.exchange,
+            self.depth,
+        );
+        self.books.insert(orders.exchange, levels);
+    }
+
+    /// Records the connection status of `exchange`. The levels of a disconnected exchange are
+    /// dropped from the merged view since we can no longer tell whether they're still valid.
+    pub fn set_status(&mut self, exchange: Exchange, status: ConnectionStatus) {
+        if !self.is_selected(&exchange) {
+            return;
+        }
+
+        match status {
+            ConnectionStatus::Connected => {
+                self.connected_at.insert(exchange, Instant::now());
+            }
+            ConnectionStatus::Disconnected => {
+                self.books.remove(&exchange);
+            }
+        }
+        self.statuses.insert(exchange, status);
+    }
+
+    /// Connected exchanges that sent no orderbook within `stale_after` as of `now`
+    pub fn stale_exchanges(&self, now: Instant) -> HashSet<Exchange> {
+        let stale_after = match self.stale_after {
+            Some(stale_after) => stale_after,
+            None => return HashSet::new(),
+        };
+
+        self.statuses
+            .iter()
+            .filter(|(_, status)| **status == ConnectionStatus::Connected)
+            .filter_map(|(exchange, _)| {
+                let heard_at = self
+                    .last_updates
+                    .get(exchange)
+                    .or_else(|| self.connected_at.get(exchange))?;
+                (now.saturating_duration_since(*heard_at) > stale_after).then_some(*exchange)
+            })
+            .collect()
+    }
+
+    /// Merges the books of all fresh exchanges into a top `depth` ladder with the spread across venues
+    pub fn summary(&self) -> Summary {
+        let now = Instant::now();
+        let stale = self.stale_exchanges(now);
+        let mut asks = Vec::new();
+        let mut bids = Vec::new();
+
+        for (exchange, (book_asks, book_bids)) in &self.books {
+            if stale.contains(exchange) {
+                continue;
+            }
+            asks.extend_from_slice(book_asks);
+            bids.extend_from_slice(book_bids);
+        }
+
+        // Sorted on the exact decimals, since doubles may not tell close prices apart, then largest amount and exchange
+        asks.sort_by_cached_key(|level| {
+            (
+                Aggregator::decimal(&level.price_decimal),
+                Reverse(Aggregator::decimal(&level.amount_decimal)),
+                level.exchange.clone(),
+            )
+        });
+        bids.sort_by_cached_key(|level| {
+            (
+                Reverse(Aggregator::decimal(&level.price_decimal)),
+                Reverse(Aggregator::decimal(&level.amount_decimal)),
+                level.exchange.clone(),
+            )
+        });
+        asks.truncate(self.depth);
+        bids.truncate(self.depth);
+
+        let spread = Aggregator::spread(&asks, &bids);
+
+        Summary {
+            spread: spread.to_f64().unwrap_or_default(),
+            spread_decimal: spread.to_string(),
+            bids,
+            asks,
+            exchanges: self.exchange_statuses(now, &stale),
+            // Set by the broadcast handle, which knows what symbol the aggregator is for
+            symbol: String::new(),
+            lag: None,
+            // Stamped by the broadcast handle as the Summary is sent
+            sequence: 0,
+            server_timestamp_us: 0,
+            source: None,
+        }
+    }
+
+    /// Connection status and freshness of every exchange we've heard from, sorted by name
+    fn exchange_statuses(&self, now: Instant, stale: &HashSet<Exchange>) -> Vec<ExchangeStatus> {
+        let mut exchanges: Vec<ExchangeStatus> = self
+            .statuses
+            .iter()
+            .map(|(exchange, status)| {
+                let status = match status {
+                    ConnectionStatus::Connected if stale.contains(exchange) => {
+                        orderbook::ConnectionStatus::Stale
+                    }
+                    ConnectionStatus::Connected => orderbook::ConnectionStatus::Connected,
+                    ConnectionStatus::Disconnected => orderbook::ConnectionStatus::Disconnected,
+                };
+
+                ExchangeStatus {
+                    exchange: exchange.to_string(),
+                    status: status as i32,
+                    last_update_age_ms: self.last_updates.get(exchange).map(|updated_at| {
+                        now.saturating_duration_since(*updated_at).as_millis() as u64
+                    }),
+                }
+            })
+            .collect();
+        exchanges.sort_by(|status_l, status_r| status_l.exchange.cmp(&status_r.exchange));
+
+        exchanges
+    }
+
+    /// Best ask minus best bid. If one of the sides is still empty there's no spread to report
+    fn spread(asks: &[Level], bids: &[Level]) -> Decimal {
+        match (asks.first(), bids.first()) {
+            (Some(best_ask), Some(best_bid)) => {
+                Aggregator::decimal(&best_ask.price_decimal)
+                    - Aggregator::decimal(&best_bid.price_decimal)
+            }
+            _ => Decimal::ZERO,
+        }
+    }
+
+    /// Helper to sort the asks based on price and convert the top `depth` of them to a Vec of Levels to be send to a client
+    pub(crate) fn sort_and_convert(
+        asks: &mut [OfferData],
+        bids: &mut [OfferData],
+        exchange: &Exchange,
+        depth: usize,
+    ) -> (Vec<Level>, Vec<Level>) {
+        asks.sort_by_key(|order| order.price);
+        bids.sort_by_key(|order| Reverse(order.price));
+
+        let converted_asks = Aggregator::convert_to_levels(asks, exchange, depth);
+        let converted_bids = Aggregator::convert_to_levels(bids, exchange, depth);
+
+        (converted_asks, converted_bids)
+    }
+
+    /// Helper to convert asks and prices to Level Struct to be sent via gRPC
+    fn convert_to_levels(
+        securities: &mut [OfferData],
+        exchange: &Exchange,
+        depth: usize,
+    ) -> Vec<Level> {
+        securities
+            .iter_mut()
+            .take(depth)
+            .map(|bid| Level {
+                amount: bid.quantity.to_f64().unwrap_or_default(),
+                exchange: exchange.to_string(),
+                price: bid.price.to_f64().unwrap_or_default(),
+                price_decimal: bid.price.to_string(),
+                amount_decimal: bid.quantity.to_string(),
+            })
+            .collect()
+    }
+
+    /// Helper to read back the exact decimals of a Level, which we always fill in ourselves
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap_or_default()
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::server::grpc_server::orderbook::{ArbitrageEvent, ArbitrageState};
+
+use super::{
+    mapper::{Exchange, OfferData},
+    messages::{ConnectionStatus, OrderbookMessage},
+};
+
+/// Best levels of the latest book of an exchange
+#[derive(Debug, Clone)]
+struct Top {
+    bid: OfferData,
+    ask: OfferData,
+    received_at: Instant,
+}
+
+/// Opportunity that's currently above the threshold
+#[derive(Debug)]
+struct Opportunity {
+    opened_at: Instant,
+    /// Latest event sent for it, which is sent again as CLOSED once it's over
+    event: ArbitrageEvent,
+}
+
+/// Stateful detector of the opportunities to buy a symbol on one exchange and sell it on another
+#[derive(Debug)]
+pub struct ArbitrageDetector {
+    symbol: String,
+    /// Minimum gross edge in basis points of the buy price. Any positive edge counts when zero
+    min_edge_bps: Decimal,
+    /// Top of the book per exchange, dropped once the exchange disconnects
+    tops: HashMap<Exchange, Top>,
+    /// Open opportunities by buy and sell exchange
+    open: HashMap<(Exchange, Exchange), Opportunity>,
+    /// Time without an orderbook after which the top of an exchange is left out. Never when None
+    stale_after: Option<Duration>,
+}
+
+impl ArbitrageDetector {
+    pub fn new(symbol: String, min_edge_bps: Decimal) -> Self {
+        ArbitrageDetector {
+            symbol,
+            min_edge_bps,
+            tops: HashMap::new(),
+            open: HashMap::new(),
+            stale_after: None,
+        }
+    }
+
+    /// Leaves out the tops of exchanges that sent no orderbook for `stale_after`
+    pub fn with_stale_after(mut self, stale_after: Option<Duration>) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    pub fn stale_after(&self) -> Option<Duration> {
+        self.stale_after
+    }
+
+    /// Updates the top of the book of the exchange in `msg` and returns how the opportunities
+    /// changed, sorted by buy and sell exchange
+    pub fn apply(&mut self, msg: &OrderbookMessage) -> Vec<ArbitrageEvent> {
+        let now = match msg {
+            OrderbookMessage::Message { message } => {
+                let received_at = message.received_at.unwrap_or_else(Instant::now);
+                // Books are validated before they're broadcast, so neither side is empty
+                let bid = message.bids.iter().max_by_key(|level| level.price);
+                let ask = message.asks.iter().min_by_key(|level| level.price);
+                if let (Some(bid), Some(ask)) = (bid, ask) {
+                    let top = Top {
+                        bid: bid.clone(),
+                        ask: ask.clone(),
+                        received_at,
+                    };
+                    self.tops.insert(message.exchange, top);
+                }
+                received_at
+            }
+            OrderbookMessage::Status { exchange, status } => {
+                if *status == ConnectionStatus::Disconnected {
+                    self.tops.remove(exchange);
+                }
+                Instant::now()
+            }
+        };
+
+        self.refresh(now)
+    }
+
+    /// Re-evaluates the opportunities as of `now`, leaving out stale exchanges, and returns how
+    /// they changed, sorted by buy and sell exchange
+    pub fn refresh(&mut self, now: Instant) -> Vec<ArbitrageEvent> {
+        let fresh: Vec<(&Exchange, &Top)> = self
+            .tops
+            .iter()
+            .filter(|(_, top)| match self.stale_after {
+                Some(stale_after) => now.saturating_duration_since(top.received_at) <= stale_after,
+                None => true,
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        let mut current = Vec::new();
+        for (buy_exchange, buy) in &fresh {
+            for (sell_exchange, sell) in &fresh {
+                if buy_exchange == sell_exchange {
+                    continue;
+                }
+                let event =
+                    match self.opportunity(**buy_exchange, &buy.ask, **sell_exchange, &sell.bid) {
+                        Some(event) => event,
+                        None => continue,
+                    };
+                current.push((**buy_exchange, **sell_exchange));
+
+                match self.open.get_mut(&(**buy_exchange, **sell_exchange)) {
+                    Some(open) => {
+                        if !ArbitrageDetector::same_values(&open.event, &event) {
+                            open.event = ArbitrageEvent {
+                                state: ArbitrageState::Updated as i32,
+                                duration_ms: ArbitrageDetector::millis(open.opened_at, now),
+                                ..event
+                            };
+                            events.push(open.event.clone());
+                        }
+                    }
+                    None => {
+                        events.push(event.clone());
+                        self.open.insert(
+                            (**buy_exchange, **sell_exchange),
+                            Opportunity {
+                                opened_at: now,
+                                event,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        let closed: Vec<(Exchange, Exchange)> = self
+            .open
+            .keys()
+            .filter(|pair| !current.contains(pair))
+            .copied()
+            .collect();
+        for pair in closed {
+            let open = self.open.remove(&pair).unwrap();
+            events.push(ArbitrageEvent {
+                state: ArbitrageState::Closed as i32,
+                duration_ms: ArbitrageDetector::millis(open.opened_at, now),
+                ..open.event
+            });
+        }
+
+        events.sort_by(|a, b| {
+            (&a.buy_exchange, &a.sell_exchange).cmp(&(&b.buy_exchange, &b.sell_exchange))
+        });
+        events
+    }
+
+    /// Helper to build the OPENED event of buying at `ask` of `buy_exchange` and selling at `bid`
+    /// of `sell_exchange`, if the edge is above the threshold
+    fn opportunity(
+        &self,
+        buy_exchange: Exchange,
+        ask: &OfferData,
+        sell_exchange: Exchange,
+        bid: &OfferData,
+    ) -> Option<ArbitrageEvent> {
+        let edge = bid.price - ask.price;
+        if edge <= Decimal::ZERO {
+            return None;
+        }
+        let edge_bps = edge * Decimal::from(10_000) / ask.price;
+        if edge_bps < self.min_edge_bps {
+            return None;
+        }
+        let size = ask.quantity.min(bid.quantity);
+
+        Some(ArbitrageEvent {
+            symbol: self.symbol.clone(),
+            state: ArbitrageState::Opened as i32,
+            buy_exchange: buy_exchange.to_string(),
+            buy_price: ask.price.normalize().to_string(),
+            sell_exchange: sell_exchange.to_string(),
+            sell_price: bid.price.normalize().to_string(),
+            size: size.normalize().to_string(),
+            edge: edge.normalize().to_string(),
+            edge_bps: edge_bps.to_f64().unwrap_or_default(),
+            gross_profit: (edge * size).normalize().to_string(),
+            duration_ms: 0,
+        })
+    }
+
+    /// Helper to tell whether two events of the same opportunity carry the same prices and size
+    fn same_values(a: &ArbitrageEvent, b: &ArbitrageEvent) -> bool {
+        a.buy_price == b.buy_price && a.sell_price == b.sell_price && a.size == b.size
+    }
+
+    /// Helper to get the milliseconds from `since` to `now`
+    fn millis(since: Instant, now: Instant) -> u64 {
+        now.saturating_duration_since(since).as_millis() as u64
+    }
+}
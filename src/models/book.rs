@@ -0,0 +1,77 @@
+use super::{mapper::Exchange, mapper::OfferData, messages::Orders};
+
+/// Side of the orderbook an update applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+/// Orderbook maintained locally for exchanges that send incremental updates on top of a snapshot.
+/// Asks are kept sorted by ascending price and bids by descending price so the best levels come first.
+#[derive(Debug, Default, Clone)]
+pub struct LocalBook {
+    asks: Vec<OfferData>,
+    bids: Vec<OfferData>,
+}
+
+impl LocalBook {
+    /// Replaces the whole book with a snapshot
+    pub fn reset(&mut self, asks: Vec<OfferData>, bids: Vec<OfferData>) {
+        self.asks.clear();
+        self.bids.clear();
+
+        for offer in asks {
+            self.apply(Side::Ask, offer);
+        }
+        for offer in bids {
+            self.apply(Side::Bid, offer);
+        }
+    }
+
+    /// Applies a single price level update. A zero quantity removes the level.
+    pub fn apply(&mut self, side: Side, offer: OfferData) {
+        let levels = match side {
+            Side::Ask => &mut self.asks,
+            Side::Bid => &mut self.bids,
+        };
+
+        let position = levels.binary_search_by(|level| match side {
+            Side::Ask => level.price.cmp(&offer.price),
+            Side::Bid => offer.price.cmp(&level.price),
+        });
+
+        match (position, offer.quantity.is_zero()) {
+            (Ok(index), true) => {
+                levels.remove(index);
+            }
+            (Ok(index), false) => levels[index] = offer,
+            (Err(_), true) => {}
+            (Err(index), false) => levels.insert(index, offer),
+        }
+    }
+
+    /// Drops every level past `depth` on both sides
+    pub fn truncate(&mut self, depth: usize) {
+        self.asks.truncate(depth);
+        self.bids.truncate(depth);
+    }
+
+    /// Whether we received a snapshot yet
+    pub fn is_empty(&self) -> bool {
+        self.asks.is_empty() && self.bids.is_empty()
+    }
+
+    /// Top `depth` levels of each side as the Orders of `symbol` to be sent to the aggregator
+    pub fn to_orders(&self, exchange: Exchange, symbol: String, depth: usize) -> Orders {
+        Orders {
+            exchange,
+            symbol,
+            asks: self.asks.iter().take(depth).cloned().collect(),
+            bids: self.bids.iter().take(depth).cloned().collect(),
+            received_at: None,
+            event_time_us: None,
+            update_id: None,
+        }
+    }
+}
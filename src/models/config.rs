@@ -0,0 +1,666 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use url::Url;
+
+use crate::client::output::OutputFormat;
+
+use super::{
+    consts::{
+        BACKOFF_INITIAL_MS, BACKOFF_MAX_MS, BINANCE_REST_API, BINANCE_WS_API, BITSTAMP_REST_API,
+        BITSTAMP_WS_API, CHANNEL_BUFFER_LIMIT, CLIENT_BUFFER_LIMIT, CLIENT_ENV_PREFIX,
+        COINBASE_WS_API, DEPTH_LEVEL_BINANCE, DEPTH_LEVEL_KRAKEN, ERR_COUNT_LOG,
+        HEARTBEAT_SECS_OKX, IP_ADDRESS, KRAKEN_WS_API, MAX_BOOK_DEPTH, MAX_PAIR_EXCHANGE,
+        OKX_WS_API, QUOTE_ASSETS, RECORDER_FLUSH_SECS, RECORDER_MAX_FILE_BYTES,
+        RECORDER_ROTATE_SECS, SERVER_ENV_PREFIX, SERVER_PORT, SHUTDOWN_TIMEOUT_MS,
+        SNAPSHOT_LIMIT_BINANCE, STALE_FEED_MS, UPDATE_SPEED_BINANCE,
+    },
+    errors::ConfigError,
+    mapper::Exchange,
+    replay::ReplaySpeed,
+    stream_service::LagPolicy,
+};
+
+/// Server configuration. Every value defaults to its constant in `consts`, see the README for how
+/// files, environment variables and flags are layered on top
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// IP address the gRPC server listens on
+    pub address: IpAddr,
+    /// Port the gRPC server listens on
+    pub port: u16,
+    /// Port the Prometheus `/metrics` endpoint listens on, on the same address. Disabled when None
+    pub metrics_port: Option<u16>,
+    /// Symbols (currency pairs) to stream, e.g. ["ethbtc", "btcusdt"]
+    #[serde(deserialize_with = "de_list")]
+    pub symbols: Vec<String>,
+    /// Exchanges to stream from
+    #[serde(deserialize_with = "de_list")]
+    pub exchanges: Vec<Exchange>,
+    /// Buffer limit of the broadcast channel of each symbol
+    pub channel_buffer_limit: usize,
+    /// Buffer limit of the channel of summaries sent to each client
+    pub client_buffer_limit: usize,
+    /// What happens to clients that fall behind the `channel_buffer_limit` updates of a symbol:
+    /// "skip", "snapshot" or "terminate"
+    pub lag_policy: LagPolicy,
+    /// Levels per side sent to clients that don't ask for a depth
+    pub default_depth: usize,
+    /// Limit of levels per side kept out of local books, and so the largest depth clients can ask for
+    pub max_book_depth: usize,
+    /// Broadcast error count limit before we display a warning
+    pub err_count_log: i32,
+    /// Initial delay before reconnecting to an exchange after its feed goes down
+    pub backoff_initial_ms: u64,
+    /// Maximum delay between reconnection attempts to an exchange
+    pub backoff_max_ms: u64,
+    /// Time without an orderbook after which an exchange feed is stale
+    pub stale_feed_ms: u64,
+    /// Time given to end client streams, close exchange connections and complete recordings on
+    /// SIGINT or SIGTERM, before exiting anyway
+    pub shutdown_timeout_ms: u64,
+    /// Quote assets used to split symbols like "ethbtc" into base and quote
+    #[serde(deserialize_with = "de_list")]
+    pub quote_assets: Vec<String>,
+    pub binance: BinanceConfig,
+    pub bitstamp: BitstampConfig,
+    pub coinbase: CoinbaseConfig,
+    pub kraken: KrakenConfig,
+    pub okx: OkxConfig,
+    pub recorder: RecorderConfig,
+    pub replay: ReplayConfig,
+    pub tls: ServerTlsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinanceConfig {
+    /// Web Socket URL endpoint
+    pub ws_api: String,
+    /// REST API endpoint used to fetch depth snapshots
+    pub rest_api: String,
+    /// Keep a full depth book out of the diff stream instead of streaming the top levels
+    pub full_depth: bool,
+    /// Partial depth stream, one of depth5, depth10 or depth20
+    pub depth_level: String,
+    /// Stream speed, either 100ms or 1000ms
+    pub update_speed: String,
+    /// Number of levels per side requested in depth snapshots
+    pub snapshot_limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BitstampConfig {
+    /// Web Socket URL endpoint
+    pub ws_api: String,
+    /// REST API endpoint used to fetch orderbook snapshots
+    pub rest_api: String,
+    /// Keep a book out of the diff_order_book channel instead of streaming full snapshots
+    pub diff: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoinbaseConfig {
+    /// Web Socket URL endpoint
+    pub ws_api: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KrakenConfig {
+    /// Web Socket URL endpoint
+    pub ws_api: String,
+    /// Book depth, one of 10, 25, 100, 500 or 1000
+    pub depth_level: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OkxConfig {
+    /// Web Socket URL endpoint
+    pub ws_api: String,
+    /// Seconds between heartbeats. OKX closes connections that stay quiet for 30 seconds
+    pub heartbeat_secs: u64,
+}
+
+/// Recording of the raw exchange frames, see `Recorder` for the format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    /// Directory the recordings are written to. Nothing is recorded when None
+    pub dir: Option<PathBuf>,
+    /// Seconds after which a new file is started
+    pub rotate_secs: u64,
+    /// Uncompressed bytes after which a new file is started
+    pub max_file_bytes: u64,
+    /// Seconds between flushes to disk
+    pub flush_secs: u64,
+}
+
+/// Replay of recordings instead of streaming from the exchanges
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayConfig {
+    /// Recordings to replay, merged by the time their frames were received. Streams live when empty
+    #[serde(deserialize_with = "de_list")]
+    pub files: Vec<PathBuf>,
+    /// Either "realtime", "max" or an acceleration factor like "10x"
+    pub speed: ReplaySpeed,
+    /// Hold the replay until a client subscribes so that it gets every frame
+    pub wait_for_clients: bool,
+}
+
+/// TLS of the gRPC server. Clients are served in plaintext unless a certificate is given
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerTlsConfig {
+    /// PEM certificate chain of the server
+    pub cert: Option<PathBuf>,
+    /// PEM private key of the server certificate
+    pub key: Option<PathBuf>,
+    /// PEM certificate of the CA that signs client certificates. When set, clients are required to
+    /// present a certificate signed by it (mutual TLS)
+    pub client_ca: Option<PathBuf>,
+}
+
+/// TLS of the connection to the gRPC server, used with "https" URLs. Certificates are verified
+/// against the system's roots unless a CA is given
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientTlsConfig {
+    /// PEM certificate of the CA that signs the server certificate
+    pub ca: Option<PathBuf>,
+    /// PEM certificate chain presented to servers that require client certificates
+    pub cert: Option<PathBuf>,
+    /// PEM private key of the client certificate
+    pub key: Option<PathBuf>,
+    /// Name the server certificate is verified against. The host of the URL when None
+    pub domain: Option<String>,
+}
+
+/// Client configuration, layered like the server's with environment variables prefixed with
+/// `ORDERBOOK_CLIENT_`, e.g. `ORDERBOOK_CLIENT_DEPTH=20`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// IP address of the gRPC server
+    pub address: IpAddr,
+    /// Port of the gRPC server
+    pub port: u16,
+    /// URL of the gRPC server, e.g. "http://127.0.0.1:50505". Takes precedence over `address` and `port`
+    pub url: Option<String>,
+    /// Symbols to receive. Every symbol the server streams when empty
+    #[serde(deserialize_with = "de_list")]
+    pub symbols: Vec<String>,
+    /// Levels per side of the merged orderbook. The server's default when 0
+    pub depth: u32,
+    /// Exchanges to merge. Every exchange the server streams when empty
+    #[serde(deserialize_with = "de_list")]
+    pub exchanges: Vec<Exchange>,
+    /// Minimum milliseconds between summaries. Every update when 0
+    pub throttle_ms: u32,
+    /// How summaries are written to stdout
+    pub output: OutputFormat,
+    /// Stop after receiving this many summaries. Never stops when 0
+    pub count: u64,
+    /// Stop after this many seconds. Never stops when 0
+    pub duration_secs: u64,
+    pub tls: ClientTlsConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: IP_ADDRESS,
+            port: SERVER_PORT,
+            metrics_port: None,
+            symbols: Vec::new(),
+            exchanges: vec![Exchange::Binance, Exchange::Bitstamp],
+            channel_buffer_limit: CHANNEL_BUFFER_LIMIT,
+            client_buffer_limit: CLIENT_BUFFER_LIMIT,
+            lag_policy: LagPolicy::default(),
+            default_depth: MAX_PAIR_EXCHANGE,
+            max_book_depth: MAX_BOOK_DEPTH,
+            err_count_log: ERR_COUNT_LOG,
+            backoff_initial_ms: BACKOFF_INITIAL_MS,
+            backoff_max_ms: BACKOFF_MAX_MS,
+            stale_feed_ms: STALE_FEED_MS,
+            shutdown_timeout_ms: SHUTDOWN_TIMEOUT_MS,
+            quote_assets: QUOTE_ASSETS.iter().map(|quote| quote.to_string()).collect(),
+            binance: BinanceConfig::default(),
+            bitstamp: BitstampConfig::default(),
+            coinbase: CoinbaseConfig::default(),
+            kraken: KrakenConfig::default(),
+            okx: OkxConfig::default(),
+            recorder: RecorderConfig::default(),
+            replay: ReplayConfig::default(),
+            tls: ServerTlsConfig::default(),
+        }
+    }
+}
+
+impl Default for BinanceConfig {
+    fn default() -> Self {
+        BinanceConfig {
+            ws_api: BINANCE_WS_API.to_string(),
+            rest_api: BINANCE_REST_API.to_string(),
+            full_depth: false,
+            depth_level: DEPTH_LEVEL_BINANCE.to_string(),
+            update_speed: UPDATE_SPEED_BINANCE.to_string(),
+            snapshot_limit: SNAPSHOT_LIMIT_BINANCE,
+        }
+    }
+}
+
+impl Default for BitstampConfig {
+    fn default() -> Self {
+        BitstampConfig {
+            ws_api: BITSTAMP_WS_API.to_string(),
+            rest_api: BITSTAMP_REST_API.to_string(),
+            diff: false,
+        }
+    }
+}
+
+impl Default for CoinbaseConfig {
+    fn default() -> Self {
+        CoinbaseConfig {
+            ws_api: COINBASE_WS_API.to_string(),
+        }
+    }
+}
+
+impl Default for KrakenConfig {
+    fn default() -> Self {
+        KrakenConfig {
+            ws_api: KRAKEN_WS_API.to_string(),
+            depth_level: DEPTH_LEVEL_KRAKEN,
+        }
+    }
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        RecorderConfig {
+            dir: None,
+            rotate_secs: RECORDER_ROTATE_SECS,
+            max_file_bytes: RECORDER_MAX_FILE_BYTES,
+            flush_secs: RECORDER_FLUSH_SECS,
+        }
+    }
+}
+
+impl Default for OkxConfig {
+    fn default() -> Self {
+        OkxConfig {
+            ws_api: OKX_WS_API.to_string(),
+            heartbeat_secs: HEARTBEAT_SECS_OKX,
+        }
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            address: IP_ADDRESS,
+            port: SERVER_PORT,
+            url: None,
+            symbols: Vec::new(),
+            depth: 0,
+            exchanges: Vec::new(),
+            throttle_ms: 0,
+            output: OutputFormat::default(),
+            count: 0,
+            duration_secs: 0,
+            tls: ClientTlsConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Address the gRPC server listens on, e.g. "[::1]:50505"
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+
+    /// Loads the defaults, the TOML file at `path` and the environment variables, in that order.
+    /// Command line flags are applied by the caller, which then has to `validate` the result
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+
+        let env = Env::prefixed(SERVER_ENV_PREFIX)
+            // ORDERBOOK_SYMBOL used to hold the single symbol we streamed
+            .map(|key| {
+                if key == "symbol" {
+                    "symbols".into()
+                } else {
+                    key.into()
+                }
+            })
+            .split("__");
+
+        load(ServerConfig::default(), path, env)
+    }
+
+    /// Checks that every value makes sense so that we fail at startup instead of once we connect
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check("port", self.port > 0, "must be larger than 0")?;
+        if let Some(metrics_port) = self.metrics_port {
+            check(
+                "metrics_port",
+                metrics_port > 0 && metrics_port != self.port,
+                "must be larger than 0 and differ from port",
+            )?;
+        }
+        check(
+            "symbols",
+            !self.symbols.is_empty(),
+            "at least one symbol is required. Use -s, ORDERBOOK_SYMBOL or the configuration file",
+        )?;
+        check(
+            "exchanges",
+            !self.exchanges.is_empty(),
+            "at least one exchange is required",
+        )?;
+        check(
+            "channel_buffer_limit",
+            self.channel_buffer_limit > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "client_buffer_limit",
+            self.client_buffer_limit > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "max_book_depth",
+            self.max_book_depth > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "default_depth",
+            (1..=self.max_book_depth).contains(&self.default_depth),
+            format!(
+                "must be between 1 and max_book_depth ({})",
+                self.max_book_depth
+            ),
+        )?;
+        check(
+            "backoff_initial_ms",
+            self.backoff_initial_ms > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "backoff_max_ms",
+            self.backoff_max_ms >= self.backoff_initial_ms,
+            format!(
+                "must be at least backoff_initial_ms ({})",
+                self.backoff_initial_ms
+            ),
+        )?;
+        check(
+            "stale_feed_ms",
+            self.stale_feed_ms > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "shutdown_timeout_ms",
+            self.shutdown_timeout_ms > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "quote_assets",
+            !self.quote_assets.is_empty(),
+            "at least one quote asset is required",
+        )?;
+
+        validate_url("binance.ws_api", &self.binance.ws_api, &["ws", "wss"])?;
+        validate_url(
+            "binance.rest_api",
+            &self.binance.rest_api,
+            &["http", "https"],
+        )?;
+        check(
+            "binance.depth_level",
+            ["depth5", "depth10", "depth20"].contains(&self.binance.depth_level.as_str()),
+            "must be one of depth5, depth10 or depth20",
+        )?;
+        check(
+            "binance.update_speed",
+            ["100ms", "1000ms"].contains(&self.binance.update_speed.as_str()),
+            "must be either 100ms or 1000ms",
+        )?;
+        check(
+            "binance.snapshot_limit",
+            (1..=5000).contains(&self.binance.snapshot_limit),
+            "must be between 1 and 5000",
+        )?;
+        validate_url("bitstamp.ws_api", &self.bitstamp.ws_api, &["ws", "wss"])?;
+        validate_url(
+            "bitstamp.rest_api",
+            &self.bitstamp.rest_api,
+            &["http", "https"],
+        )?;
+        validate_url("coinbase.ws_api", &self.coinbase.ws_api, &["ws", "wss"])?;
+        validate_url("kraken.ws_api", &self.kraken.ws_api, &["ws", "wss"])?;
+        check(
+            "kraken.depth_level",
+            [10, 25, 100, 500, 1000].contains(&self.kraken.depth_level),
+            "must be one of 10, 25, 100, 500 or 1000",
+        )?;
+        validate_url("okx.ws_api", &self.okx.ws_api, &["ws", "wss"])?;
+        check(
+            "okx.heartbeat_secs",
+            (1..30).contains(&self.okx.heartbeat_secs),
+            "must be between 1 and 29 since OKX closes quiet connections after 30 seconds",
+        )?;
+        check(
+            "recorder.rotate_secs",
+            self.recorder.rotate_secs > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "recorder.max_file_bytes",
+            self.recorder.max_file_bytes > 0,
+            "must be larger than 0",
+        )?;
+        check(
+            "recorder.flush_secs",
+            self.recorder.flush_secs > 0,
+            "must be larger than 0",
+        )?;
+        if let Some(file) = self.replay.files.iter().find(|file| !file.is_file()) {
+            return Err(ConfigError::Invalid {
+                key: "replay.files",
+                reason: format!("{} is not a file", file.display()),
+            });
+        }
+        // Recordings only hold the Web Socket frames, not the REST snapshots these modes start from
+        check(
+            "replay.files",
+            self.replay.files.is_empty() || !(self.binance.full_depth || self.bitstamp.diff),
+            "can't replay with binance.full_depth or bitstamp.diff enabled",
+        )?;
+        check(
+            "tls.key",
+            self.tls.cert.is_some() == self.tls.key.is_some(),
+            "tls.cert and tls.key must be set together",
+        )?;
+        check(
+            "tls.client_ca",
+            self.tls.client_ca.is_none() || self.tls.cert.is_some(),
+            "requires tls.cert and tls.key",
+        )?;
+        validate_files(&[
+            ("tls.cert", &self.tls.cert),
+            ("tls.key", &self.tls.key),
+            ("tls.client_ca", &self.tls.client_ca),
+        ])?;
+
+        Ok(())
+    }
+}
+
+impl ClientConfig {
+    /// Loads the defaults, the TOML file at `path` and the environment variables, in that order.
+    /// Command line flags are applied by the caller, which then has to `validate` the result
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+
+        load(
+            ClientConfig::default(),
+            path,
+            Env::prefixed(CLIENT_ENV_PREFIX),
+        )
+    }
+
+    /// Checks that every value makes sense so that we fail before connecting to the server
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check("port", self.port > 0, "must be larger than 0")?;
+        if let Some(url) = &self.url {
+            validate_url("url", url, &["http", "https"])?;
+        }
+        check(
+            "url",
+            !self.tls.is_set() || self.server_url().starts_with("https://"),
+            "must use https when tls is set",
+        )?;
+        check(
+            "tls.key",
+            self.tls.cert.is_some() == self.tls.key.is_some(),
+            "tls.cert and tls.key must be set together",
+        )?;
+        validate_files(&[
+            ("tls.ca", &self.tls.ca),
+            ("tls.cert", &self.tls.cert),
+            ("tls.key", &self.tls.key),
+        ])?;
+
+        Ok(())
+    }
+
+    /// URL of the gRPC server, e.g. "http://[::1]:50505", or "https://[::1]:50505" when `tls` is set
+    pub fn server_url(&self) -> String {
+        let scheme = if self.tls.is_set() { "https" } else { "http" };
+        match &self.url {
+            Some(url) => url.clone(),
+            None => format!("{}://{}", scheme, SocketAddr::new(self.address, self.port)),
+        }
+    }
+}
+
+impl ClientTlsConfig {
+    /// Whether any TLS option was given
+    pub fn is_set(&self) -> bool {
+        self.ca.is_some() || self.cert.is_some() || self.key.is_some() || self.domain.is_some()
+    }
+}
+
+/// Helper to layer the TOML file at `path` and then the environment variables on top of `defaults`
+fn load<T>(defaults: T, path: Option<&Path>, env: Env) -> Result<T, ConfigError>
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let mut figment = Figment::from(Serialized::defaults(defaults));
+    if let Some(path) = path {
+        if !path.is_file() {
+            return Err(ConfigError::Invalid {
+                key: "config",
+                reason: format!("{} is not a file", path.display()),
+            });
+        }
+        figment = figment.merge(Toml::file(path));
+    }
+
+    figment
+        .merge(env)
+        .extract()
+        .map_err(|error| ConfigError::Load(Box::new(error)))
+}
+
+/// Helper to fail with `reason` unless `valid`
+fn check(key: &'static str, valid: bool, reason: impl Into<String>) -> Result<(), ConfigError> {
+    if valid {
+        return Ok(());
+    }
+
+    Err(ConfigError::Invalid {
+        key,
+        reason: reason.into(),
+    })
+}
+
+/// Reads the PEM certificate or key at `path` of a TLS option
+pub fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Helper to check that every file that was given exists
+fn validate_files(files: &[(&'static str, &Option<PathBuf>)]) -> Result<(), ConfigError> {
+    for (key, file) in files {
+        if let Some(file) = file {
+            check(
+                key,
+                file.is_file(),
+                format!("{} is not a file", file.display()),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Helper to check that `url` is a valid URL with one of `schemes`
+fn validate_url(key: &'static str, url: &str, schemes: &[&str]) -> Result<(), ConfigError> {
+    let url = Url::parse(url).map_err(|error| ConfigError::Invalid {
+        key,
+        reason: format!("{} is not a valid URL. {}", url, error),
+    })?;
+
+    check(
+        key,
+        schemes.contains(&url.scheme()),
+        format!("{} must use one of the schemes {:?}", url, schemes),
+    )
+}
+
+/// Helper to read lists either as arrays, like in TOML files, or as comma separated strings,
+/// like in environment variables, e.g. "binance,kraken"
+fn de_list<'a, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'a>,
+    T: FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Values(Vec<String>),
+        Joined(String),
+    }
+
+    let values = match List::deserialize(deserializer)? {
+        List::Values(values) => values,
+        List::Joined(joined) => joined.split(',').map(str::to_string).collect(),
+    };
+
+    values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().map_err(de::Error::custom))
+        .collect()
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::models::{
+    book::{LocalBook, Side},
+    config::ServerConfig,
+    errors::OrderbookError,
+    mapper::{BinanceCombinedStreamData, BinanceDiffDepthData, BinanceStreamData, Exchange},
+    messages::Orders,
+};
+
+use super::ExchangeConnector;
+
+/// Binance connector. Streams the top levels of every symbol, or in full depth mode keeps a local
+/// book per symbol synced from the `@depth` diffs and a REST snapshot as Binance documents it
+pub struct BinanceConnector {
+    api_url: String,
+    /// REST endpoint used to fetch depth snapshots. Only set in full depth mode
+    snapshot_url: Option<String>,
+    /// Partial depth stream, e.g. "depth20"
+    depth_level: String,
+    /// Stream speed, e.g. "100ms"
+    update_speed: String,
+    /// Number of levels per side requested in depth snapshots
+    snapshot_limit: usize,
+    /// Number of levels per side sent out of the local books
+    max_book_depth: usize,
+    http_client: reqwest::Client,
+    books: HashMap<String, LocalBook>,
+    /// Last update applied to the local book of each symbol. Missing until we get a snapshot
+    last_update_ids: HashMap<String, u64>,
+}
+
+impl Default for BinanceConnector {
+    fn default() -> Self {
+        BinanceConnector::from_config(&ServerConfig::default())
+    }
+}
+
+impl BinanceConnector {
+    /// Connector pointing at the endpoints in `config`
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let binance = &config.binance;
+
+        BinanceConnector {
+            api_url: binance.ws_api.clone(),
+            snapshot_url: binance.full_depth.then(|| binance.rest_api.clone()),
+            depth_level: binance.depth_level.clone(),
+            update_speed: binance.update_speed.clone(),
+            snapshot_limit: binance.snapshot_limit,
+            max_book_depth: config.max_book_depth,
+            http_client: reqwest::Client::new(),
+            books: HashMap::new(),
+            last_update_ids: HashMap::new(),
+        }
+    }
+
+    pub fn new(api_url: String) -> Self {
+        BinanceConnector {
+            api_url,
+            snapshot_url: None,
+            ..BinanceConnector::default()
+        }
+    }
+
+    /// Connector that keeps full depth local books out of the diff stream, using snapshots
+    /// fetched from `snapshot_url`
+    pub fn full_depth(api_url: String, snapshot_url: String) -> Self {
+        BinanceConnector {
+            snapshot_url: Some(snapshot_url),
+            ..BinanceConnector::new(api_url)
+        }
+    }
+
+    /// Name of the depth stream of `symbol`, e.g. "ethbtc@depth20@100ms"
+    fn stream_name(&self, symbol: &str) -> String {
+        let depth = match self.snapshot_url {
+            Some(_) => "depth",
+            None => &self.depth_level,
+        };
+
+        format!("{}@{}@{}", symbol, depth, self.update_speed)
+    }
+
+    /// Helper to fetch the depth snapshot of `symbol` and reset its local book
+    async fn sync_snapshot(&mut self, snapshot_url: &str, symbol: &str) -> Result<()> {
+        let snapshot_url = format!(
+            "{}/api/v3/depth?symbol={}&limit={}",
+            snapshot_url,
+            symbol.to_uppercase(),
+            self.snapshot_limit
+        );
+
+        log::info!("Fetching Binance depth snapshot at: {}", &snapshot_url);
+        let snapshot: BinanceStreamData = self
+            .http_client
+            .get(&snapshot_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.books
+            .entry(symbol.to_string())
+            .or_default()
+            .reset(snapshot.asks, snapshot.bids);
+        self.last_update_ids
+            .insert(symbol.to_string(), snapshot.last_update_id);
+
+        Ok(())
+    }
+
+    /// Helper to apply a diff event on top of the local book of `symbol`
+    fn apply_diff(&mut self, symbol: String, data: BinanceDiffDepthData) -> Result<Option<Orders>> {
+        let last_update_id = *self
+            .last_update_ids
+            .get(&symbol)
+            .ok_or_else(|| anyhow!("Binance {} update received before snapshot", symbol))?;
+
+        // Already part of the snapshot
+        if data.final_update_id <= last_update_id {
+            return Ok(None);
+        }
+
+        // Every event has to start right after the last one applied, otherwise we missed some
+        if data.first_update_id > last_update_id + 1 {
+            self.last_update_ids.remove(&symbol);
+            return Err(OrderbookError::SequenceGap {
+                exchange: Exchange::Binance,
+                expected: last_update_id + 1,
+                received: data.first_update_id,
+            }
+            .into());
+        }
+
+        let book = self.books.entry(symbol.clone()).or_default();
+        for offer in data.asks {
+            book.apply(Side::Ask, offer);
+        }
+        for offer in data.bids {
+            book.apply(Side::Bid, offer);
+        }
+        self.last_update_ids
+            .insert(symbol.clone(), data.final_update_id);
+
+        let mut orders = book.to_orders(Exchange::Binance, symbol, self.max_book_depth);
+        orders.event_time_us = data.event_time.map(|event_time| event_time * 1000);
+        orders.update_id = Some(data.final_update_id);
+
+        Ok(Some(orders))
+    }
+}
+
+#[tonic::async_trait]
+impl ExchangeConnector for BinanceConnector {
+    fn exchange(&self) -> Exchange {
+        Exchange::Binance
+    }
+
+    /// Combined stream carrying the depth streams of all symbols
+    fn url(&self, symbols: &[String]) -> String {
+        let streams: Vec<String> = symbols
+            .iter()
+            .map(|symbol| self.stream_name(symbol))
+            .collect();
+
+        format!("{}/stream?streams={}", self.api_url, streams.join("/"))
+    }
+
+    /// In full depth mode fetches the snapshots the diff events are applied on top of
+    async fn on_connect(&mut self, symbols: &[String]) -> Result<()> {
+        self.books.clear();
+        self.last_update_ids.clear();
+
+        if let Some(snapshot_url) = self.snapshot_url.clone() {
+            for symbol in symbols {
+                self.sync_snapshot(&snapshot_url, symbol).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Combined stream payloads are wrapped as `{"stream": "<streamName>", "data": <payload>}`
+    /// where the stream name starts with the symbol
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>> {
+        if self.snapshot_url.is_some() {
+            let parsed: BinanceCombinedStreamData<BinanceDiffDepthData> =
+                serde_json::from_str(text)?;
+            return self.apply_diff(parsed.symbol(), parsed.data);
+        }
+
+        let parsed: BinanceCombinedStreamData<BinanceStreamData> = serde_json::from_str(text)?;
+
+        Ok(Some(Orders {
+            exchange: Exchange::Binance,
+            symbol: parsed.symbol(),
+            asks: parsed.data.asks,
+            bids: parsed.data.bids,
+            received_at: None,
+            // Partial depth streams don't carry their event time
+            event_time_us: None,
+            update_id: Some(parsed.data.last_update_id),
+        }))
+    }
+}
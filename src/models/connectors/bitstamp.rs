@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::models::{
+    book::{LocalBook, Side},
+    config::ServerConfig,
+    errors::OrderbookError,
+    mapper::{BitstampData, BitstampStreamData, Exchange},
+    messages::Orders,
+};
+
+use super::ExchangeConnector;
+
+/// Bitstamp connector. Subscribes to the full orderbooks of every symbol, or in diff mode keeps a
+/// local book per symbol synced from `diff_order_book_{symbol}` and a REST snapshot
+pub struct BitstampConnector {
+    api_url: String,
+    /// REST endpoint used to fetch orderbook snapshots. Only set in diff mode
+    snapshot_url: Option<String>,
+    /// Number of levels per side sent out of the local books
+    max_book_depth: usize,
+    http_client: reqwest::Client,
+    books: HashMap<String, LocalBook>,
+    /// Microtimestamp of the snapshot of each symbol. Missing until we get one
+    snapshot_microtimestamps: HashMap<String, usize>,
+    /// Microtimestamp of the last update applied on top of the snapshot of each symbol
+    last_microtimestamps: HashMap<String, usize>,
+}
+
+impl Default for BitstampConnector {
+    fn default() -> Self {
+        BitstampConnector::from_config(&ServerConfig::default())
+    }
+}
+
+impl BitstampConnector {
+    /// Connector pointing at the endpoints in `config`
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let bitstamp = &config.bitstamp;
+
+        BitstampConnector {
+            api_url: bitstamp.ws_api.clone(),
+            snapshot_url: bitstamp.diff.then(|| bitstamp.rest_api.clone()),
+            max_book_depth: config.max_book_depth,
+            http_client: reqwest::Client::new(),
+            books: HashMap::new(),
+            snapshot_microtimestamps: HashMap::new(),
+            last_microtimestamps: HashMap::new(),
+        }
+    }
+
+    pub fn new(api_url: String) -> Self {
+        BitstampConnector {
+            api_url,
+            snapshot_url: None,
+            ..BitstampConnector::default()
+        }
+    }
+
+    /// Connector that keeps a local book out of the diff channel, using snapshots fetched
+    /// from `snapshot_url`
+    pub fn diff(api_url: String, snapshot_url: String) -> Self {
+        BitstampConnector {
+            snapshot_url: Some(snapshot_url),
+            ..BitstampConnector::new(api_url)
+        }
+    }
+
+    /// Prefix of the channels we subscribe to, followed by the symbol
+    fn channel_prefix(&self) -> &'static str {
+        match self.snapshot_url {
+            Some(_) => "diff_order_book_",
+            None => "order_book_",
+        }
+    }
+
+    /// Helper to build the subscribe and unsubscribe events
+    fn channel_message(&self, event: &str, symbol: &str) -> Message {
+        let msg = json!({
+            "event": event,
+            "data": {
+                "channel": format!("{}{}", self.channel_prefix(), symbol)
+            }
+        });
+
+        Message::Text(msg.to_string())
+    }
+
+    /// Helper to fetch the orderbook snapshot of `symbol` and reset its local book
+    async fn sync_snapshot(&mut self, snapshot_url: &str, symbol: &str) -> Result<()> {
+        let snapshot_url = format!("{}/api/v2/order_book/{}/", snapshot_url, symbol);
+
+        log::info!("Fetching Bitstamp orderbook snapshot at: {}", &snapshot_url);
+        let snapshot: BitstampStreamData = self
+            .http_client
+            .get(&snapshot_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.books.entry(symbol.to_string()).or_default().reset(
+            snapshot.asks.unwrap_or_default(),
+            snapshot.bids.unwrap_or_default(),
+        );
+        self.snapshot_microtimestamps.insert(
+            symbol.to_string(),
+            snapshot
+                .microtimestamp
+                .ok_or_else(|| anyhow!("Bitstamp snapshot without microtimestamp"))?,
+        );
+
+        Ok(())
+    }
+
+    /// Helper to apply a diff on top of the local book of `symbol`
+    fn apply_diff(&mut self, symbol: String, data: BitstampStreamData) -> Result<Option<Orders>> {
+        let snapshot_microtimestamp = *self
+            .snapshot_microtimestamps
+            .get(&symbol)
+            .ok_or_else(|| anyhow!("Bitstamp {} update received before snapshot", symbol))?;
+        let microtimestamp = data
+            .microtimestamp
+            .ok_or_else(|| anyhow!("Bitstamp update without microtimestamp"))?;
+
+        // Already part of the snapshot
+        if microtimestamp <= snapshot_microtimestamp {
+            return Ok(None);
+        }
+
+        // There are no sequence numbers, so going back in time is all we can tell is out of order
+        if let Some(&last) = self
+            .last_microtimestamps
+            .get(&symbol)
+            .filter(|&&last| microtimestamp < last)
+        {
+            self.snapshot_microtimestamps.remove(&symbol);
+            return Err(OrderbookError::OutOfOrder {
+                exchange: Exchange::Bitstamp,
+                last: last as u64,
+                received: microtimestamp as u64,
+            }
+            .into());
+        }
+
+        let book = self.books.entry(symbol.clone()).or_default();
+        for offer in data.asks.unwrap_or_default() {
+            book.apply(Side::Ask, offer);
+        }
+        for offer in data.bids.unwrap_or_default() {
+            book.apply(Side::Bid, offer);
+        }
+        self.last_microtimestamps
+            .insert(symbol.clone(), microtimestamp);
+
+        let mut orders = book.to_orders(Exchange::Bitstamp, symbol, self.max_book_depth);
+        orders.event_time_us = Some(microtimestamp as u64);
+
+        Ok(Some(orders))
+    }
+}
+
+#[tonic::async_trait]
+impl ExchangeConnector for BitstampConnector {
+    fn exchange(&self) -> Exchange {
+        Exchange::Bitstamp
+    }
+
+    fn url(&self, _symbols: &[String]) -> String {
+        self.api_url.clone()
+    }
+
+    fn subscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        symbols
+            .iter()
+            .map(|symbol| self.channel_message("bts:subscribe", symbol))
+            .collect()
+    }
+
+    fn unsubscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        symbols
+            .iter()
+            .map(|symbol| self.channel_message("bts:unsubscribe", symbol))
+            .collect()
+    }
+
+    /// In diff mode fetches the snapshots the updates are applied on top of
+    async fn on_connect(&mut self, symbols: &[String]) -> Result<()> {
+        self.books.clear();
+        self.snapshot_microtimestamps.clear();
+        self.last_microtimestamps.clear();
+
+        if let Some(snapshot_url) = self.snapshot_url.clone() {
+            for symbol in symbols {
+                self.sync_snapshot(&snapshot_url, symbol).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>> {
+        let parsed: BitstampData = serde_json::from_str(text)?;
+
+        // Subscription acknowledgements don't carry any orderbook
+        if parsed.data.timestamp.is_none() {
+            return Ok(None);
+        }
+
+        let symbol = parsed
+            .channel
+            .strip_prefix(self.channel_prefix())
+            .ok_or_else(|| anyhow!("Unexpected Bitstamp channel {}", parsed.channel))?
+            .to_string();
+
+        if self.snapshot_url.is_some() {
+            return self.apply_diff(symbol, parsed.data);
+        }
+
+        match (parsed.data.asks, parsed.data.bids) {
+            (Some(asks), Some(bids)) => Ok(Some(Orders {
+                exchange: Exchange::Bitstamp,
+                symbol,
+                asks,
+                bids,
+                received_at: None,
+                event_time_us: parsed
+                    .data
+                    .microtimestamp
+                    .map(|microtimestamp| microtimestamp as u64),
+                update_id: None,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
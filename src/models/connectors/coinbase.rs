@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::models::{
+    book::{LocalBook, Side},
+    config::ServerConfig,
+    errors::OrderbookError,
+    mapper::{CoinbaseMessage, Exchange, OfferData},
+    messages::Orders,
+};
+
+use super::{normalize_symbol, split_symbol, ExchangeConnector};
+
+/// Coinbase Exchange connector. Keeps a local book per product out of the snapshot and updates of
+/// the `level2_batch` channel, which unlike `level2` needs no authentication
+pub struct CoinbaseConnector {
+    api_url: String,
+    /// Quote assets used to split symbols into product ids
+    quote_assets: Vec<String>,
+    /// Number of levels per side sent out of the local books
+    max_book_depth: usize,
+    books: HashMap<String, LocalBook>,
+}
+
+impl Default for CoinbaseConnector {
+    fn default() -> Self {
+        CoinbaseConnector::from_config(&ServerConfig::default())
+    }
+}
+
+impl CoinbaseConnector {
+    /// Connector pointing at the endpoint in `config`
+    pub fn from_config(config: &ServerConfig) -> Self {
+        CoinbaseConnector {
+            api_url: config.coinbase.ws_api.clone(),
+            quote_assets: config.quote_assets.clone(),
+            max_book_depth: config.max_book_depth,
+            books: HashMap::new(),
+        }
+    }
+
+    pub fn new(api_url: String) -> Self {
+        CoinbaseConnector {
+            api_url,
+            ..CoinbaseConnector::default()
+        }
+    }
+
+    /// Coinbase product id, e.g. "ETH-BTC" for "ethbtc"
+    fn product_id(&self, symbol: &str) -> String {
+        match split_symbol(symbol, &self.quote_assets) {
+            Some((base, quote)) => format!("{}-{}", base, quote),
+            None => symbol.to_uppercase(),
+        }
+    }
+
+    /// Helper to build the subscribe and unsubscribe messages
+    fn channel_message(&self, kind: &str, symbols: &[String]) -> Message {
+        let product_ids: Vec<String> = symbols
+            .iter()
+            .map(|symbol| self.product_id(symbol))
+            .collect();
+        let msg = json!({
+            "type": kind,
+            "product_ids": product_ids,
+            "channels": ["level2_batch"]
+        });
+
+        Message::Text(msg.to_string())
+    }
+}
+
+#[tonic::async_trait]
+impl ExchangeConnector for CoinbaseConnector {
+    fn exchange(&self) -> Exchange {
+        Exchange::Coinbase
+    }
+
+    fn url(&self, _symbols: &[String]) -> String {
+        self.api_url.clone()
+    }
+
+    fn subscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("subscribe", symbols)]
+    }
+
+    fn unsubscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("unsubscribe", symbols)]
+    }
+
+    /// Every connection starts with new snapshots so we drop whatever we had
+    async fn on_connect(&mut self, _symbols: &[String]) -> Result<()> {
+        self.books.clear();
+        Ok(())
+    }
+
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>> {
+        let symbol = match serde_json::from_str(text)? {
+            CoinbaseMessage::Snapshot {
+                product_id,
+                asks,
+                bids,
+            } => {
+                let symbol = normalize_symbol(&product_id);
+                self.books
+                    .entry(symbol.clone())
+                    .or_default()
+                    .reset(asks, bids);
+                symbol
+            }
+            CoinbaseMessage::L2update {
+                product_id,
+                changes,
+            } => {
+                let symbol = normalize_symbol(&product_id);
+                let book = match self.books.get_mut(&symbol) {
+                    Some(book) if !book.is_empty() => book,
+                    _ => {
+                        return Err(anyhow!(
+                            "Coinbase {} update received before snapshot",
+                            product_id
+                        ))
+                    }
+                };
+
+                for change in changes {
+                    let side = match change.side.as_str() {
+                        "buy" => Side::Bid,
+                        "sell" => Side::Ask,
+                        side => return Err(anyhow!("Unknown Coinbase side: {}", side)),
+                    };
+                    let offer = OfferData {
+                        price: change.price,
+                        quantity: change.quantity,
+                    };
+                    book.apply(side, offer);
+                }
+                symbol
+            }
+            CoinbaseMessage::Error { message, reason } => {
+                return Err(OrderbookError::SubscriptionRejected {
+                    exchange: Exchange::Coinbase,
+                    reason: format!("{}. {}", message, reason),
+                }
+                .into());
+            }
+            CoinbaseMessage::Other => return Ok(None),
+        };
+
+        Ok(Some(self.books[&symbol].to_orders(
+            Exchange::Coinbase,
+            symbol.clone(),
+            self.max_book_depth,
+        )))
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::models::{
+    book::{LocalBook, Side},
+    config::ServerConfig,
+    errors::OrderbookError,
+    mapper::{Exchange, KrakenBookData, KrakenEvent},
+    messages::Orders,
+};
+
+use super::{normalize_symbol, split_symbol, ExchangeConnector};
+
+/// Kraken connector. Keeps a local book per pair out of the snapshot and updates of the `book` channel
+pub struct KrakenConnector {
+    api_url: String,
+    /// Book depth we subscribe to
+    depth_level: usize,
+    /// Quote assets used to split symbols into pairs
+    quote_assets: Vec<String>,
+    books: HashMap<String, LocalBook>,
+}
+
+impl Default for KrakenConnector {
+    fn default() -> Self {
+        KrakenConnector::from_config(&ServerConfig::default())
+    }
+}
+
+impl KrakenConnector {
+    /// Connector pointing at the endpoint in `config`
+    pub fn from_config(config: &ServerConfig) -> Self {
+        KrakenConnector {
+            api_url: config.kraken.ws_api.clone(),
+            depth_level: config.kraken.depth_level,
+            quote_assets: config.quote_assets.clone(),
+            books: HashMap::new(),
+        }
+    }
+
+    pub fn new(api_url: String) -> Self {
+        KrakenConnector {
+            api_url,
+            ..KrakenConnector::default()
+        }
+    }
+
+    /// Kraken pair, e.g. "ETH/XBT" for "ethbtc". Kraken calls Bitcoin XBT
+    fn pair(&self, symbol: &str) -> String {
+        let kraken_asset = |asset: String| match asset.as_str() {
+            "BTC" => "XBT".to_string(),
+            _ => asset,
+        };
+
+        match split_symbol(symbol, &self.quote_assets) {
+            Some((base, quote)) => format!("{}/{}", kraken_asset(base), kraken_asset(quote)),
+            None => symbol.to_uppercase(),
+        }
+    }
+
+    /// Our symbol for a Kraken pair, e.g. "ethbtc" for "ETH/XBT"
+    fn symbol(pair: &str) -> String {
+        let assets: Vec<&str> = pair
+            .split('/')
+            .map(|asset| match asset {
+                "XBT" => "BTC",
+                _ => asset,
+            })
+            .collect();
+
+        normalize_symbol(&assets.concat())
+    }
+
+    /// Helper to build the subscribe and unsubscribe events
+    fn channel_message(&self, event: &str, symbols: &[String]) -> Message {
+        let pairs: Vec<String> = symbols.iter().map(|symbol| self.pair(symbol)).collect();
+        let msg = json!({
+            "event": event,
+            "pair": pairs,
+            "subscription": {
+                "name": "book",
+                "depth": self.depth_level
+            }
+        });
+
+        Message::Text(msg.to_string())
+    }
+
+    /// Helper to handle events. Only a failed subscription is an error
+    fn handle_event(event: KrakenEvent) -> Result<Option<Orders>> {
+        if event.event == "subscriptionStatus" && event.status.as_deref() == Some("error") {
+            return Err(OrderbookError::SubscriptionRejected {
+                exchange: Exchange::Kraken,
+                reason: event.error_message.unwrap_or_default(),
+            }
+            .into());
+        }
+
+        Ok(None)
+    }
+}
+
+#[tonic::async_trait]
+impl ExchangeConnector for KrakenConnector {
+    fn exchange(&self) -> Exchange {
+        Exchange::Kraken
+    }
+
+    fn url(&self, _symbols: &[String]) -> String {
+        self.api_url.clone()
+    }
+
+    fn subscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("subscribe", symbols)]
+    }
+
+    fn unsubscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("unsubscribe", symbols)]
+    }
+
+    /// Every connection starts with new snapshots so we drop whatever we had
+    async fn on_connect(&mut self, _symbols: &[String]) -> Result<()> {
+        self.books.clear();
+        Ok(())
+    }
+
+    /// Book messages are arrays like `[channelID, {book}, ({book},) channelName, pair]`
+    /// where an update with both sides is split into two objects
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>> {
+        let elements = match serde_json::from_str(text)? {
+            Value::Array(elements) => elements,
+            event => return KrakenConnector::handle_event(serde_json::from_value(event)?),
+        };
+
+        let pair = elements
+            .last()
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Kraken book message without pair"))?;
+        let symbol = KrakenConnector::symbol(&pair);
+        let depth_level = self.depth_level;
+        let book = self.books.entry(symbol.clone()).or_default();
+
+        for element in elements.into_iter().filter(Value::is_object) {
+            let data: KrakenBookData = serde_json::from_value(element)?;
+
+            if !data.snapshot_asks.is_empty() || !data.snapshot_bids.is_empty() {
+                book.reset(data.snapshot_asks, data.snapshot_bids);
+                continue;
+            }
+
+            if book.is_empty() {
+                return Err(anyhow!("Kraken {} update received before snapshot", pair));
+            }
+            for offer in data.asks {
+                book.apply(Side::Ask, offer);
+            }
+            for offer in data.bids {
+                book.apply(Side::Bid, offer);
+            }
+        }
+
+        // Kraken expects levels that fall out of the subscribed depth to be dropped
+        book.truncate(depth_level);
+
+        Ok(Some(book.to_orders(Exchange::Kraken, symbol, depth_level)))
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{config::ServerConfig, mapper::Exchange, messages::Orders};
+
+pub mod binance;
+pub mod bitstamp;
+pub mod coinbase;
+pub mod kraken;
+pub mod okx;
+
+pub use binance::BinanceConnector;
+pub use bitstamp::BitstampConnector;
+pub use coinbase::CoinbaseConnector;
+pub use kraken::KrakenConnector;
+pub use okx::OkxConnector;
+
+/// Everything the generic listener needs to know in order to stream orderbooks from an exchange.
+/// Adding a new venue means implementing this trait and adding it to `connector_for`.
+#[tonic::async_trait]
+pub trait ExchangeConnector: Send {
+    /// Exchange this connector streams from
+    fn exchange(&self) -> Exchange;
+
+    /// Web Socket URL to connect to in order to stream `symbols`
+    fn url(&self, symbols: &[String]) -> String;
+
+    /// Messages sent right after connecting to subscribe to the orderbooks of `symbols`.
+    /// Exchanges that encode the subscription in the URL don't need any.
+    fn subscribe_messages(&self, _symbols: &[String]) -> Vec<Message> {
+        Vec::new()
+    }
+
+    /// Messages sent before closing the connection to unsubscribe from the orderbooks of `symbols`
+    fn unsubscribe_messages(&self, _symbols: &[String]) -> Vec<Message> {
+        Vec::new()
+    }
+
+    /// Called once the subscription messages were sent. Connectors that need to fetch extra
+    /// state before parsing updates can do it here.
+    async fn on_connect(&mut self, _symbols: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Parses a text frame into the Orders of one of the symbols. Returns `None` for frames that
+    /// aren't orderbook updates, e.g. subscription acknowledgements or heartbeats.
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>>;
+
+    /// Application level heartbeat the exchange expects from us and how often to send it.
+    /// Web Socket pings are answered automatically so most exchanges don't need this.
+    fn heartbeat(&self) -> Option<(Duration, Message)> {
+        None
+    }
+}
+
+/// Returns the connector of `exchange` pointing at the endpoints in `config`
+pub fn connector_for(exchange: Exchange, config: &ServerConfig) -> Box<dyn ExchangeConnector> {
+    match exchange {
+        Exchange::Binance => Box::new(BinanceConnector::from_config(config)),
+        Exchange::Bitstamp => Box::new(BitstampConnector::from_config(config)),
+        Exchange::Coinbase => Box::new(CoinbaseConnector::from_config(config)),
+        Exchange::Kraken => Box::new(KrakenConnector::from_config(config)),
+        Exchange::Okx => Box::new(OkxConnector::from_config(config)),
+    }
+}
+
+/// Normalized form of a symbol: lower case without separators, e.g. "ethbtc" for "ETH-BTC"
+pub fn normalize_symbol(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(|c| !matches!(c, '-' | '/' | '_'))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Splits a symbol into its upper case base and quote assets, e.g. "ethbtc" or "eth-btc" into
+/// ("ETH", "BTC"), on its separator if any or else on the first of `quote_assets` it ends with
+pub fn split_symbol(symbol: &str, quote_assets: &[String]) -> Option<(String, String)> {
+    let symbol = symbol.to_uppercase();
+
+    if let Some((base, quote)) = symbol.split_once(['-', '/']) {
+        return Some((base.to_string(), quote.to_string()));
+    }
+
+    quote_assets.iter().find_map(|quote| {
+        let quote = quote.to_uppercase();
+        symbol
+            .strip_suffix(&quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| (base.to_string(), quote))
+    })
+}
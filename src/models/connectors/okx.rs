@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::models::{
+    config::ServerConfig,
+    errors::OrderbookError,
+    mapper::{Exchange, OkxData, OkxEvent},
+    messages::Orders,
+};
+
+use super::{normalize_symbol, split_symbol, ExchangeConnector};
+
+/// OKX connector. Subscribes to the `books5` channel of every instrument which pushes a full 5 level snapshot
+/// every time the book changes. OKX expects a "ping" text frame to keep the connection alive.
+pub struct OkxConnector {
+    api_url: String,
+    /// Seconds between heartbeats
+    heartbeat_secs: u64,
+    /// Quote assets used to split symbols into instrument ids
+    quote_assets: Vec<String>,
+}
+
+impl Default for OkxConnector {
+    fn default() -> Self {
+        OkxConnector::from_config(&ServerConfig::default())
+    }
+}
+
+impl OkxConnector {
+    /// Connector pointing at the endpoint in `config`
+    pub fn from_config(config: &ServerConfig) -> Self {
+        OkxConnector {
+            api_url: config.okx.ws_api.clone(),
+            heartbeat_secs: config.okx.heartbeat_secs,
+            quote_assets: config.quote_assets.clone(),
+        }
+    }
+
+    pub fn new(api_url: String) -> Self {
+        OkxConnector {
+            api_url,
+            ..OkxConnector::default()
+        }
+    }
+
+    /// OKX instrument id, e.g. "ETH-BTC" for "ethbtc"
+    fn inst_id(&self, symbol: &str) -> String {
+        match split_symbol(symbol, &self.quote_assets) {
+            Some((base, quote)) => format!("{}-{}", base, quote),
+            None => symbol.to_uppercase(),
+        }
+    }
+
+    /// Helper to build the subscribe and unsubscribe operations
+    fn channel_message(&self, op: &str, symbols: &[String]) -> Message {
+        let args: Vec<Value> = symbols
+            .iter()
+            .map(|symbol| {
+                json!({
+                    "channel": "books5",
+                    "instId": self.inst_id(symbol)
+                })
+            })
+            .collect();
+        let msg = json!({
+            "op": op,
+            "args": args
+        });
+
+        Message::Text(msg.to_string())
+    }
+}
+
+#[tonic::async_trait]
+impl ExchangeConnector for OkxConnector {
+    fn exchange(&self) -> Exchange {
+        Exchange::Okx
+    }
+
+    fn url(&self, _symbols: &[String]) -> String {
+        self.api_url.clone()
+    }
+
+    fn subscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("subscribe", symbols)]
+    }
+
+    fn unsubscribe_messages(&self, symbols: &[String]) -> Vec<Message> {
+        vec![self.channel_message("unsubscribe", symbols)]
+    }
+
+    fn parse(&mut self, text: &str) -> Result<Option<Orders>> {
+        // Reply to our heartbeat
+        if text == "pong" {
+            return Ok(None);
+        }
+
+        let value: Value = serde_json::from_str(text)?;
+        if value.get("event").is_some() {
+            let event: OkxEvent = serde_json::from_value(value)?;
+            if event.event == "error" {
+                return Err(OrderbookError::SubscriptionRejected {
+                    exchange: Exchange::Okx,
+                    reason: format!("{}: {}", event.code, event.msg),
+                }
+                .into());
+            }
+            return Ok(None);
+        }
+
+        let parsed: OkxData = serde_json::from_value(value)?;
+        if parsed.arg.channel != "books5" {
+            return Ok(None);
+        }
+
+        let symbol = normalize_symbol(&parsed.arg.inst_id);
+        Ok(parsed.data.into_iter().last().map(|book| Orders {
+            exchange: Exchange::Okx,
+            symbol,
+            asks: book.asks,
+            bids: book.bids,
+            received_at: None,
+            event_time_us: None,
+            update_id: None,
+        }))
+    }
+
+    fn heartbeat(&self) -> Option<(Duration, Message)> {
+        Some((
+            Duration::from_secs(self.heartbeat_secs),
+            Message::Text("ping".to_string()),
+        ))
+    }
+}
@@ -1,18 +1,64 @@
+use std::net::{IpAddr, Ipv6Addr};
+
 /// Buffer limit of boradcast channel
 pub const CHANNEL_BUFFER_LIMIT: usize = 1024;
+/// Buffer limit of the channel of summaries sent to each client
+pub const CLIENT_BUFFER_LIMIT: usize = 100;
+/// Buffer limit of the channel of frames waiting to be recorded
+pub const RECORDER_BUFFER_LIMIT: usize = 1024;
+/// Buffer limit of the channel of frames read ahead of a replay
+pub const REPLAY_READ_AHEAD: usize = 1024;
 /// Binance Web Socket URL endpoint
 pub const BINANCE_WS_API: &str = "wss://stream.binance.com:9443";
+/// Binance REST API endpoint used to fetch depth snapshots
+pub const BINANCE_REST_API: &str = "https://api.binance.com";
 /// Bitstamp Web Socket URL endpoint
 pub const BITSTAMP_WS_API: &str = "wss://ws.bitstamp.net";
+/// Bitstamp REST API endpoint used to fetch orderbook snapshots
+pub const BITSTAMP_REST_API: &str = "https://www.bitstamp.net";
+/// Coinbase Exchange Web Socket URL endpoint
+pub const COINBASE_WS_API: &str = "wss://ws-feed.exchange.coinbase.com";
+/// Kraken Web Socket URL endpoint
+pub const KRAKEN_WS_API: &str = "wss://ws.kraken.com";
+/// OKX public Web Socket URL endpoint
+pub const OKX_WS_API: &str = "wss://ws.okx.com:8443/ws/v5/public";
 /// Broadcast error count limit before we display a warning
 pub const ERR_COUNT_LOG: i32 = 100;
 /// Binance depth level. We set to 20
 pub const DEPTH_LEVEL_BINANCE: &str = "depth20";
 /// Binance web socket stream speed. We set to 100 ms
 pub const UPDATE_SPEED_BINANCE: &str = "100ms";
+/// Number of levels per side requested in Binance depth snapshots
+pub const SNAPSHOT_LIMIT_BINANCE: usize = 1000;
+/// Kraken book depth. Valid values are 10, 25, 100, 500 and 1000
+pub const DEPTH_LEVEL_KRAKEN: usize = 10;
+/// OKX closes connections that don't send a "ping" within 30 seconds
+pub const HEARTBEAT_SECS_OKX: u64 = 25;
+/// Limit of levels per side sent to the aggregator from locally maintained books
+pub const MAX_BOOK_DEPTH: usize = 100;
+/// Quote assets used to split symbols like "ethbtc" into base and quote
+pub const QUOTE_ASSETS: [&str; 8] = ["usdt", "usdc", "busd", "btc", "eth", "usd", "eur", "gbp"];
 /// Port at which our gRPC Server will be running
-pub const SERVER_PORT: &str = "50505";
+pub const SERVER_PORT: u16 = 50505;
 /// IP Address at which our gRPC Server will be running
-pub const IP_ADDRESS: &str = "[::1]";
+pub const IP_ADDRESS: IpAddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+/// Prefix of the environment variables overriding the server configuration, e.g. ORDERBOOK_PORT
+pub const SERVER_ENV_PREFIX: &str = "ORDERBOOK_";
+/// Prefix of the environment variables overriding the client configuration, e.g. ORDERBOOK_CLIENT_DEPTH
+pub const CLIENT_ENV_PREFIX: &str = "ORDERBOOK_CLIENT_";
 /// Limit of asks and bids we're returning to the user
 pub const MAX_PAIR_EXCHANGE: usize = 10;
+/// Initial delay before reconnecting to an exchange after its feed goes down
+pub const BACKOFF_INITIAL_MS: u64 = 500;
+/// Maximum delay between reconnection attempts to an exchange
+pub const BACKOFF_MAX_MS: u64 = 30_000;
+/// Milliseconds without an orderbook after which an exchange feed is stale, as far as health checks go
+pub const STALE_FEED_MS: u64 = 30_000;
+/// Time the server is given to end client streams and close exchange connections once it's told to stop
+pub const SHUTDOWN_TIMEOUT_MS: u64 = 5_000;
+/// Seconds after which the recorder starts a new file
+pub const RECORDER_ROTATE_SECS: u64 = 3600;
+/// Uncompressed bytes after which the recorder starts a new file
+pub const RECORDER_MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+/// Seconds between flushes of the recorded frames to disk
+pub const RECORDER_FLUSH_SECS: u64 = 1;
@@ -1,8 +1,172 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
 use thiserror::Error;
+use tonic::{Code, Status};
+
+use super::{book::Side, mapper::Exchange};
 
+/// Everything that can go wrong between the exchange feeds and the clients. Each kind is counted
+/// under its own label in the metrics and surfaced to clients with its own gRPC status code.
 #[derive(Error, Debug)]
 pub enum OrderbookError {
-    // TODO: Future version, add Custom Error handling
+    /// Connecting to an exchange, subscribing over the connection or reading from it failed
+    #[error("{exchange} connection failed. {source}")]
+    Connection {
+        exchange: Exchange,
+        source: anyhow::Error,
+    },
+    /// The exchange refused the subscription to the orderbooks of a symbol
+    #[error("{exchange} rejected the subscription. {reason}")]
+    SubscriptionRejected { exchange: Exchange, reason: String },
+    /// A frame of the exchange couldn't be parsed into an orderbook
+    #[error("Can't parse {exchange} data. {source}")]
+    Parse {
+        exchange: Exchange,
+        source: anyhow::Error,
+    },
+    /// An update didn't follow the previous one so the local book can't be trusted anymore
+    #[error("{exchange} sequence gap. Expected update {expected} but got {received}")]
+    SequenceGap {
+        exchange: Exchange,
+        expected: u64,
+        received: u64,
+    },
+    /// An update is older than the last one applied to the local book
+    #[error("{exchange} update {received} is older than the last applied update {last}")]
+    OutOfOrder {
+        exchange: Exchange,
+        last: u64,
+        received: u64,
+    },
+    /// The exchange is connected but hasn't sent an orderbook for a while
+    #[error("{exchange} sent no orderbook for {age:?}")]
+    StaleBook { exchange: Exchange, age: Duration },
+    /// The exchange sent a book of `symbol` without a single level on `side`
+    #[error("{exchange} {symbol} book has no {side:?} levels")]
+    EmptyBook {
+        exchange: Exchange,
+        symbol: String,
+        side: Side,
+    },
+    /// A level of the book has a price or quantity that makes no sense, or shares its price with
+    /// another level of its side
+    #[error("{exchange} {symbol} book has an invalid {side:?} level. {reason}")]
+    InvalidLevel {
+        exchange: Exchange,
+        symbol: String,
+        side: Side,
+        reason: String,
+    },
+    /// The best bid of the book is at or above its best ask
+    #[error("{exchange} {symbol} book is crossed. Best bid {best_bid} >= best ask {best_ask}")]
+    CrossedBook {
+        exchange: Exchange,
+        symbol: String,
+        best_bid: Decimal,
+        best_ask: Decimal,
+    },
+    /// A client fell behind the updates of `symbol` and `skipped` of them
+    #[error(
+        "Fell behind the updates of {symbol} by {skipped}. \
+         Consume faster, set throttle_ms or ask for fewer symbols"
+    )]
+    ClientLag { symbol: String, skipped: u64 },
+    /// Anything else, e.g. a recording that can't be read
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl OrderbookError {
+    /// Whether the local book can't be trusted anymore and has to be synced from scratch
+    pub fn requires_resync(&self) -> bool {
+        matches!(
+            self,
+            OrderbookError::SequenceGap { .. } | OrderbookError::OutOfOrder { .. }
+        )
+    }
+
+    /// Connection failure of `exchange` caused by `source`
+    pub fn connection(exchange: Exchange, source: impl Into<anyhow::Error>) -> Self {
+        OrderbookError::Connection {
+            exchange,
+            source: source.into(),
+        }
+    }
+
+    /// Classifies an error returned by a connector while parsing a frame of `exchange`. Errors
+    /// the connector already classified are kept, anything else is a parse failure
+    pub fn parse(exchange: Exchange, error: anyhow::Error) -> Self {
+        match error.downcast::<OrderbookError>() {
+            Ok(error) => error,
+            Err(source) => OrderbookError::Parse { exchange, source },
+        }
+    }
+
+    /// Exchange the error comes from, if any
+    pub fn exchange(&self) -> Option<Exchange> {
+        match self {
+            OrderbookError::Connection { exchange, .. }
+            | OrderbookError::SubscriptionRejected { exchange, .. }
+            | OrderbookError::Parse { exchange, .. }
+            | OrderbookError::SequenceGap { exchange, .. }
+            | OrderbookError::OutOfOrder { exchange, .. }
+            | OrderbookError::StaleBook { exchange, .. }
+            | OrderbookError::EmptyBook { exchange, .. }
+            | OrderbookError::InvalidLevel { exchange, .. }
+            | OrderbookError::CrossedBook { exchange, .. } => Some(*exchange),
+            OrderbookError::ClientLag { .. } | OrderbookError::Other(_) => None,
+        }
+    }
+
+    /// Label of the kind of error in the metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OrderbookError::Connection { .. } => "connection",
+            OrderbookError::SubscriptionRejected { .. } => "subscription_rejected",
+            OrderbookError::Parse { .. } => "parse",
+            OrderbookError::SequenceGap { .. } => "sequence_gap",
+            OrderbookError::OutOfOrder { .. } => "out_of_order",
+            OrderbookError::StaleBook { .. } => "stale_book",
+            OrderbookError::EmptyBook { .. } => "empty_book",
+            OrderbookError::InvalidLevel { .. } => "invalid_level",
+            OrderbookError::CrossedBook { .. } => "crossed_book",
+            OrderbookError::ClientLag { .. } => "client_lag",
+            OrderbookError::Other(_) => "other",
+        }
+    }
+
+    /// gRPC status code the error is reported to clients with
+    pub fn code(&self) -> Code {
+        match self {
+            OrderbookError::Connection { .. } | OrderbookError::StaleBook { .. } => {
+                Code::Unavailable
+            }
+            OrderbookError::SubscriptionRejected { .. } => Code::FailedPrecondition,
+            OrderbookError::SequenceGap { .. }
+            | OrderbookError::OutOfOrder { .. }
+            | OrderbookError::InvalidLevel { .. }
+            | OrderbookError::CrossedBook { .. } => Code::DataLoss,
+            OrderbookError::EmptyBook { .. } => Code::NotFound,
+            OrderbookError::ClientLag { .. } => Code::ResourceExhausted,
+            OrderbookError::Parse { .. } | OrderbookError::Other(_) => Code::Internal,
+        }
+    }
+}
+
+impl From<OrderbookError> for Status {
+    fn from(error: OrderbookError) -> Self {
+        Status::new(error.code(), error.to_string())
+    }
+}
+
+/// Configuration that can't be loaded or doesn't make sense, reported at startup
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// The configuration file or an environment variable couldn't be read into the configuration
+    #[error("Failed to load configuration. {0}")]
+    Load(#[from] Box<figment::Error>),
+    /// A value was loaded but it's out of range or malformed
+    #[error("Invalid configuration `{key}`: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
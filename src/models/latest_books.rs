@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{
+    mapper::Exchange,
+    messages::{OrderbookMessage, SymbolChannels},
+};
+
+/// Latest message of every exchange on the channel of each symbol, out of which clients that fell
+/// behind the broadcast get a fresh snapshot of the books
+#[derive(Debug, Clone, Default)]
+pub struct LatestBooks {
+    symbols: Arc<HashMap<String, Mutex<HashMap<Exchange, OrderbookMessage>>>>,
+}
+
+impl LatestBooks {
+    /// Spawns a task per symbol of `channels` that keeps its latest messages
+    pub fn watch(channels: &SymbolChannels) -> Self {
+        let latest = LatestBooks {
+            symbols: Arc::new(
+                channels
+                    .keys()
+                    .map(|symbol| (symbol.clone(), Mutex::default()))
+                    .collect(),
+            ),
+        };
+
+        for (symbol, chan_send) in channels {
+            let mut chan_recv = chan_send.subscribe();
+            let latest = latest.clone();
+            let symbol = symbol.clone();
+            tokio::spawn(async move {
+                loop {
+                    let msg = match chan_recv.recv().await {
+                        Ok(msg) => msg,
+                        // Whatever was skipped is older than what comes next
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    };
+                    let exchange = match &msg {
+                        OrderbookMessage::Message { message } => message.exchange,
+                        OrderbookMessage::Status { exchange, .. } => *exchange,
+                    };
+
+                    latest.symbols[&symbol]
+                        .lock()
+                        .unwrap()
+                        .insert(exchange, msg);
+                }
+            });
+        }
+
+        latest
+    }
+
+    /// Latest message of every exchange on the channel of `symbol`
+    pub fn snapshot(&self, symbol: &str) -> Vec<OrderbookMessage> {
+        match self.symbols.get(symbol) {
+            Some(messages) => messages.lock().unwrap().values().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+}
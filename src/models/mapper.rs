@@ -1,10 +1,14 @@
 use enum_display_derive::Display;
+use rust_decimal::Decimal;
 use serde::de;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt;
 use std::fmt::Display;
+use std::str::FromStr;
 
-use crate::client::grpc_client::orderbook::{Level, Summary};
+use crate::client::grpc_client::orderbook::{
+    ConnectionStatus, ExchangeStatus, Level, Source, Summary,
+};
 
 pub mod orderbook {
     tonic::include_proto!("orderbook");
@@ -13,37 +17,93 @@ pub mod orderbook {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OfferData {
     /// Price level to be updated
-    #[serde(deserialize_with = "de_float_from_str")]
-    pub price: f32,
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub price: Decimal,
     /// Quantity
-    #[serde(deserialize_with = "de_float_from_str")]
-    pub quantity: f32,
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub quantity: Decimal,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Display)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Display)]
 pub enum Exchange {
     Binance,
     Bitstamp,
+    Coinbase,
+    Kraken,
+    Okx,
+}
+
+impl FromStr for Exchange {
+    type Err = String;
+
+    /// Case insensitive conversion from an exchange name, e.g. "binance" or "Bitstamp"
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "binance" => Ok(Exchange::Binance),
+            "bitstamp" => Ok(Exchange::Bitstamp),
+            "coinbase" => Ok(Exchange::Coinbase),
+            "kraken" => Ok(Exchange::Kraken),
+            "okx" => Ok(Exchange::Okx),
+            _ => Err(format!("Unsupported exchange: {}", name)),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BinanceStreamData {
-    pub last_update_id: usize,
+    pub last_update_id: u64,
     /// Bids to be updated
     pub bids: Vec<OfferData>,
     /// Asks to be updated
     pub asks: Vec<OfferData>,
 }
 
+/// Payload of a Binance combined stream, e.g. `{"stream": "ethbtc@depth20@100ms", "data": {...}}`
+#[derive(Debug, Deserialize)]
+pub struct BinanceCombinedStreamData<T> {
+    pub stream: String,
+    pub data: T,
+}
+
+impl<T> BinanceCombinedStreamData<T> {
+    /// Symbol the payload belongs to, which is the prefix of the stream name
+    pub fn symbol(&self) -> String {
+        self.stream
+            .split('@')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Binance diff depth event. `first_update_id` and `final_update_id` are the "U" and "u"
+/// used to check that no update was missed
+#[derive(Debug, Deserialize)]
+pub struct BinanceDiffDepthData {
+    /// When Binance produced the event, in milliseconds since the Unix epoch
+    #[serde(rename = "E", default)]
+    pub event_time: Option<u64>,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    /// Bids to be updated
+    #[serde(rename = "b")]
+    pub bids: Vec<OfferData>,
+    /// Asks to be updated
+    #[serde(rename = "a")]
+    pub asks: Vec<OfferData>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BitstampOfferData {
     /// Price level to be updated
-    #[serde(deserialize_with = "de_float_from_str")]
-    pub price: f32,
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub price: Decimal,
     /// Quantity of transaction
-    #[serde(deserialize_with = "de_float_from_str")]
-    pub quantity: f32,
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub quantity: Decimal,
 }
 
 #[derive(Debug, Deserialize)]
@@ -69,14 +129,112 @@ pub struct BitstampData {
     pub event: String,
 }
 
-/// Helper to convert the returned floats which are encapsulated between quotes AKA strings
-/// to actual float types
-pub fn de_float_from_str<'a, D>(deserializer: D) -> Result<f32, D::Error>
+/// Coinbase level2 messages. We only care about the snapshot and its incremental updates
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CoinbaseMessage {
+    Snapshot {
+        product_id: String,
+        /// Bids to be replaced
+        bids: Vec<OfferData>,
+        /// Asks to be replaced
+        asks: Vec<OfferData>,
+    },
+    L2update {
+        product_id: String,
+        /// Levels to be updated
+        changes: Vec<CoinbaseChange>,
+    },
+    Error {
+        message: String,
+        #[serde(default)]
+        reason: String,
+    },
+    /// Subscription acknowledgements, heartbeats and any other message type
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinbaseChange {
+    /// Either "buy" or "sell"
+    pub side: String,
+    /// Price level to be updated
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub price: Decimal,
+    /// New quantity. Zero means the level should be removed
+    #[serde(deserialize_with = "de_decimal_from_str")]
+    pub quantity: Decimal,
+}
+
+/// Kraken book payload. A snapshot carries "as" and "bs" while updates carry "a" and/or "b"
+#[derive(Debug, Deserialize)]
+pub struct KrakenBookData {
+    /// Asks snapshot
+    #[serde(rename = "as", default, deserialize_with = "de_offers_from_levels")]
+    pub snapshot_asks: Vec<OfferData>,
+    /// Bids snapshot
+    #[serde(rename = "bs", default, deserialize_with = "de_offers_from_levels")]
+    pub snapshot_bids: Vec<OfferData>,
+    /// Asks to be updated
+    #[serde(rename = "a", default, deserialize_with = "de_offers_from_levels")]
+    pub asks: Vec<OfferData>,
+    /// Bids to be updated
+    #[serde(rename = "b", default, deserialize_with = "de_offers_from_levels")]
+    pub bids: Vec<OfferData>,
+}
+
+/// Kraken events such as heartbeats, system status and subscription status
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KrakenEvent {
+    pub event: String,
+    pub status: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxArg {
+    pub channel: String,
+    pub inst_id: String,
+}
+
+/// OKX books5 push. Each one is a full 5 level snapshot
+#[derive(Debug, Deserialize)]
+pub struct OkxData {
+    pub arg: OkxArg,
+    pub data: Vec<OkxBook>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OkxBook {
+    /// Bids to be updated
+    #[serde(deserialize_with = "de_offers_from_levels")]
+    pub bids: Vec<OfferData>,
+    /// Asks to be updated
+    #[serde(deserialize_with = "de_offers_from_levels")]
+    pub asks: Vec<OfferData>,
+}
+
+/// OKX subscription acknowledgements and errors
+#[derive(Debug, Deserialize)]
+pub struct OkxEvent {
+    pub event: String,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+}
+
+/// Helper to convert the returned prices and quantities which are encapsulated between quotes AKA strings
+/// to exact decimal types
+pub fn de_decimal_from_str<'a, D>(deserializer: D) -> Result<Decimal, D::Error>
 where
     D: Deserializer<'a>,
 {
     let str_val = String::deserialize(deserializer)?;
-    str_val.parse::<f32>().map_err(de::Error::custom)
+    str_val.parse::<Decimal>().map_err(de::Error::custom)
 }
 
 /// Helper to convert the returned numbers which are encapsulated between quotes AKA strings
@@ -90,13 +248,37 @@ where
     Ok(Some(num))
 }
 
+/// Helper to convert price levels sent as arrays of strings where only the first two entries,
+/// price and quantity, matter. E.g. Kraken's ["price", "volume", "timestamp"]
+pub fn de_offers_from_levels<'a, D>(deserializer: D) -> Result<Vec<OfferData>, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    let levels = Vec::<Vec<String>>::deserialize(deserializer)?;
+
+    levels
+        .into_iter()
+        .map(|level| match level.as_slice() {
+            [price, quantity, ..] => Ok(OfferData {
+                price: price.parse::<Decimal>().map_err(de::Error::custom)?,
+                quantity: quantity.parse::<Decimal>().map_err(de::Error::custom)?,
+            }),
+            _ => Err(de::Error::custom(format!(
+                "Invalid price level: {:?}",
+                level
+            ))),
+        })
+        .collect()
+}
+
 // These are structs used to beautify Client's output
 
 /// Equivalent of Level struct but used to output data
+#[derive(Serialize)]
 pub struct LevelOutput {
-    exchange: String,
-    price: f64,
-    amount: f64,
+    pub exchange: String,
+    pub price: String,
+    pub amount: String,
 }
 
 impl fmt::Debug for LevelOutput {
@@ -114,25 +296,82 @@ impl From<Level> for LevelOutput {
     fn from(level: Level) -> Self {
         LevelOutput {
             exchange: level.exchange,
-            price: level.price,
-            amount: level.amount,
+            price: level.price_decimal,
+            amount: level.amount_decimal,
+        }
+    }
+}
+
+/// Equivalent of ExchangeStatus struct but used to output data
+#[derive(Debug, Serialize)]
+pub struct ExchangeStatusOutput {
+    pub exchange: String,
+    /// Connection status name, e.g. "CONNECTED" or "STALE"
+    pub status: String,
+    /// Milliseconds since the exchange last sent an orderbook, if it ever did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_update_age_ms: Option<u64>,
+}
+
+impl From<ExchangeStatus> for ExchangeStatusOutput {
+    /// Convert from an ExchangeStatus to ExchangeStatusOutput for pretty print
+    fn from(status: ExchangeStatus) -> Self {
+        let name = ConnectionStatus::from_i32(status.status)
+            .map(|status| status.as_str_name())
+            .unwrap_or("UNKNOWN");
+
+        ExchangeStatusOutput {
+            exchange: status.exchange,
+            status: name.to_string(),
+            last_update_age_ms: status.last_update_age_ms,
+        }
+    }
+}
+
+/// Equivalent of Source struct but used to output data
+#[derive(Debug, Serialize)]
+pub struct SourceOutput {
+    pub exchange: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_time_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_id: Option<u64>,
+}
+
+impl From<Source> for SourceOutput {
+    /// Convert from a Source to SourceOutput for pretty print
+    fn from(source: Source) -> Self {
+        SourceOutput {
+            exchange: source.exchange,
+            event_time_us: source.event_time_us,
+            update_id: source.update_id,
         }
     }
 }
 
 /// Equivalent of Summary struct but used to output data
+#[derive(Serialize)]
 pub struct SummaryOutput {
-    pub spread: f64,
+    pub symbol: String,
+    pub spread: String,
     pub asks: Vec<LevelOutput>,
     pub bids: Vec<LevelOutput>,
+    pub exchanges: Vec<ExchangeStatusOutput>,
+    /// Position of the Summary in the stream of the client
+    pub sequence: u64,
+    /// When the server sent the Summary, in microseconds since the Unix epoch
+    pub server_timestamp_us: u64,
+    /// Exchange orderbook the Summary was produced by, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceOutput>,
 }
 
 impl fmt::Debug for SummaryOutput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{{\n\tspread: \"{}\",\n\tasks: {:#?},\n\tbids: {:#?}\n}}",
-            self.spread, self.asks, self.bids
+            "{{\n\tsymbol: \"{}\",\n\tspread: \"{}\",\n\tasks: {:#?},\n\tbids: {:#?}\n}}",
+            self.symbol, self.spread, self.asks, self.bids
         )
     }
 }
@@ -142,11 +381,21 @@ impl From<Summary> for SummaryOutput {
     fn from(summary: Summary) -> Self {
         let asks = summary.asks.into_iter().map(LevelOutput::from).collect();
         let bids = summary.bids.into_iter().map(LevelOutput::from).collect();
+        let exchanges = summary
+            .exchanges
+            .into_iter()
+            .map(ExchangeStatusOutput::from)
+            .collect();
 
         SummaryOutput {
-            spread: summary.spread,
+            symbol: summary.symbol,
+            spread: summary.spread_decimal,
             asks,
             bids,
+            exchanges,
+            sequence: summary.sequence,
+            server_timestamp_us: summary.server_timestamp_us,
+            source: summary.source.map(SourceOutput::from),
         }
     }
 }
@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::Sender;
 
 use super::mapper::{Exchange, OfferData};
 
@@ -10,14 +14,43 @@ use super::mapper::{Exchange, OfferData};
 pub enum OrderbookMessage {
     /// Message to be sent to broadcast queue
     Message { message: Box<Orders> },
+    /// Connection status change of an exchange feed
+    Status {
+        exchange: Exchange,
+        status: ConnectionStatus,
+    },
+}
+
+/// State of the connection to an exchange Web Socket
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// Connected and subscribed to the orderbook
+    Connected,
+    /// Connection dropped, we're waiting to reconnect
+    Disconnected,
 }
 
 /// Struct to hold the "buy" and "sell"s of a certain orderbook
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Orders {
     pub exchange: Exchange,
+    /// Symbol (currency pair) of the orderbook, e.g. "ethbtc"
+    pub symbol: String,
     /// Bids to be updated
     pub bids: Vec<OfferData>,
     /// Asks to be updated
     pub asks: Vec<OfferData>,
+    /// When the frame carrying the orderbook was received, to measure latency. Set by the listeners
+    #[serde(skip)]
+    pub received_at: Option<Instant>,
+    /// When the exchange produced the orderbook, in microseconds since the Unix epoch, if it tells
+    #[serde(default)]
+    pub event_time_us: Option<u64>,
+    /// Update ID of the exchange the orderbook is up to, if it has one
+    #[serde(default)]
+    pub update_id: Option<u64>,
 }
+
+/// Broadcast channel of every symbol we're streaming. Each symbol gets its own channel so that
+/// clients only receive the orderbooks they asked for.
+pub type SymbolChannels = HashMap<String, Sender<OrderbookMessage>>;
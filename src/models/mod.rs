@@ -1,6 +1,17 @@
+pub mod aggregator;
+pub mod arbitrage;
+pub mod book;
+pub mod config;
+pub mod connectors;
 pub mod consts;
 pub mod errors;
+pub mod latest_books;
 pub mod mapper;
 pub mod messages;
+pub mod recorder;
+pub mod replay;
 pub mod stream;
 pub mod stream_service;
+pub mod subscribers;
+pub mod supervisor;
+pub mod validation;
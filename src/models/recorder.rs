@@ -0,0 +1,252 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use super::{config::RecorderConfig, consts::RECORDER_BUFFER_LIMIT, mapper::Exchange};
+
+/// A single line of a recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Microseconds since the UNIX epoch at which the frame was received
+    pub received_at_us: u64,
+    pub exchange: Exchange,
+    /// Symbol the frame was parsed into, if any
+    pub symbol: Option<String>,
+    /// Raw frame
+    pub frame: String,
+}
+
+/// File being written and when it was started
+struct RecordingFile {
+    encoder: GzEncoder<BufWriter<File>>,
+    started: Instant,
+    /// Uncompressed bytes written so far
+    bytes: u64,
+}
+
+/// Writes the raw frames of one exchange to rotating gzip files of JSON lines. See the README for the format
+pub struct Recorder {
+    dir: PathBuf,
+    exchange: Exchange,
+    rotate: Duration,
+    max_file_bytes: u64,
+    flush: Duration,
+    file: Option<RecordingFile>,
+    last_flush: Instant,
+    /// Number of files started, which tells apart files started within the same millisecond
+    sequence: u64,
+}
+
+impl Recorder {
+    /// Recorder of `exchange`, if recording is enabled in `config`
+    pub fn from_config(config: &RecorderConfig, exchange: Exchange) -> Option<Self> {
+        config.dir.as_ref().map(|dir| Recorder {
+            dir: dir.clone(),
+            exchange,
+            rotate: Duration::from_secs(config.rotate_secs),
+            max_file_bytes: config.max_file_bytes,
+            flush: Duration::from_secs(config.flush_secs),
+            file: None,
+            last_flush: Instant::now(),
+            sequence: 0,
+        })
+    }
+
+    /// Appends `frame`, received at `received_at`, starting a new file first if the current one is due
+    pub fn record(
+        &mut self,
+        received_at: SystemTime,
+        symbol: Option<&str>,
+        frame: &str,
+    ) -> Result<()> {
+        let line = serde_json::to_string(&RecordedFrame {
+            received_at_us: received_at.duration_since(UNIX_EPOCH)?.as_micros() as u64,
+            exchange: self.exchange,
+            symbol: symbol.map(str::to_string),
+            frame: frame.to_string(),
+        })?;
+
+        let due = self.file.as_ref().is_some_and(|file| {
+            file.started.elapsed() >= self.rotate || file.bytes >= self.max_file_bytes
+        });
+        if due {
+            self.finish()?;
+        }
+        if self.file.is_none() {
+            self.file = Some(self.start_file()?);
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            file.encoder.write_all(line.as_bytes())?;
+            file.encoder.write_all(b"\n")?;
+            file.bytes += line.len() as u64 + 1;
+        }
+
+        if self.last_flush.elapsed() >= self.flush {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the frames recorded so far to disk
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.encoder.flush()?;
+        }
+        self.last_flush = Instant::now();
+
+        Ok(())
+    }
+
+    /// Completes the current file, if any. The next frame starts a new one
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            file.encoder.finish()?.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Helper to create the next file, never touching existing ones
+    fn start_file(&mut self) -> Result<RecordingFile> {
+        fs::create_dir_all(&self.dir)?;
+
+        let started_at_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = self.dir.join(format!(
+            "{}-{}-{}.jsonl.gz",
+            self.exchange.to_string().to_lowercase(),
+            started_at_ms,
+            self.sequence
+        ));
+        self.sequence += 1;
+
+        log::info!("Recording {} frames to {}", self.exchange, path.display());
+        let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+
+        Ok(RecordingFile {
+            encoder: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            started: Instant::now(),
+            bytes: 0,
+        })
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(error) = self.finish() {
+            log::warn!(
+                "Failed to complete {} recording. Error: {:?}",
+                self.exchange,
+                error
+            );
+        }
+    }
+}
+
+/// What the thread of a `RecorderHandle` does next
+enum Command {
+    Record {
+        received_at: SystemTime,
+        symbol: Option<String>,
+        frame: String,
+    },
+    Flush,
+    Finish(oneshot::Sender<Result<()>>),
+}
+
+/// Feeds a `Recorder` running on a blocking thread of its own, so that writing files never blocks
+/// the listener
+pub struct RecorderHandle {
+    exchange: Exchange,
+    flush: Duration,
+    commands: mpsc::Sender<Command>,
+}
+
+impl RecorderHandle {
+    /// Moves `recorder` to a blocking thread, which completes its file once the handle is dropped
+    pub fn spawn(mut recorder: Recorder) -> Self {
+        let (exchange, flush) = (recorder.exchange, recorder.flush);
+        let (commands, mut commands_recv) = mpsc::channel(RECORDER_BUFFER_LIMIT);
+
+        tokio::task::spawn_blocking(move || {
+            while let Some(command) = commands_recv.blocking_recv() {
+                match command {
+                    Command::Record {
+                        received_at,
+                        symbol,
+                        frame,
+                    } => {
+                        if let Err(error) = recorder.record(received_at, symbol.as_deref(), &frame)
+                        {
+                            log::warn!(
+                                "Failed to record {} frame. Error: {:?}",
+                                recorder.exchange,
+                                error
+                            );
+                        }
+                    }
+                    Command::Flush => {
+                        if let Err(error) = recorder.flush() {
+                            log::warn!(
+                                "Failed to flush {} recording. Error: {:?}",
+                                recorder.exchange,
+                                error
+                            );
+                        }
+                    }
+                    Command::Finish(done) => {
+                        let _ = done.send(recorder.finish());
+                    }
+                }
+            }
+        });
+
+        RecorderHandle {
+            exchange,
+            flush,
+            commands,
+        }
+    }
+
+    /// Queues `frame`, received at `received_at`, to be recorded. Fails instead of waiting if the
+    /// recorder fell behind
+    pub fn record(&self, received_at: SystemTime, symbol: Option<&str>, frame: &str) -> Result<()> {
+        self.commands
+            .try_send(Command::Record {
+                received_at,
+                symbol: symbol.map(str::to_string),
+                frame: frame.to_string(),
+            })
+            .map_err(|error| anyhow!("{} recorder can't keep up: {}", self.exchange, error))
+    }
+
+    /// Time between flushes, which the caller has to request while no frames come in
+    pub fn flush_period(&self) -> Duration {
+        self.flush
+    }
+
+    /// Queues a flush of the frames recorded so far
+    pub fn flush(&self) {
+        let _ = self.commands.try_send(Command::Flush);
+    }
+
+    /// Completes the current file once the frames queued before are recorded
+    pub async fn finish(&self) -> Result<()> {
+        let (done, done_recv) = oneshot::channel();
+        self.commands
+            .send(Command::Finish(done))
+            .await
+            .map_err(|_| anyhow!("{} recorder is gone", self.exchange))?;
+
+        done_recv
+            .await
+            .map_err(|_| anyhow!("{} recorder is gone", self.exchange))?
+    }
+}
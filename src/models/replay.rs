@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc,
+    time::{sleep_until, Instant},
+};
+
+use crate::server::metrics::Metrics;
+
+use super::{
+    connectors::ExchangeConnector,
+    consts::REPLAY_READ_AHEAD,
+    errors::OrderbookError,
+    mapper::Exchange,
+    messages::{ConnectionStatus, SymbolChannels},
+    recorder::RecordedFrame,
+    stream::send_orders,
+    subscribers::Subscribers,
+    supervisor::send_status,
+    validation::validate_book,
+};
+
+/// How fast recorded frames are played back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ReplaySpeed {
+    /// Keep the time between frames they were received with
+    #[default]
+    RealTime,
+    /// Divide the time between frames by this factor, e.g. "10x"
+    Accelerated(u32),
+    /// Don't wait between frames at all
+    Max,
+}
+
+impl ReplaySpeed {
+    /// Time to wait before playing a frame received `elapsed` after the first one
+    fn offset(&self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            ReplaySpeed::RealTime => Some(elapsed),
+            ReplaySpeed::Accelerated(factor) => Some(elapsed / *factor),
+            ReplaySpeed::Max => None,
+        }
+    }
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    /// Either "realtime", "max" or an acceleration factor like "10x"
+    fn from_str(speed: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid replay speed: {}. Use realtime, max or a factor like 10x",
+                speed
+            )
+        };
+
+        match speed.to_lowercase().as_str() {
+            "realtime" | "1x" => Ok(ReplaySpeed::RealTime),
+            "max" => Ok(ReplaySpeed::Max),
+            factor => match factor.strip_suffix('x').map(str::parse::<u32>) {
+                Some(Ok(factor)) if factor > 0 => Ok(ReplaySpeed::Accelerated(factor)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaySpeed::RealTime => write!(f, "realtime"),
+            ReplaySpeed::Accelerated(factor) => write!(f, "{}x", factor),
+            ReplaySpeed::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl TryFrom<String> for ReplaySpeed {
+    type Error = String;
+
+    fn try_from(speed: String) -> Result<Self, Self::Error> {
+        speed.parse()
+    }
+}
+
+impl From<ReplaySpeed> for String {
+    fn from(speed: ReplaySpeed) -> Self {
+        speed.to_string()
+    }
+}
+
+/// Frames of a single recording, in the order they were received
+type RecordingFrames = Peekable<Box<dyn Iterator<Item = Result<RecordedFrame>> + Send>>;
+
+/// Helper to open the recording at `path` as an iterator of frames
+fn open_recording(path: &Path) -> Result<RecordingFrames> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open recording {}", path.display()))?;
+    let lines = BufReader::new(MultiGzDecoder::new(file)).lines();
+    let path = path.to_path_buf();
+
+    let frames: Box<dyn Iterator<Item = Result<RecordedFrame>> + Send> =
+        Box::new(lines.map_while(move |line| {
+            let line = match line {
+                Ok(line) => line,
+                // Recordings cut short by a crash end with a truncated gzip member
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                    log::warn!("{} ends early. Replaying up to there", path.display());
+                    return None;
+                }
+                Err(error) => {
+                    let error = anyhow::Error::new(error);
+                    return Some(Err(
+                        error.context(format!("Failed to read {}", path.display()))
+                    ));
+                }
+            };
+            Some(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("Invalid frame in {}: {}", path.display(), line)),
+            )
+        }));
+
+    Ok(frames.peekable())
+}
+
+/// Plays the recordings at `paths` through the same parsing and broadcast path as the live listeners,
+/// merged by the time their frames were received and paced by `speed`
+// Every argument is a piece of state the replay task owns for as long as it plays
+#[allow(clippy::too_many_arguments)]
+pub async fn replay(
+    paths: &[PathBuf],
+    connectors: Vec<Box<dyn ExchangeConnector>>,
+    symbols: &[String],
+    channels: &SymbolChannels,
+    speed: ReplaySpeed,
+    subscribers: &Subscribers,
+    wait_for_clients: bool,
+    metrics: &Metrics,
+) -> Result<()> {
+    let recordings = paths
+        .iter()
+        .map(|path| open_recording(path))
+        .collect::<Result<Vec<_>>>()?;
+    let mut connectors: HashMap<Exchange, Box<dyn ExchangeConnector>> = connectors
+        .into_iter()
+        .map(|connector| (connector.exchange(), connector))
+        .collect();
+
+    if wait_for_clients {
+        log::info!("Waiting for a client to subscribe before replaying");
+        subscribers.wait().await;
+    }
+
+    log::info!("Replaying {} recordings at {} speed", paths.len(), speed);
+    let started = Instant::now();
+    let mut first_received_at_us = None;
+    let mut connected = HashSet::new();
+    let mut replayed = 0;
+
+    // Decompressing the recordings blocks, so they're read on a thread of their own
+    let (frames_send, mut frames_recv) = mpsc::channel(REPLAY_READ_AHEAD);
+    tokio::task::spawn_blocking(move || read_frames(recordings, frames_send));
+
+    while let Some(frame) = frames_recv.recv().await.transpose()? {
+        let connector = match connectors.get_mut(&frame.exchange) {
+            Some(connector) => connector,
+            None => continue,
+        };
+
+        let first = *first_received_at_us.get_or_insert(frame.received_at_us);
+        let elapsed = Duration::from_micros(frame.received_at_us.saturating_sub(first));
+        match speed.offset(elapsed) {
+            Some(offset) => sleep_until(started + offset).await,
+            // Let the clients catch up so that they don't lag behind the broadcast channels
+            None => tokio::task::yield_now().await,
+        }
+
+        metrics.message_received(frame.exchange);
+        // Never reported disconnected so that clients keep the last books once the recordings are over
+        if connected.insert(frame.exchange) {
+            connector.on_connect(symbols).await?;
+            send_status(channels, frame.exchange, ConnectionStatus::Connected);
+        }
+
+        let mut orders = match connector.parse(&frame.frame) {
+            Ok(Some(orders)) => orders,
+            Ok(None) => continue,
+            Err(error) => {
+                let error = OrderbookError::parse(frame.exchange, error);
+                metrics.error(&error);
+                // There's no new connection to resync from so we start over from the next snapshot
+                if error.requires_resync() {
+                    log::warn!("{} book out of sync. Error: {:?}", frame.exchange, error);
+                    connector.on_connect(symbols).await?;
+                } else {
+                    log::warn!(
+                        "Dropping {} message. Error: {:?}. String: {}",
+                        frame.exchange,
+                        error,
+                        &frame.frame
+                    );
+                }
+                continue;
+            }
+        };
+
+        orders.received_at = Some(Instant::now().into_std());
+
+        if let Err(error) = validate_book(&orders) {
+            metrics.error(&error);
+            log::warn!(
+                "Quarantined {} orderbook. Error: {}. String: {}",
+                frame.exchange,
+                error,
+                &frame.frame
+            );
+            continue;
+        }
+
+        // Nobody listening to a symbol isn't an error while replaying
+        if send_orders(orders, channels, subscribers).is_err() {
+            metrics.broadcast_dropped(frame.exchange);
+        }
+        replayed += 1;
+    }
+
+    log::info!(
+        "Replay finished after {:?}. {} orderbooks replayed",
+        started.elapsed(),
+        replayed
+    );
+
+    Ok(())
+}
+
+/// Helper to send the frames of all recordings to `frames_send` in the order they were received,
+/// until one fails to read or the replay is over
+fn read_frames(
+    mut recordings: Vec<RecordingFrames>,
+    frames_send: mpsc::Sender<Result<RecordedFrame>>,
+) {
+    while let Some(frame) = next_frame(&mut recordings).transpose() {
+        let failed = frame.is_err();
+        if frames_send.blocking_send(frame).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Helper to take the earliest frame out of all recordings
+fn next_frame(recordings: &mut [RecordingFrames]) -> Result<Option<RecordedFrame>> {
+    let mut earliest: Option<(usize, u64)> = None;
+
+    for (index, frames) in recordings.iter_mut().enumerate() {
+        let received_at_us = match frames.peek() {
+            Some(Ok(frame)) => frame.received_at_us,
+            // Surface the error right away
+            Some(Err(_)) => return frames.next().transpose(),
+            None => continue,
+        };
+        if earliest.is_none_or(|(_, earliest_us)| received_at_us < earliest_us) {
+            earliest = Some((index, received_at_us));
+        }
+    }
+
+    match earliest {
+        Some((index, _)) => recordings[index].next().transpose(),
+        None => Ok(None),
+    }
+}
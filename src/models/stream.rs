@@ -1,181 +1,198 @@
-use anyhow::Result;
+use std::time::{Instant, SystemTime};
+
+use anyhow::{anyhow, bail, Result};
 use futures_util::{SinkExt, StreamExt};
-use serde_json::json;
-use tokio::sync::broadcast::Sender;
+use tokio::time::interval_at;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use url::Url;
 
-use crate::models::{
-    consts::{
-        BINANCE_WS_API, BITSTAMP_WS_API, DEPTH_LEVEL_BINANCE, ERR_COUNT_LOG, UPDATE_SPEED_BINANCE,
-    },
-    mapper::{BinanceStreamData, BitstampData},
-};
+use crate::server::{metrics::Metrics, shutdown::Shutdown};
 
 use super::{
-    mapper::Exchange,
-    messages::{OrderbookMessage, Orders},
+    connectors::ExchangeConnector,
+    errors::OrderbookError,
+    messages::{ConnectionStatus, OrderbookMessage, Orders, SymbolChannels},
+    recorder::RecorderHandle,
+    subscribers::Subscribers,
+    supervisor::send_status,
+    validation::validate_book,
 };
 
-/// Binance streamer
-/// 1. Connects to the Binance Web Socket which already contains the orderbook that we want to subscribe to
-/// 2. Indefinitely listens for bitstamp orderbooks
-/// 2.1 For each orderbook received it sends over the broadcast channel to be agregated and ordered by our server
-pub async fn binance_data_listen(
-    symbol: String,
-    chan_send: Sender<OrderbookMessage>,
-) -> Result<()> {
-    let url = format!(
-        "{}/ws/{}@{}@{}",
-        BINANCE_WS_API, &symbol, DEPTH_LEVEL_BINANCE, UPDATE_SPEED_BINANCE
-    );
-    log::info!("Listening for Binance orderbooks at: {}", &url);
-    let url = Url::parse(&url).expect("Bad Binance URL!");
-    let (mut ws_stream, _) = connect_async(url).await?;
+/// Generic exchange streamer.
+/// 1. Connects to the exchange Web Socket given by the connector and subscribes to all `symbols`
+/// 2. Indefinitely listens for orderbooks and sends the valid ones over the broadcast channel of their symbol
+/// 3. Returns once the connection drops, a book goes out of sync, a subscription is rejected or `shutdown` is requested
+// Every argument is a piece of state the supervisor lends to the connection
+#[allow(clippy::too_many_arguments)]
+pub async fn listen(
+    connector: &mut dyn ExchangeConnector,
+    symbols: &[String],
+    channels: &SymbolChannels,
+    subscribers: &Subscribers,
+    err_count_log: i32,
+    recorder: Option<&RecorderHandle>,
+    metrics: &Metrics,
+    shutdown: &Shutdown,
+) -> Result<(), OrderbookError> {
+    let exchange = connector.exchange();
+    let url = connector.url(symbols);
+    log::info!("Listening for {} orderbooks at: {}", exchange, &url);
+    let url = Url::parse(&url).map_err(|error| OrderbookError::connection(exchange, error))?;
+    let (mut ws_stream, _) = tokio::select! {
+        connected = connect_async(url) => connected.map_err(|error| OrderbookError::connection(exchange, error))?,
+        _ = shutdown.requested() => return Ok(()),
+    };
 
-    let mut err_count = 0;
+    for message in connector.subscribe_messages(symbols) {
+        ws_stream
+            .send(message)
+            .await
+            .map_err(|error| OrderbookError::connection(exchange, error))?;
+    }
+    // Fetching snapshots may take a while
+    tokio::select! {
+        connected = connector.on_connect(symbols) => connected.map_err(|error| OrderbookError::connection(exchange, error))?,
+        _ = shutdown.requested() => return Ok(()),
+    }
 
-    while let Some(msg) = ws_stream.next().await {
-        let msg = msg?;
+    send_status(channels, exchange, ConnectionStatus::Connected);
 
-        let msg_str = msg.into_text()?;
+    let heartbeat = connector.heartbeat();
+    let mut heartbeat_timer = heartbeat
+        .as_ref()
+        .map(|(period, _)| interval_at((Instant::now() + *period).into(), *period));
 
-        let parsed: BinanceStreamData = match serde_json::from_str(&msg_str) {
-            Ok(val) => val,
-            Err(error) => {
-                log::warn!(
-                    "Can't parse Binance data, dropping message. Error: {:?}. String: {}",
-                    error,
-                    &msg_str
-                );
+    // Quiet feeds would otherwise leave their last frames unflushed for as long as they're quiet
+    let mut flush_timer = recorder.map(|recorder| {
+        let period = recorder.flush_period();
+        interval_at((Instant::now() + period).into(), period)
+    });
+
+    let mut err_count = 0;
+
+    loop {
+        let msg = tokio::select! {
+            msg = ws_stream.next() => msg,
+            // Only polled for exchanges with an application level heartbeat
+            _ = async { heartbeat_timer.as_mut().unwrap().tick().await }, if heartbeat_timer.is_some() => {
+                if let Some((_, message)) = &heartbeat {
+                    ws_stream.send(message.clone()).await.map_err(|error| OrderbookError::connection(exchange, error))?;
+                }
                 continue;
             }
+            // Only polled while recording
+            _ = async { flush_timer.as_mut().unwrap().tick().await }, if flush_timer.is_some() => {
+                if let Some(recorder) = recorder {
+                    recorder.flush();
+                }
+                continue;
+            }
+            _ = shutdown.requested() => {
+                log::info!("Unsubscribing from {} and closing the connection", exchange);
+                for message in connector.unsubscribe_messages(symbols) {
+                    ws_stream.send(message).await.map_err(|error| OrderbookError::connection(exchange, error))?;
+                }
+                ws_stream.close(None).await.map_err(|error| OrderbookError::connection(exchange, error))?;
+                return Ok(());
+            }
         };
 
-        if send_binance_data(parsed, &chan_send).await.is_err() {
-            err_count += 1;
+        let msg_str = match msg {
+            Some(msg) => match msg.map_err(|error| OrderbookError::connection(exchange, error))? {
+                Message::Text(msg_str) => msg_str,
+                // Pings are answered by tungstenite and a close frame ends the stream
+                _ => continue,
+            },
+            None => break,
+        };
+        let received_at = SystemTime::now();
+        let received = Instant::now();
+        metrics.message_received(exchange);
+
+        let parsed = connector.parse(&msg_str);
+
+        if let Some(recorder) = recorder {
+            let symbol = match &parsed {
+                Ok(Some(orders)) => Some(orders.symbol.as_str()),
+                _ => None,
+            };
+            // Losing the recording is better than losing the feed
+            if let Err(error) = recorder.record(received_at, symbol, &msg_str) {
+                log::warn!("Failed to record {} frame. Error: {:?}", exchange, error);
+            }
         }
 
-        if err_count > ERR_COUNT_LOG {
+        let mut orders = match parsed {
+            Ok(Some(orders)) => orders,
+            Ok(None) => continue,
+            Err(error) => match OrderbookError::parse(exchange, error) {
+                // The local book is out of sync, we have to start over with a new connection
+                error if error.requires_resync() => return Err(error),
+                // Nothing comes through a rejected subscription, so we back off and subscribe again
+                error @ OrderbookError::SubscriptionRejected { .. } => return Err(error),
+                error => {
+                    metrics.error(&error);
+                    log::warn!(
+                        "Dropping {} message. Error: {:?}. String: {}",
+                        exchange,
+                        error,
+                        &msg_str
+                    );
+                    continue;
+                }
+            },
+        };
+        orders.received_at = Some(received);
+
+        if let Err(error) = validate_book(&orders) {
+            metrics.error(&error);
             log::warn!(
-                "Failed to send {} binance orderbooks. No clients connected.",
-                ERR_COUNT_LOG
+                "Quarantined {} orderbook. Error: {}. String: {}",
+                exchange,
+                error,
+                &msg_str
             );
-            err_count = 0;
-        }
-    }
-
-    // Unreachable code but we like to do the right thing and close the stream eventually :)
-    ws_stream.close(None).await?;
-
-    Ok(())
-}
-
-/// Helper to send binance data to broadcast channel
-async fn send_binance_data(
-    data: BinanceStreamData,
-    chan_send: &Sender<OrderbookMessage>,
-) -> Result<()> {
-    let message = OrderbookMessage::Message {
-        message: Box::new(Orders {
-            exchange: Exchange::Binance,
-            asks: data.asks,
-            bids: data.bids,
-        }),
-    };
-
-    chan_send.send(message)?;
-
-    Ok(())
-}
-
-/// Bitstamp streamer.
-/// 1. Connects to the bitstamp Web Socket
-/// 2. Subscribes to an orderbook
-/// 3. Indefinitely listens for bitstamp orderbooks
-/// 3.1 For each orderbook received it sends over the broadcast channel to be agregated and ordered by our server
-pub async fn bitstamp_data_listen(
-    symbol: String,
-    chan_send: Sender<OrderbookMessage>,
-) -> Result<()> {
-    log::info!("Listening for Bitstamp orderbooks at: {}", BITSTAMP_WS_API);
-    let url = Url::parse(BITSTAMP_WS_API).expect("Bad Bitstamp URL!");
-    let (mut ws_stream, _) = connect_async(url).await?;
-
-    let order_book = format!("order_book_{}", &symbol);
-
-    let subscribe_msg = json!({
-        "event": "bts:subscribe",
-        "data": {
-            "channel": order_book
+            continue;
         }
-    });
-
-    let message = Message::Text(subscribe_msg.to_string());
-    ws_stream.send(message).await?;
 
-    let mut err_count = 0;
-
-    while let Some(msg) = ws_stream.next().await {
-        let msg = msg?;
-
-        let msg_str = msg.into_text()?;
-
-        let parsed: BitstampData = match serde_json::from_str(&msg_str) {
-            Ok(val) => val,
-            Err(error) => {
-                log::warn!(
-                    "Can't parse bitstamp data, dropping message. Error: {:?}. String: {}",
-                    error,
-                    &msg_str
-                );
-                continue;
-            }
-        };
-
-        if parsed.data.timestamp.is_some() && send_bitstamp_data(parsed, &chan_send).await.is_err()
-        {
+        if send_orders(orders, channels, subscribers).is_err() {
+            metrics.broadcast_dropped(exchange);
             err_count += 1;
         }
 
-        if err_count > ERR_COUNT_LOG {
+        if err_count > err_count_log {
             log::warn!(
-                "Failed to send {} bitstamp orderbooks. No clients connected.",
-                ERR_COUNT_LOG
+                "Failed to send {} {} orderbooks. No clients connected.",
+                err_count_log,
+                exchange
             );
             err_count = 0;
         }
     }
 
-    let unsubscribe_msg = json!({
-        "event": "bts:unsubscribe",
-        "data": {
-            "channel": order_book
-        }
-    });
-    let message = Message::Text(unsubscribe_msg.to_string());
-    ws_stream.send(message).await?;
-
-    // Unreachable code but we like to do the right thing and close the stream eventually :)
-    ws_stream.close(None).await?;
-
+    // The exchange closed the connection so there's nothing left to unsubscribe from or close
     Ok(())
 }
 
-/// Helper to send binance data to mpsc channel
-async fn send_bitstamp_data(
-    data: BitstampData,
-    chan_send: &Sender<OrderbookMessage>,
+/// Helper to send orders to the broadcast channel of their symbol, failing if no client stream is
+/// subscribed
+pub(crate) fn send_orders(
+    orders: Orders,
+    channels: &SymbolChannels,
+    subscribers: &Subscribers,
 ) -> Result<()> {
+    let chan_send = channels
+        .get(&orders.symbol)
+        .ok_or_else(|| anyhow!("Not streaming symbol {}", orders.symbol))?;
     let message = OrderbookMessage::Message {
-        message: Box::new(Orders {
-            exchange: Exchange::Bitstamp,
-            asks: data.data.asks.unwrap(),
-            bids: data.data.bids.unwrap(),
-        }),
+        message: Box::new(orders),
     };
 
     chan_send.send(message)?;
+    // The server's own receivers keep the channels open, so client streams are counted apart
+    if subscribers.count() == 0 {
+        bail!("No client subscribed");
+    }
 
     Ok(())
 }
@@ -1,153 +1,594 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::Result;
-use grpc_server::orderbook::{Level, Summary};
+use clap::ValueEnum;
+use grpc_server::orderbook::{self as proto, Lag, Summary};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{
-    broadcast::{self, Receiver, Sender},
+    broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+        Receiver,
+    },
     mpsc,
 };
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
+use tonic::Status;
 
-use crate::server::grpc_server::{self, ResultSummary};
+use crate::server::{
+    grpc_server::{self, ResultArbitrageEvent, ResultSummary},
+    metrics::{ClientMetrics, Metrics},
+    shutdown::Shutdown,
+};
 
 use super::{
-    consts::{CHANNEL_BUFFER_LIMIT, MAX_PAIR_EXCHANGE},
+    aggregator::Aggregator,
+    arbitrage::ArbitrageDetector,
+    config::{RecorderConfig, ReplayConfig, ServerConfig},
+    connectors::{normalize_symbol, ExchangeConnector},
+    consts::MAX_PAIR_EXCHANGE,
     errors::OrderbookError,
-    mapper::{Exchange, OfferData},
-    messages::OrderbookMessage,
-    stream::{binance_data_listen, bitstamp_data_listen},
+    latest_books::LatestBooks,
+    mapper::Exchange,
+    messages::{OrderbookMessage, SymbolChannels},
+    recorder::{Recorder, RecorderHandle},
+    replay::replay,
+    subscribers::Subscribers,
+    supervisor::{supervise, Backoff},
 };
 
 pub mod orderbook {
     tonic::include_proto!("orderbook");
 }
 
+/// How the stream of a client goes on once it falls behind the broadcast channel of a symbol.
+/// The client is caught up with the latest update either way, and told what happened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LagPolicy {
+    /// Drop the missed updates and resume with the latest ones. The next Summary carries the
+    /// number of updates skipped
+    #[default]
+    Skip,
+    /// Rebuild the books from the latest update of every exchange and send them right away
+    Snapshot,
+    /// End the stream with a RESOURCE_EXHAUSTED status
+    Terminate,
+}
+
+/// What a client asked to receive for each of its symbols
+#[derive(Debug, Clone)]
+pub struct ClientView {
+    /// Levels per side of the merged ladder
+    pub depth: usize,
+    /// Exchanges to merge. Every exchange is merged when None
+    pub exchanges: Option<HashSet<Exchange>>,
+    /// Minimum time between summaries. Every update produces a summary when None
+    pub throttle: Option<Duration>,
+    /// What happens if the client falls behind. Set by the server
+    pub lag_policy: LagPolicy,
+    /// Time without an orderbook after which an exchange is flagged stale and its levels left
+    /// out. Set by the server, exchanges never go stale when None
+    pub stale_after: Option<Duration>,
+}
+
+impl Default for ClientView {
+    fn default() -> Self {
+        ClientView {
+            depth: MAX_PAIR_EXCHANGE,
+            exchanges: None,
+            throttle: None,
+            lag_policy: LagPolicy::default(),
+            stale_after: None,
+        }
+    }
+}
+
+/// Number of the last Summary sent on a client stream, shared by every symbol of the stream
+pub type StreamSequence = Arc<tokio::sync::Mutex<u64>>;
+
+/// Orderbook that produced a Summary
+#[derive(Debug, Clone)]
+struct Origin {
+    exchange: Exchange,
+    /// When the server received the orderbook, if known
+    received_at: Option<std::time::Instant>,
+    /// Exchange timestamp of the orderbook in microseconds, if the exchange sends one
+    event_time_us: Option<u64>,
+    /// Exchange update id of the orderbook, if the exchange sends one
+    update_id: Option<u64>,
+}
+
+impl Origin {
+    /// Origin of the orderbook in `msg`. None for status changes
+    fn of(msg: &OrderbookMessage) -> Option<Origin> {
+        match msg {
+            OrderbookMessage::Message { message } => Some(Origin {
+                exchange: message.exchange,
+                received_at: message.received_at,
+                event_time_us: message.event_time_us,
+                update_id: message.update_id,
+            }),
+            OrderbookMessage::Status { .. } => None,
+        }
+    }
+
+    fn source(&self) -> proto::Source {
+        proto::Source {
+            exchange: self.exchange.to_string(),
+            event_time_us: self.event_time_us,
+            update_id: self.update_id,
+        }
+    }
+}
+
 pub struct StreamService {
-    pub symbol: String,
-    /// Private sender that sends message to channel
-    chan_send: Sender<OrderbookMessage>,
-    /// Private reciever that gets the messages sent by send
-    _chan_recv: Receiver<OrderbookMessage>,
+    /// Normalized symbols we'll be streaming, e.g. "ethbtc"
+    pub symbols: Vec<String>,
+    /// Exchanges we'll be streaming orderbooks from
+    connectors: Vec<Box<dyn ExchangeConnector>>,
+    /// Private senders that send messages to the channel of each symbol
+    channels: SymbolChannels,
+    /// Delays between reconnection attempts to an exchange
+    backoff: Backoff,
+    /// Broadcast error count limit before we display a warning
+    err_count_log: i32,
+    /// Where and how the raw frames of every exchange are recorded
+    recorder: RecorderConfig,
+    /// Recordings played instead of streaming from the exchanges, if any
+    replay: ReplayConfig,
+    /// Where the listeners count what they receive
+    metrics: Arc<Metrics>,
+    /// Tells the listeners when to unsubscribe and close their connections
+    shutdown: Shutdown,
+    /// Client streams of the symbols, which the replay may wait for
+    subscribers: Subscribers,
 }
 
 impl StreamService {
-    pub fn new(symbol: Option<String>) -> Self {
-        let symbol = if let Some(symbol) = symbol {
-            symbol
-        } else {
-            dotenv::var("ORDERBOOK_SYMBOL").expect("could not find env var ORDERBOOK_SYMBOL")
-        };
-
-        StreamService::init_service(symbol)
+    /// Streams the symbols in `config` from every connector, counting what they receive in `metrics`,
+    /// until `shutdown` is requested
+    pub fn new(
+        config: &ServerConfig,
+        connectors: Vec<Box<dyn ExchangeConnector>>,
+        metrics: Arc<Metrics>,
+        shutdown: Shutdown,
+    ) -> Self {
+        StreamService::init_service(config, connectors, metrics, shutdown)
     }
 
-    /// Initializes the service that spawns orderbook threads
-    fn init_service(symbol: String) -> StreamService {
-        let (chan_send, chan_recv) = broadcast::channel::<OrderbookMessage>(CHANNEL_BUFFER_LIMIT);
+    /// Initializes the service that spawns orderbook threads, with one broadcast channel per symbol
+    fn init_service(
+        config: &ServerConfig,
+        connectors: Vec<Box<dyn ExchangeConnector>>,
+        metrics: Arc<Metrics>,
+        shutdown: Shutdown,
+    ) -> StreamService {
+        let mut symbols: Vec<String> = config
+            .symbols
+            .iter()
+            .map(|symbol| normalize_symbol(symbol.trim()))
+            .filter(|symbol| !symbol.is_empty())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+
+        let mut channels = HashMap::new();
+        for symbol in &symbols {
+            // No receiver is kept around, orderbooks sent while nobody listens are counted as dropped
+            let (chan_send, _) =
+                broadcast::channel::<OrderbookMessage>(config.channel_buffer_limit);
+            channels.insert(symbol.clone(), chan_send);
+        }
+
         StreamService {
-            symbol,
-            chan_send,
-            _chan_recv: chan_recv,
+            symbols,
+            connectors,
+            channels,
+            backoff: Backoff::new(
+                Duration::from_millis(config.backoff_initial_ms),
+                Duration::from_millis(config.backoff_max_ms),
+            ),
+            err_count_log: config.err_count_log,
+            recorder: config.recorder.clone(),
+            replay: config.replay.clone(),
+            metrics,
+            shutdown,
+            subscribers: Subscribers::default(),
         }
     }
 
-    /// Spawns two threads that will be listening for orders in 2 different exchanges:
-    /// - Binance
-    /// - Bitstamp
-    /// Additionaly they'll be sending orderbooks through a multi-producer, multi-consumer
-    /// broadcast queue so that we can combine and order the data.
-    pub async fn run(self) -> Result<Sender<OrderbookMessage>> {
-        let cloned_symbol = self.symbol.clone();
-        let chan_send_cloned = self.chan_send.clone();
+    /// Client streams to count in, so that the replay can wait for them
+    pub fn subscribers(&self) -> Subscribers {
+        self.subscribers.clone()
+    }
+
+    /// Spawns a supervised listener per connector, or a single replay of the recordings, sending
+    /// orderbooks through a multi-producer, multi-consumer broadcast queue per symbol
+    pub async fn run(self) -> Result<SymbolChannels> {
+        if !self.replay.files.is_empty() {
+            let channels = self.channels.clone();
+            tokio::spawn(async move {
+                let result = tokio::select! {
+                    result = replay(
+                        &self.replay.files,
+                        self.connectors,
+                        &self.symbols,
+                        &channels,
+                        self.replay.speed,
+                        &self.subscribers,
+                        self.replay.wait_for_clients,
+                        &self.metrics,
+                    ) => result,
+                    // Recordings have nothing to close
+                    _ = self.shutdown.requested() => Ok(()),
+                };
+                if let Err(error) = result {
+                    log::error!("Replay failed. Error: {:?}", error);
+                }
+            });
 
-        tokio::spawn(async move { binance_data_listen(cloned_symbol, chan_send_cloned).await });
+            return Ok(self.channels);
+        }
 
-        // We need to clone them again as the above ones got moved
-        let cloned_symbol = self.symbol.clone();
-        let chan_send_cloned = self.chan_send.clone();
-        tokio::spawn(async move { bitstamp_data_listen(cloned_symbol, chan_send_cloned).await });
+        for connector in self.connectors {
+            let recorder = Recorder::from_config(&self.recorder, connector.exchange())
+                .map(RecorderHandle::spawn);
+            tokio::spawn(supervise(
+                connector,
+                self.symbols.clone(),
+                self.channels.clone(),
+                self.subscribers.clone(),
+                self.err_count_log,
+                recorder,
+                self.backoff.clone(),
+                self.metrics.clone(),
+                self.shutdown.clone(),
+            ));
+        }
 
-        Ok(self.chan_send)
+        Ok(self.channels)
     }
 
-    /// Receiver loop. Always listens and waits for messages and call handle_message to process messages accordingly
+    /// Receiver loop. Always listens and waits for messages of `symbol` and call handle_message to process messages accordingly.
+    /// Each client merges the books of its own `view`, and gets caught up according to its `lag_policy` when it falls behind
+    // Every argument is a piece of state of the client the loop owns for as long as it streams
+    #[allow(clippy::too_many_arguments)]
     pub async fn broadcast_handle(
         client_id: String,
+        symbol: String,
+        view: ClientView,
         mut chan_recv: Receiver<OrderbookMessage>,
         chan_send: mpsc::Sender<ResultSummary>,
+        sequence: StreamSequence,
+        client_metrics: Arc<ClientMetrics>,
+        latest_books: LatestBooks,
+        shutdown: Shutdown,
     ) -> Result<()> {
         log::info!(
-            "Stream Server ready to stream. Connected to client: {}",
+            "Stream Server ready to stream {}. Connected to client: {}",
+            &symbol,
             &client_id
         );
 
-        while let Ok(msg) = chan_recv.recv().await {
-            let summary = StreamService::handle_message(&msg)?;
+        let mut aggregator = StreamService::aggregator(&view);
+        let mut throttle_timer = view.throttle.map(|period| {
+            let mut timer = interval_at(Instant::now() + period, period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            timer
+        });
+        // Exchanges go stale without any message coming through, so they're checked a couple of
+        // times per `stale_after`. Only exchanges going stale are news, fresh ones come with an update
+        let mut freshness_timer = view.stale_after.map(|stale_after| {
+            let period = (stale_after / 2).min(Duration::from_secs(1));
+            let mut timer = interval_at(Instant::now() + period, period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            timer
+        });
+        let mut stale = HashSet::new();
+        // Whether the books changed since the last throttled Summary, and the latest orderbook since
+        let mut pending = false;
+        let mut pending_origin = None;
+        // Lag to report on the next Summary, and updates skipped since the stream started
+        let mut pending_lag = None;
+        let mut total_skipped = 0;
 
-            if let Err(error) = chan_send.send(Ok(summary)).await {
-                log::debug!(
-                    "Failed to send broadcast message. No clients available. Error: {:?}",
-                    error
-                );
+        loop {
+            let msg = tokio::select! {
+                msg = chan_recv.recv() => msg,
+                // Only polled for throttled clients
+                _ = async { throttle_timer.as_mut().unwrap().tick().await }, if throttle_timer.is_some() => {
+                    if pending {
+                        pending = false;
+                        let origin = pending_origin.take();
+                        let mut summary = aggregator.summary();
+                        summary.lag = pending_lag.take();
+                        if !StreamService::send_summary(summary, &symbol, &chan_send, &sequence, &client_metrics, origin).await {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                // Only polled for clients of servers that flag stale exchanges
+                _ = async { freshness_timer.as_mut().unwrap().tick().await }, if freshness_timer.is_some() => {
+                    let now_stale = aggregator.stale_exchanges(std::time::Instant::now());
+                    let went_stale = !now_stale.is_subset(&stale);
+                    stale = now_stale;
+                    if !went_stale {
+                        continue;
+                    }
+                    if throttle_timer.is_some() {
+                        pending = true;
+                        continue;
+                    }
+
+                    let mut summary = aggregator.summary();
+                    summary.lag = pending_lag.take();
+                    if !StreamService::send_summary(summary, &symbol, &chan_send, &sequence, &client_metrics, None).await {
+                        break;
+                    }
+                    continue;
+                }
+                _ = shutdown.requested() => break,
+            };
+
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(RecvError::Lagged(skipped)) => {
+                    // Whatever the policy, the client resumes from the latest update
+                    let (backlog, lagged) = StreamService::drain_backlog(&mut chan_recv);
+                    let skipped = skipped + lagged + backlog.len() as u64;
+                    total_skipped += skipped;
+                    let error = OrderbookError::ClientLag {
+                        symbol: symbol.clone(),
+                        skipped,
+                    };
+                    client_metrics.broadcast_lagged(&symbol, skipped);
+                    client_metrics.error(&error);
+                    log::warn!(
+                        "Client {} lagged. Error: {}. Policy: {:?}",
+                        &client_id,
+                        error,
+                        view.lag_policy
+                    );
+
+                    let policy = match view.lag_policy {
+                        LagPolicy::Skip => proto::LagPolicy::Skip,
+                        LagPolicy::Snapshot => proto::LagPolicy::Snapshot,
+                        LagPolicy::Terminate => {
+                            let _ = chan_send.send(Err(Status::from(error))).await;
+                            break;
+                        }
+                    };
+                    pending_lag = Some(Lag {
+                        skipped,
+                        total_skipped,
+                        policy: policy as i32,
+                    });
+
+                    if view.lag_policy == LagPolicy::Snapshot {
+                        // The snapshot may be behind the backlog or ahead of it. Either way the
+                        // latest message of every exchange is applied last
+                        aggregator = StreamService::aggregator(&view);
+                        for msg in latest_books.snapshot(&symbol).iter().chain(&backlog) {
+                            StreamService::apply_message(&mut aggregator, msg);
+                        }
+
+                        pending = false;
+                        let mut summary = aggregator.summary();
+                        summary.lag = pending_lag.take();
+                        if !StreamService::send_summary(
+                            summary,
+                            &symbol,
+                            &chan_send,
+                            &sequence,
+                            &client_metrics,
+                            None,
+                        )
+                        .await
+                        {
+                            break;
+                        }
+                    } else {
+                        // Only the Summaries in between are skipped, the books and statuses still count
+                        for msg in &backlog {
+                            StreamService::apply_message(&mut aggregator, msg);
+                        }
+                        pending = true;
+                    }
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let origin = Origin::of(&msg);
+
+            if throttle_timer.is_some() {
+                StreamService::apply_message(&mut aggregator, &msg);
+                pending = true;
+                pending_origin = origin.or(pending_origin);
+                continue;
+            }
+
+            let mut summary = StreamService::handle_message(&mut aggregator, &msg);
+            summary.lag = pending_lag.take();
+            if !StreamService::send_summary(
+                summary,
+                &symbol,
+                &chan_send,
+                &sequence,
+                &client_metrics,
+                origin,
+            )
+            .await
+            {
                 break;
             }
         }
 
-        log::info!("Stream Server closed connection to client: {}", &client_id);
+        log::info!(
+            "Stream Server closed {} connection to client: {}",
+            &symbol,
+            &client_id
+        );
 
         Ok(())
     }
 
-    pub(crate) fn handle_message(msg: &OrderbookMessage) -> Result<Summary, OrderbookError> {
-        let (mut asks, mut bids, exchange) = match msg {
-            OrderbookMessage::Message { message } => {
-                let asklen = message.asks.len();
-                let bidlen = message.bids.len();
-                log::debug!(
-                    "Received message for {:?} with {} asks and {} bids",
-                    message.exchange,
-                    asklen,
-                    bidlen
-                );
+    /// Receiver loop of an arbitrage client. Feeds every message of `symbol` to the `detector` and
+    /// sends the client the events of the opportunities that opened, changed or closed
+    pub async fn arbitrage_handle(
+        client_id: String,
+        symbol: String,
+        mut detector: ArbitrageDetector,
+        mut chan_recv: Receiver<OrderbookMessage>,
+        chan_send: mpsc::Sender<ResultArbitrageEvent>,
+        client_metrics: Arc<ClientMetrics>,
+        shutdown: Shutdown,
+    ) {
+        log::info!(
+            "Stream Server ready to watch {} for arbitrage. Connected to client: {}",
+            &symbol,
+            &client_id
+        );
+
+        // Stale exchanges close their opportunities without any message coming through
+        let mut freshness_timer = detector.stale_after().map(|stale_after| {
+            let period = (stale_after / 2).min(Duration::from_secs(1));
+            let mut timer = interval_at(Instant::now() + period, period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            timer
+        });
 
-                (message.asks.clone(), message.bids.clone(), message.exchange)
+        'receive: loop {
+            let events = tokio::select! {
+                msg = chan_recv.recv() => match msg {
+                    Ok(msg) => detector.apply(&msg),
+                    Err(RecvError::Lagged(skipped)) => {
+                        let error = OrderbookError::ClientLag {
+                            symbol: symbol.clone(),
+                            skipped,
+                        };
+                        client_metrics.broadcast_lagged(&symbol, skipped);
+                        client_metrics.error(&error);
+                        log::warn!("Arbitrage client {} lagged. Error: {}", &client_id, error);
+                        // The detector only needs the latest books, which the next messages bring
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                // Only polled for clients of servers that flag stale exchanges
+                _ = async { freshness_timer.as_mut().unwrap().tick().await }, if freshness_timer.is_some() => {
+                    detector.refresh(std::time::Instant::now())
+                }
+                _ = shutdown.requested() => break,
+            };
+
+            for event in events {
+                if chan_send.send(Ok(event)).await.is_err() {
+                    log::debug!("Arbitrage client {} is gone", &client_id);
+                    break 'receive;
+                }
+                client_metrics.set_queue_depth(chan_send.max_capacity() - chan_send.capacity());
             }
-        };
+        }
+
+        log::info!(
+            "Stream Server closed {} arbitrage connection to client: {}",
+            &symbol,
+            &client_id
+        );
+    }
 
-        let (converted_asks, converted_bids) =
-            StreamService::sort_and_convert(&mut asks, &mut bids, &exchange);
+    /// Helper to build an empty aggregator of the books `view` asks for
+    fn aggregator(view: &ClientView) -> Aggregator {
+        Aggregator::with_view(view.depth, view.exchanges.clone()).with_stale_after(view.stale_after)
+    }
 
-        let spread = converted_asks[0].price - converted_bids[0].price;
+    /// Updates the aggregator with the orderbook or connection status in `msg` and returns the
+    /// merged Summary across all exchanges
+    pub(crate) fn handle_message(aggregator: &mut Aggregator, msg: &OrderbookMessage) -> Summary {
+        StreamService::apply_message(aggregator, msg);
 
-        Ok(Summary {
-            spread,
-            bids: converted_bids,
-            asks: converted_asks,
-        })
+        aggregator.summary()
     }
 
-    /// Helper to sort the asks based on price and convert them to a Vec of Levels to be send to a client
-    pub(crate) fn sort_and_convert(
-        asks: &mut [OfferData],
-        bids: &mut [OfferData],
-        exchange: &Exchange,
-    ) -> (Vec<Level>, Vec<Level>) {
-        asks.sort_by(|order_l, order_r| order_l.price.partial_cmp(&order_r.price).unwrap());
-        bids.sort_by(|order_l, order_r| order_r.price.partial_cmp(&order_l.price).unwrap());
+    /// Helper to number, stamp and send the Summary of `symbol` to a client. Returns false once the
+    /// client is gone
+    async fn send_summary(
+        mut summary: Summary,
+        symbol: &str,
+        chan_send: &mpsc::Sender<ResultSummary>,
+        sequence: &StreamSequence,
+        client_metrics: &ClientMetrics,
+        origin: Option<Origin>,
+    ) -> bool {
+        summary.symbol = symbol.to_string();
+        summary.source = origin.as_ref().map(Origin::source);
+
+        // Held until the Summary is queued, so that the stream is numbered in order across symbols
+        let mut sequence = sequence.lock().await;
+        *sequence += 1;
+        summary.sequence = *sequence;
+        summary.server_timestamp_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        if let Err(error) = chan_send.send(Ok(summary)).await {
+            log::debug!(
+                "Failed to send broadcast message. No clients available. Error: {:?}",
+                error
+            );
+            return false;
+        }
+        drop(sequence);
+
+        if let Some(Origin {
+            exchange,
+            received_at: Some(received_at),
+            ..
+        }) = origin
+        {
+            client_metrics.observe_latency(exchange, received_at);
+        }
+        client_metrics.set_queue_depth(chan_send.max_capacity() - chan_send.capacity());
 
-        let converted_asks = StreamService::convert_to_levels(asks, exchange);
-        let converted_bids = StreamService::convert_to_levels(bids, exchange);
+        true
+    }
 
-        (converted_asks, converted_bids)
+    /// Helper to take every message waiting in `chan_recv`, so that the next one received is the
+    /// latest. Returns them along with how many more it lagged behind on meanwhile
+    fn drain_backlog(chan_recv: &mut Receiver<OrderbookMessage>) -> (Vec<OrderbookMessage>, u64) {
+        let mut backlog = Vec::new();
+        let mut lagged = 0;
+        loop {
+            match chan_recv.try_recv() {
+                Ok(msg) => backlog.push(msg),
+                Err(TryRecvError::Lagged(skipped)) => lagged += skipped,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return (backlog, lagged),
+            }
+        }
     }
 
-    /// Helper to convert asks and prices to Level Struct to be sent via gRPC
-    fn convert_to_levels(securities: &mut [OfferData], exchange: &Exchange) -> Vec<Level> {
-        securities
-            .iter_mut()
-            .take(MAX_PAIR_EXCHANGE)
-            .map(|bid| Level {
-                amount: bid.quantity as f64,
-                exchange: exchange.to_string(),
-                price: bid.price as f64,
-            })
-            .collect()
+    /// Helper to update the aggregator with the orderbook or connection status in `msg`
+    fn apply_message(aggregator: &mut Aggregator, msg: &OrderbookMessage) {
+        match msg {
+            OrderbookMessage::Message { message } => {
+                log::debug!(
+                    "Received message for {:?} with {} asks and {} bids",
+                    message.exchange,
+                    message.asks.len(),
+                    message.bids.len()
+                );
+
+                aggregator.update(message.as_ref().clone());
+            }
+            OrderbookMessage::Status { exchange, status } => {
+                log::debug!("{} is now {:?}", exchange, status);
+
+                aggregator.set_status(*exchange, *status);
+            }
+        }
     }
 }
@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Client streams subscribed to the symbols, counted apart from the server's own receivers
+#[derive(Debug, Clone, Default)]
+pub struct Subscribers {
+    inner: Arc<SubscribersInner>,
+}
+
+#[derive(Debug, Default)]
+struct SubscribersInner {
+    count: AtomicUsize,
+    subscribed: Notify,
+}
+
+impl Subscribers {
+    /// Counts a client stream in until the returned handle is dropped
+    pub fn subscribe(&self) -> Subscription {
+        self.inner.count.fetch_add(1, Ordering::SeqCst);
+        self.inner.subscribed.notify_waiters();
+
+        Subscription {
+            subscribers: self.clone(),
+        }
+    }
+
+    /// Number of client streams subscribed
+    pub fn count(&self) -> usize {
+        self.inner.count.load(Ordering::SeqCst)
+    }
+
+    /// Returns once at least one client stream is subscribed
+    pub async fn wait(&self) {
+        loop {
+            // Created before the check so that a subscription in between isn't missed
+            let subscribed = self.inner.subscribed.notified();
+            if self.count() > 0 {
+                return;
+            }
+            subscribed.await;
+        }
+    }
+}
+
+/// Handle of a subscribed client stream, see `Subscribers`
+#[derive(Debug)]
+pub struct Subscription {
+    subscribers: Subscribers,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.subscribers.inner.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
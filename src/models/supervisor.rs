@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::{sleep, Instant};
+
+use crate::server::{metrics::Metrics, shutdown::Shutdown};
+
+use super::{
+    connectors::ExchangeConnector,
+    consts::{BACKOFF_INITIAL_MS, BACKOFF_MAX_MS},
+    mapper::Exchange,
+    messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+    recorder::RecorderHandle,
+    stream::listen,
+    subscribers::Subscribers,
+};
+
+/// Jittered exponential backoff used between reconnection attempts, so that reconnecting listeners
+/// don't hammer the exchange in lockstep
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(
+            Duration::from_millis(BACKOFF_INITIAL_MS),
+            Duration::from_millis(BACKOFF_MAX_MS),
+        )
+    }
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Returns a delay between half and all of `initial * 2^attempt`, capped at `max`, and bumps the
+    /// attempt counter
+    pub fn next_delay(&mut self) -> Duration {
+        let cap = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let half = cap / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=cap - half)
+    }
+
+    /// Starts over from the initial delay
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Keeps an exchange listener alive, reconnecting with backoff whenever it drops until `shutdown`
+/// is requested. Each connection gets a recording of its own
+// Every argument is a piece of state the supervisor owns for as long as the feed runs
+#[allow(clippy::too_many_arguments)]
+pub async fn supervise(
+    mut connector: Box<dyn ExchangeConnector>,
+    symbols: Vec<String>,
+    channels: SymbolChannels,
+    subscribers: Subscribers,
+    err_count_log: i32,
+    recorder: Option<RecorderHandle>,
+    mut backoff: Backoff,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+) {
+    let exchange = connector.exchange();
+
+    loop {
+        let started = Instant::now();
+        let result = listen(
+            connector.as_mut(),
+            &symbols,
+            &channels,
+            &subscribers,
+            err_count_log,
+            recorder.as_ref(),
+            &metrics,
+            &shutdown,
+        )
+        .await;
+
+        if let Err(error) = &result {
+            metrics.error(error);
+        }
+
+        if shutdown.is_requested() {
+            if let Err(error) = result {
+                log::warn!("{} feed failed to shut down. Error: {:?}", exchange, error);
+            }
+            finish_recording(recorder.as_ref(), exchange).await;
+            log::info!("{} feed is shut down", exchange);
+            return;
+        }
+
+        if started.elapsed() >= backoff.max {
+            backoff.reset();
+        }
+        let delay = backoff.next_delay();
+
+        match result {
+            Ok(()) => log::warn!(
+                "{} closed the connection. Reconnecting in {:?}",
+                exchange,
+                delay
+            ),
+            Err(error) => log::warn!(
+                "{} feed is down. Error: {:?}. Reconnecting in {:?}",
+                exchange,
+                error,
+                delay
+            ),
+        }
+
+        send_status(&channels, exchange, ConnectionStatus::Disconnected);
+
+        // Every connection gets its own recording
+        finish_recording(recorder.as_ref(), exchange).await;
+
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = shutdown.requested() => {
+                log::info!("{} feed is shut down", exchange);
+                return;
+            }
+        }
+    }
+}
+
+/// Helper to complete the recording of `exchange`, if recording
+async fn finish_recording(recorder: Option<&RecorderHandle>, exchange: Exchange) {
+    if let Some(recorder) = recorder {
+        if let Err(error) = recorder.finish().await {
+            log::warn!(
+                "Failed to complete {} recording. Error: {:?}",
+                exchange,
+                error
+            );
+        }
+    }
+}
+
+/// Helper to let the aggregators of every symbol know about a connection status change.
+/// Having no clients connected is not an error so send failures are ignored.
+pub fn send_status(channels: &SymbolChannels, exchange: Exchange, status: ConnectionStatus) {
+    for chan_send in channels.values() {
+        let _ = chan_send.send(OrderbookMessage::Status { exchange, status });
+    }
+}
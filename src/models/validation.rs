@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use rust_decimal::Decimal;
+
+use super::{book::Side, errors::OrderbookError, mapper::OfferData, messages::Orders};
+
+/// Checks that both sides of a book have distinct positive levels and that it isn't crossed, before
+/// it's broadcast to the aggregators
+pub fn validate_book(orders: &Orders) -> Result<(), OrderbookError> {
+    let best_bid = validate_side(orders, Side::Bid, &orders.bids)?
+        .max()
+        .unwrap_or_default();
+    let best_ask = validate_side(orders, Side::Ask, &orders.asks)?
+        .min()
+        .unwrap_or_default();
+
+    if best_bid >= best_ask {
+        return Err(OrderbookError::CrossedBook {
+            exchange: orders.exchange,
+            symbol: orders.symbol.clone(),
+            best_bid,
+            best_ask,
+        });
+    }
+
+    Ok(())
+}
+
+/// Helper to check the levels of one `side` of `orders`. Returns their prices
+fn validate_side<'a>(
+    orders: &Orders,
+    side: Side,
+    levels: &'a [OfferData],
+) -> Result<impl Iterator<Item = Decimal> + 'a, OrderbookError> {
+    if levels.is_empty() {
+        return Err(OrderbookError::EmptyBook {
+            exchange: orders.exchange,
+            symbol: orders.symbol.clone(),
+            side,
+        });
+    }
+
+    let invalid = |reason: String| OrderbookError::InvalidLevel {
+        exchange: orders.exchange,
+        symbol: orders.symbol.clone(),
+        side,
+        reason,
+    };
+    let mut prices = HashSet::with_capacity(levels.len());
+    for level in levels {
+        if level.price <= Decimal::ZERO {
+            return Err(invalid(format!("Price {} isn't positive", level.price)));
+        }
+        if level.quantity < Decimal::ZERO {
+            return Err(invalid(format!(
+                "Quantity {} at {} is negative",
+                level.quantity, level.price
+            )));
+        }
+        // Exchanges remove levels without quantity instead of sending them
+        if level.quantity.is_zero() {
+            return Err(invalid(format!("Zero quantity at {}", level.price)));
+        }
+        // Equal decimals with different scales, e.g. 0.07 and 0.070, are the same price
+        if !prices.insert(level.price.normalize()) {
+            return Err(invalid(format!("Price {} shows up twice", level.price)));
+        }
+    }
+
+    Ok(levels.iter().map(|level| level.price))
+}
@@ -1,26 +1,184 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 use orderbook::orderbook_aggregator_server::{OrderbookAggregator, OrderbookAggregatorServer};
-use orderbook::{Empty, Summary};
-use tokio::sync::broadcast::Sender;
-use tokio::sync::mpsc::channel;
+use orderbook::{ArbitrageEvent, ArbitrageRequest, BookSummaryRequest, Summary};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use tokio::sync::{broadcast::Receiver, mpsc::channel};
+use tokio::time::{timeout_at, Instant};
 use tokio_stream::wrappers::ReceiverStream;
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{
+    transport::{Certificate, Identity, Server},
+    Request, Response, Status,
+};
+use tonic_health::{server::health_reporter, ServingStatus};
 
-use crate::models::consts::{IP_ADDRESS, SERVER_PORT};
-use crate::models::messages::OrderbookMessage;
-use crate::models::stream_service::StreamService;
+use crate::models::arbitrage::ArbitrageDetector;
+use crate::models::config::{read_pem, ServerConfig, ServerTlsConfig};
+use crate::models::connectors::{connector_for, normalize_symbol};
+use crate::models::latest_books::LatestBooks;
+use crate::models::mapper::Exchange;
+use crate::models::messages::{OrderbookMessage, SymbolChannels};
+use crate::models::stream_service::{ClientView, LagPolicy, StreamSequence, StreamService};
+use crate::models::subscribers::Subscribers;
+
+use super::health::{report_health, set_status};
+use super::metrics::{serve_metrics, Metrics};
+use super::shutdown::{shutdown_signal, Shutdown, ShutdownController};
 
 pub mod orderbook {
     tonic::include_proto!("orderbook");
+
+    /// Encoded descriptors of the `orderbook` package, served over gRPC reflection
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("orderbook_descriptor");
 }
 
 #[derive(Debug)]
 pub struct OrderbookService {
-    pub chan_send: Sender<OrderbookMessage>,
+    /// Broadcast channel of every symbol the server streams
+    pub channels: SymbolChannels,
+    /// Levels per side sent to clients that don't ask for a depth
+    default_depth: usize,
+    /// Largest depth clients can ask for
+    max_depth: usize,
+    /// Buffer limit of the channel of summaries sent to each client
+    client_buffer_limit: usize,
+    /// What happens to clients that fall behind the broadcast channel of a symbol
+    lag_policy: LagPolicy,
+    /// Time without an orderbook after which an exchange is stale. Never while replaying, since
+    /// the books of a finished replay are meant to stay
+    stale_after: Option<Duration>,
+    /// Latest update of every exchange per symbol, kept only for the snapshot lag policy
+    latest_books: LatestBooks,
+    /// Where client streams are counted in, apart from the server's own receivers
+    subscribers: Subscribers,
+    /// Where connected clients are counted and their streams measured
+    metrics: Arc<Metrics>,
+    /// Tells the streams of every client when to end
+    shutdown: Shutdown,
+}
+
+impl OrderbookService {
+    /// Serves the summaries of `channels` until `shutdown` is requested. Must be called within the
+    /// runtime, which keeps the snapshots of the lag policy
+    pub fn new(
+        channels: SymbolChannels,
+        config: &ServerConfig,
+        metrics: Arc<Metrics>,
+        shutdown: Shutdown,
+    ) -> Self {
+        let latest_books = match config.lag_policy {
+            LagPolicy::Snapshot => LatestBooks::watch(&channels),
+            LagPolicy::Skip | LagPolicy::Terminate => LatestBooks::default(),
+        };
+
+        OrderbookService {
+            channels,
+            default_depth: config.default_depth,
+            max_depth: config.max_book_depth,
+            client_buffer_limit: config.client_buffer_limit,
+            lag_policy: config.lag_policy,
+            stale_after: config
+                .replay
+                .files
+                .is_empty()
+                .then(|| Duration::from_millis(config.stale_feed_ms)),
+            latest_books,
+            subscribers: Subscribers::default(),
+            metrics,
+            shutdown,
+        }
+    }
+
+    /// Counts client streams in `subscribers`, e.g. the ones of the `StreamService` so that a
+    /// replay can wait for them
+    pub fn with_subscribers(mut self, subscribers: Subscribers) -> Self {
+        self.subscribers = subscribers;
+        self
+    }
+
+    /// Helper to subscribe a receiver to the channel of each of `symbols`
+    fn subscribe(&self, symbols: Vec<String>) -> Vec<(String, Receiver<OrderbookMessage>)> {
+        symbols
+            .into_iter()
+            .map(|symbol| {
+                let chan_recv = self.channels[&symbol].subscribe();
+                (symbol, chan_recv)
+            })
+            .collect()
+    }
+
+    /// Helper to resolve the symbols a client asked for. Clients that don't ask for any symbol get
+    /// all of them
+    fn requested_symbols(&self, requested: &[String]) -> Result<Vec<String>, String> {
+        let mut symbols: Vec<String> = requested
+            .iter()
+            .map(|symbol| normalize_symbol(symbol))
+            .collect();
+        if symbols.is_empty() {
+            symbols = self.channels.keys().cloned().collect();
+        }
+        symbols.sort();
+        symbols.dedup();
+
+        if let Some(symbol) = symbols.iter().find(|s| !self.channels.contains_key(*s)) {
+            return Err(format!("Not streaming symbol {}", symbol));
+        }
+
+        Ok(symbols)
+    }
+
+    /// Helper to name a new client after the machine and the thread serving it
+    fn client_id() -> std::io::Result<String> {
+        let machine_name = &uname::uname()?.nodename;
+        let id = thread::current().id();
+
+        Ok(format!("{}:tid:{:?}", machine_name, id))
+    }
+
+    /// Helper to turn the request of a client into the view of the books it wants.
+    /// Zero and empty fields fall back to the defaults
+    fn client_view(&self, request: &BookSummaryRequest) -> Result<ClientView, String> {
+        let depth = match request.depth as usize {
+            0 => self.default_depth,
+            depth if depth > self.max_depth => {
+                return Err(format!("Depth can't be larger than {}", self.max_depth))
+            }
+            depth => depth,
+        };
+
+        let exchanges = if request.exchanges.is_empty() {
+            None
+        } else {
+            let exchanges = request
+                .exchanges
+                .iter()
+                .map(|exchange| exchange.parse::<Exchange>())
+                .collect::<Result<_, _>>()?;
+            Some(exchanges)
+        };
+
+        let throttle = match request.throttle_ms {
+            0 => None,
+            throttle_ms => Some(Duration::from_millis(throttle_ms as u64)),
+        };
+
+        Ok(ClientView {
+            depth,
+            exchanges,
+            throttle,
+            lag_policy: self.lag_policy,
+            stale_after: self.stale_after,
+        })
+    }
 }
 
 pub type ResultSummary = Result<Summary, Status>;
+pub type ResultArbitrageEvent = Result<ArbitrageEvent, Status>;
 
 #[tonic::async_trait]
 impl OrderbookAggregator for OrderbookService {
@@ -28,41 +186,228 @@ impl OrderbookAggregator for OrderbookService {
 
     async fn book_summary(
         &self,
-        _empty: Request<Empty>,
+        request: Request<BookSummaryRequest>,
     ) -> Result<Response<Self::BookSummaryStream>, Status> {
-        // TODO: Make the channel buffer limit here dynamic and larger
-        let (tx, rx) = channel(100);
+        let request = request.into_inner();
+        let view = self
+            .client_view(&request)
+            .map_err(Status::invalid_argument)?;
+        let symbols = self
+            .requested_symbols(&request.symbols)
+            .map_err(Status::not_found)?;
 
-        let machine_name = &uname::uname()?.nodename;
-        let id = thread::current().id();
-        let client_id = format!("{}:tid:{:?}", machine_name, id);
+        let (tx, rx) = channel(self.client_buffer_limit);
+
+        let client_id = OrderbookService::client_id()?;
         log::info!("Starting client with id: {}", &client_id);
+        // Counted out once the streams of every symbol are over
+        let client_metrics = Arc::new(self.metrics.client_connected());
+        let sequence = StreamSequence::default();
 
         // The nice thing about this implementation is that we can have n numbers of clients listening to
-        // the same server since we're using multi-producer, multi-consumer broadcast queue
-        let chan_recv = self.chan_send.subscribe();
-        tokio::spawn(async move {
-            StreamService::broadcast_handle(client_id, chan_recv, tx.clone()).await
-        });
+        // the same server since we're using multi-producer, multi-consumer broadcast queue.
+        // Summaries of every requested symbol are interleaved on the client's stream
+        let chan_recvs = self.subscribe(symbols);
+        // Counted in only once it has a receiver on every channel, so a replay waiting for it
+        // doesn't start early
+        let subscription = Arc::new(self.subscribers.subscribe());
+        for (symbol, chan_recv) in chan_recvs {
+            let client_id = client_id.clone();
+            let view = view.clone();
+            let tx = tx.clone();
+            let sequence = sequence.clone();
+            let client_metrics = client_metrics.clone();
+            let latest_books = self.latest_books.clone();
+            let shutdown = self.shutdown.clone();
+            let subscription = subscription.clone();
+            tokio::spawn(async move {
+                let _subscription = subscription;
+                StreamService::broadcast_handle(
+                    client_id,
+                    symbol,
+                    view,
+                    chan_recv,
+                    tx,
+                    sequence,
+                    client_metrics,
+                    latest_books,
+                    shutdown,
+                )
+                .await
+            });
+        }
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type ArbitrageEventsStream = ReceiverStream<ResultArbitrageEvent>;
+
+    async fn arbitrage_events(
+        &self,
+        request: Request<ArbitrageRequest>,
+    ) -> Result<Response<Self::ArbitrageEventsStream>, Status> {
+        let request = request.into_inner();
+        let min_edge_bps = match Decimal::from_f64(request.min_edge_bps) {
+            Some(min_edge_bps) if min_edge_bps >= Decimal::ZERO => min_edge_bps,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "Minimum edge must be a positive number of basis points, or 0",
+                ))
+            }
+        };
+        let symbols = self
+            .requested_symbols(&request.symbols)
+            .map_err(Status::not_found)?;
+
+        let (tx, rx) = channel(self.client_buffer_limit);
+
+        let client_id = OrderbookService::client_id()?;
+        log::info!(
+            "Starting arbitrage client with id: {}. Minimum edge: {} bps",
+            &client_id,
+            min_edge_bps
+        );
+        let client_metrics = Arc::new(self.metrics.client_connected());
+        let chan_recvs = self.subscribe(symbols);
+        let subscription = Arc::new(self.subscribers.subscribe());
+
+        for (symbol, chan_recv) in chan_recvs {
+            let detector = ArbitrageDetector::new(symbol.clone(), min_edge_bps)
+                .with_stale_after(self.stale_after);
+            let client_id = client_id.clone();
+            let tx = tx.clone();
+            let client_metrics = client_metrics.clone();
+            let shutdown = self.shutdown.clone();
+            let subscription = subscription.clone();
+            tokio::spawn(async move {
+                let _subscription = subscription;
+                StreamService::arbitrage_handle(
+                    client_id,
+                    symbol,
+                    detector,
+                    chan_recv,
+                    tx,
+                    client_metrics,
+                    shutdown,
+                )
+                .await
+            });
+        }
 
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 }
 
-pub async fn serve(symbol: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
-    let service = StreamService::new(symbol);
-    let chan_send = service.run().await?;
+/// Streams the symbols of `config` and serves their summaries until SIGINT or SIGTERM
+pub async fn serve(config: ServerConfig) -> Result<(), Box<dyn std::error::Error>> {
+    serve_with_shutdown(config, shutdown_signal()).await
+}
+
+/// Serves like `serve` until `signal` returns, then ends every client stream and exchange connection,
+/// waiting for them for up to `shutdown_timeout_ms`
+pub async fn serve_with_shutdown(
+    config: ServerConfig,
+    signal: impl Future<Output = ()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let controller = ShutdownController::new();
+    let metrics = Arc::new(Metrics::new());
+    if let Some(metrics_port) = config.metrics_port {
+        let addr = SocketAddr::new(config.address, metrics_port);
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(error) = serve_metrics(addr, metrics).await {
+                log::error!("Metrics server failed. Error: {:?}", error);
+            }
+        });
+    }
+
+    let connectors = config
+        .exchanges
+        .iter()
+        .map(|&exchange| connector_for(exchange, &config))
+        .collect();
+    let service = StreamService::new(&config, connectors, metrics.clone(), controller.subscribe());
+    let subscribers = service.subscribers();
+    let channels = service.run().await?;
 
     // Defining address for our service.
-    let addr = format!("{}:{}", IP_ADDRESS, SERVER_PORT).parse().unwrap();
+    let addr = config.socket_addr();
     // Create an orderbook service instance.
-    let orderbook = OrderbookService { chan_send };
+    let (mut reporter, health) = health_reporter();
+    report_health(
+        &channels,
+        reporter.clone(),
+        Duration::from_millis(config.stale_feed_ms),
+        metrics.clone(),
+        controller.subscribe(),
+    )
+    .await;
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(orderbook::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()?;
+    let orderbook = OrderbookService::new(channels, &config, metrics, controller.subscribe())
+        .with_subscribers(subscribers);
 
+    let mut builder = server_builder(&config.tls)?;
     log::info!("Server listening on {}", addr);
-    // Add orderbook service to the server.
-    Server::builder()
+    // Add orderbook service to the server, along with health checking and reflection.
+    // It stops accepting clients once shutdown is requested, and returns once their streams are over
+    let server_shutdown = controller.subscribe();
+    let server = builder
+        .add_service(health)
+        .add_service(reflection)
         .add_service(OrderbookAggregatorServer::new(orderbook))
-        .serve(addr)
-        .await?;
+        .serve_with_shutdown(addr, async move { server_shutdown.requested().await });
+    tokio::pin!(server);
+
+    tokio::select! {
+        // Failed to serve, e.g. the address is in use
+        result = &mut server => return Ok(result?),
+        _ = signal => {}
+    }
+
+    log::info!("Shutting down");
+    set_status(&mut reporter, ServingStatus::NotServing).await;
+    let deadline = Instant::now() + Duration::from_millis(config.shutdown_timeout_ms);
+    // The orderbook service only lets go of its handle once the server is over
+    let (wrapped_up, served) =
+        tokio::join!(controller.shutdown(deadline), timeout_at(deadline, server));
+    let served = match served {
+        Ok(result) => {
+            result?;
+            true
+        }
+        Err(_) => false,
+    };
+
+    if wrapped_up && served {
+        log::info!("Server shut down");
+    } else {
+        log::warn!(
+            "Server didn't shut down within {}ms. Exiting anyway",
+            config.shutdown_timeout_ms
+        );
+    }
     Ok(())
 }
+
+/// Server builder that serves over TLS when `tls` has a certificate, and that requires clients to
+/// present a certificate signed by `tls.client_ca` when set
+pub fn server_builder(tls: &ServerTlsConfig) -> anyhow::Result<Server> {
+    let (cert, key) = match (&tls.cert, &tls.key) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Ok(Server::builder()),
+    };
+
+    let mut tls_config = tonic::transport::ServerTlsConfig::new()
+        .identity(Identity::from_pem(read_pem(cert)?, read_pem(key)?));
+    if let Some(client_ca) = &tls.client_ca {
+        tls_config = tls_config.client_ca_root(Certificate::from_pem(read_pem(client_ca)?));
+        log::info!("Serving over mutual TLS");
+    } else {
+        log::info!("Serving over TLS");
+    }
+
+    Ok(Server::builder().tls_config(tls_config)?)
+}
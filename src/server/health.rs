@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, MissedTickBehavior};
+use tonic::server::NamedService;
+use tonic_health::{server::HealthReporter, ServingStatus};
+
+use crate::models::{
+    errors::OrderbookError,
+    mapper::Exchange,
+    messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+};
+
+use super::{
+    grpc_server::{
+        orderbook::orderbook_aggregator_server::OrderbookAggregatorServer, OrderbookService,
+    },
+    metrics::Metrics,
+    shutdown::Shutdown,
+};
+
+/// Time each connected exchange last sent an orderbook of any symbol
+type LastUpdates = Arc<Mutex<HashMap<Exchange, Instant>>>;
+
+/// Reports the server as SERVING while at least one exchange sent an orderbook within `stale_after`,
+/// and as NOT_SERVING for good once `shutdown` is requested
+pub async fn report_health(
+    channels: &SymbolChannels,
+    mut reporter: HealthReporter,
+    stale_after: Duration,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+) {
+    set_status(&mut reporter, ServingStatus::NotServing).await;
+
+    let last_updates = LastUpdates::default();
+    for chan_send in channels.values() {
+        let mut chan_recv = chan_send.subscribe();
+        let last_updates = last_updates.clone();
+        tokio::spawn(async move {
+            loop {
+                match chan_recv.recv().await {
+                    Ok(OrderbookMessage::Message { message }) => {
+                        let received_at = message.received_at.unwrap_or_else(Instant::now);
+                        last_updates
+                            .lock()
+                            .unwrap()
+                            .insert(message.exchange, received_at);
+                    }
+                    Ok(OrderbookMessage::Status {
+                        exchange,
+                        status: ConnectionStatus::Disconnected,
+                    }) => {
+                        last_updates.lock().unwrap().remove(&exchange);
+                    }
+                    Ok(OrderbookMessage::Status { .. }) => {}
+                    // Only the latest orderbooks matter
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = interval((stale_after / 2).min(Duration::from_secs(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut serving = false;
+        // Exchanges already reported stale, until they send an orderbook again
+        let mut stale = HashSet::new();
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.requested() => {
+                    // Fresh feeds must not report SERVING again while the server drains
+                    set_status(&mut reporter, ServingStatus::NotServing).await;
+                    return;
+                }
+            }
+            let mut fresh = false;
+            {
+                let last_updates = last_updates.lock().unwrap();
+                // Disconnected exchanges start over once they're back
+                stale.retain(|exchange| last_updates.contains_key(exchange));
+                for (exchange, received_at) in last_updates.iter() {
+                    let age = received_at.elapsed();
+                    if age <= stale_after {
+                        fresh = true;
+                        stale.remove(exchange);
+                    } else if stale.insert(*exchange) {
+                        let error = OrderbookError::StaleBook {
+                            exchange: *exchange,
+                            age,
+                        };
+                        metrics.error(&error);
+                        log::warn!("{}", error);
+                    }
+                }
+            }
+
+            if fresh != serving {
+                serving = fresh;
+                if serving {
+                    log::info!("Exchange feeds are up. Serving");
+                    set_status(&mut reporter, ServingStatus::Serving).await;
+                } else {
+                    log::warn!("Every exchange feed is down or stale. Not serving");
+                    set_status(&mut reporter, ServingStatus::NotServing).await;
+                }
+            }
+        }
+    });
+}
+
+/// Sets the overall status and the one of the orderbook service
+pub(crate) async fn set_status(reporter: &mut HealthReporter, status: ServingStatus) {
+    for service in ["", OrderbookAggregatorServer::<OrderbookService>::NAME] {
+        reporter.set_service_status(service, status).await;
+    }
+}
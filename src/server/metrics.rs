@@ -0,0 +1,265 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::models::{errors::OrderbookError, mapper::Exchange};
+
+/// Prometheus metrics of the server, exposed over HTTP at `/metrics` by `serve_metrics`
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    messages_received: IntCounterVec,
+    errors: IntCounterVec,
+    broadcast_dropped: IntCounterVec,
+    broadcast_lagged: IntCounterVec,
+    clients: IntGauge,
+    client_queue_depth: IntGaugeVec,
+    latency: HistogramVec,
+    /// Number of clients that ever connected, which tells clients apart in labels
+    next_client: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    /// Registers every metric of the server in a registry of its own
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let messages_received = IntCounterVec::new(
+            Opts::new(
+                "orderbook_messages_received_total",
+                "Text frames received from each exchange",
+            ),
+            &["exchange"],
+        )
+        .unwrap();
+        let errors = IntCounterVec::new(
+            Opts::new(
+                "orderbook_errors_total",
+                "Errors of each kind, by the exchange they come from if any",
+            ),
+            &["exchange", "kind"],
+        )
+        .unwrap();
+        let broadcast_dropped = IntCounterVec::new(
+            Opts::new(
+                "orderbook_broadcast_dropped_total",
+                "Orderbooks of each exchange that weren't broadcast since nobody was listening",
+            ),
+            &["exchange"],
+        )
+        .unwrap();
+        let broadcast_lagged = IntCounterVec::new(
+            Opts::new(
+                "orderbook_broadcast_lagged_total",
+                "Messages of each symbol skipped by clients that fell behind the broadcast channel",
+            ),
+            &["symbol"],
+        )
+        .unwrap();
+        let clients = IntGauge::new("orderbook_grpc_clients", "Connected gRPC clients").unwrap();
+        let client_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "orderbook_client_queue_depth",
+                "Summaries waiting in the send queue of each connected client",
+            ),
+            &["client"],
+        )
+        .unwrap();
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "orderbook_latency_seconds",
+                "Time from receiving an exchange frame to queueing the Summary it produced for a client",
+            )
+            // From 10µs to about 5s
+            .buckets(exponential_buckets(0.00001, 4.0, 10).unwrap()),
+            &["exchange"],
+        )
+        .unwrap();
+
+        // Names are unique within a fresh registry so registering can't fail
+        registry
+            .register(Box::new(messages_received.clone()))
+            .unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+        registry
+            .register(Box::new(broadcast_dropped.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(broadcast_lagged.clone()))
+            .unwrap();
+        registry.register(Box::new(clients.clone())).unwrap();
+        registry
+            .register(Box::new(client_queue_depth.clone()))
+            .unwrap();
+        registry.register(Box::new(latency.clone())).unwrap();
+
+        Metrics {
+            registry,
+            messages_received,
+            errors,
+            broadcast_dropped,
+            broadcast_lagged,
+            clients,
+            client_queue_depth,
+            latency,
+            next_client: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts a text frame received from `exchange`
+    pub fn message_received(&self, exchange: Exchange) {
+        self.messages_received
+            .with_label_values(&[&exchange.to_string()])
+            .inc();
+    }
+
+    /// Counts `error` under its kind. Errors that don't come from an exchange have an empty
+    /// exchange label
+    pub fn error(&self, error: &OrderbookError) {
+        let exchange = error
+            .exchange()
+            .map(|exchange| exchange.to_string())
+            .unwrap_or_default();
+        self.errors
+            .with_label_values(&[&exchange, error.kind()])
+            .inc();
+    }
+
+    /// Counts an orderbook of `exchange` that nobody was listening to
+    pub fn broadcast_dropped(&self, exchange: Exchange) {
+        self.broadcast_dropped
+            .with_label_values(&[&exchange.to_string()])
+            .inc();
+    }
+
+    /// Counts a new client. It's counted out once the returned metrics are dropped
+    pub fn client_connected(self: &Arc<Self>) -> ClientMetrics {
+        let client = self.next_client.fetch_add(1, Ordering::Relaxed).to_string();
+        self.clients.inc();
+
+        ClientMetrics {
+            queue_depth: self.client_queue_depth.with_label_values(&[&client]),
+            metrics: self.clone(),
+            client,
+        }
+    }
+
+    /// Every metric in the Prometheus text format
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Metrics of a single connected client, shared by the streams of all its symbols
+#[derive(Debug)]
+pub struct ClientMetrics {
+    metrics: Arc<Metrics>,
+    /// Label of the client
+    client: String,
+    queue_depth: IntGauge,
+}
+
+impl ClientMetrics {
+    /// Sets the number of summaries waiting to be sent to the client
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    /// Counts the `skipped` messages of `symbol` the client lagged behind on
+    pub fn broadcast_lagged(&self, symbol: &str, skipped: u64) {
+        self.metrics
+            .broadcast_lagged
+            .with_label_values(&[symbol])
+            .inc_by(skipped);
+    }
+
+    /// Counts an error of the client, such as falling behind the broadcast
+    pub fn error(&self, error: &OrderbookError) {
+        self.metrics.error(error);
+    }
+
+    /// Observes the latency of a Summary produced by a frame of `exchange` received at `received_at`
+    pub fn observe_latency(&self, exchange: Exchange, received_at: Instant) {
+        self.metrics
+            .latency
+            .with_label_values(&[&exchange.to_string()])
+            .observe(received_at.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for ClientMetrics {
+    fn drop(&mut self) {
+        self.metrics.clients.dec();
+        let _ = self
+            .metrics
+            .client_queue_depth
+            .remove_label_values(&[&self.client]);
+    }
+}
+
+/// Serves `metrics` at `http://{addr}/metrics` until the server stops
+pub async fn serve_metrics(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = metrics.clone();
+                async move { Ok::<_, Infallible>(metrics_response(&request, &metrics)) }
+            }))
+        }
+    });
+
+    log::info!("Metrics listening on http://{}/metrics", addr);
+    Server::try_bind(&addr)?.serve(make_service).await?;
+
+    Ok(())
+}
+
+/// Helper to answer a request to the metrics server
+fn metrics_response(request: &Request<Body>, metrics: &Metrics) -> Response<Body> {
+    let status = |status: StatusCode| {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = status;
+        response
+    };
+
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        return status(StatusCode::NOT_FOUND);
+    }
+
+    match metrics.encode() {
+        Ok(body) => {
+            let mut response = Response::new(Body::from(body));
+            response.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+            );
+            response
+        }
+        Err(error) => {
+            log::error!("Failed to encode metrics. Error: {:?}", error);
+            status(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
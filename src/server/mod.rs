@@ -1 +1,4 @@
 pub mod grpc_server;
+pub mod health;
+pub mod metrics;
+pub mod shutdown;
@@ -0,0 +1,111 @@
+use tokio::signal;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{timeout_at, Instant};
+
+/// Coordinates the shutdown of the server. Every task that has something to wrap up holds a
+/// `Shutdown` handle and drops it once it's done, so the controller can tell when they're all over.
+#[derive(Debug)]
+pub struct ShutdownController {
+    requested: watch::Sender<bool>,
+    done_send: mpsc::Sender<()>,
+    done_recv: mpsc::Receiver<()>,
+}
+
+impl Default for ShutdownController {
+    fn default() -> Self {
+        ShutdownController::new()
+    }
+}
+
+impl ShutdownController {
+    pub fn new() -> Self {
+        let (requested, _) = watch::channel(false);
+        let (done_send, done_recv) = mpsc::channel(1);
+
+        ShutdownController {
+            requested,
+            done_send,
+            done_recv,
+        }
+    }
+
+    /// Handle for a task to learn about the shutdown, to be dropped once the task is over
+    pub fn subscribe(&self) -> Shutdown {
+        Shutdown {
+            requested: self.requested.subscribe(),
+            _done: Some(self.done_send.clone()),
+        }
+    }
+
+    /// Requests every task to wrap up and waits until all their handles are dropped, or until
+    /// `deadline`. Returns whether they all made it in time
+    pub async fn shutdown(mut self, deadline: Instant) -> bool {
+        // Nobody listening just means there's nothing to wrap up
+        let _ = self.requested.send(true);
+        drop(self.done_send);
+
+        // Nothing is ever sent, so this only returns once every sender is gone
+        timeout_at(deadline, self.done_recv.recv()).await.is_ok()
+    }
+}
+
+/// Handle of a task telling it when to wrap up. See `ShutdownController`
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    requested: watch::Receiver<bool>,
+    /// Keeps the controller waiting while the task holds the handle
+    _done: Option<mpsc::Sender<()>>,
+}
+
+impl Default for Shutdown {
+    /// Handle that's never requested to shut down, for tasks run outside of a server
+    fn default() -> Self {
+        Shutdown {
+            requested: watch::channel(false).1,
+            _done: None,
+        }
+    }
+}
+
+impl Shutdown {
+    /// Whether the task was requested to wrap up
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Returns once the task is requested to wrap up. Never returns if the controller is dropped
+    /// without requesting it
+    pub async fn requested(&self) {
+        let mut requested = self.requested.clone();
+        while !*requested.borrow_and_update() {
+            if requested.changed().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Returns once the process receives SIGINT, e.g. Ctrl+C, or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(error) => {
+                log::warn!("Can't listen for SIGTERM. Error: {:?}", error);
+                let _ = signal::ctrl_c().await;
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = signal::ctrl_c().await;
+    }
+}
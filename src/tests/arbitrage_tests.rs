@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rust_decimal::Decimal;
+
+    use crate::models::{
+        arbitrage::ArbitrageDetector,
+        mapper::Exchange,
+        messages::{ConnectionStatus, OrderbookMessage},
+    };
+    use crate::server::grpc_server::orderbook::ArbitrageState;
+    use crate::tests::fixtures::OrdersBuilder;
+
+    /// Tests that an opportunity opens once the best bid of an exchange is above the best ask of
+    /// another, is updated while it lasts and closes with its duration
+    #[test]
+    fn test_detect_opportunity() {
+        let mut detector = ArbitrageDetector::new("ethbtc".to_string(), Decimal::ZERO);
+        let started = Instant::now();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_bids(&[("0.069", "1")])
+            .with_asks(&[("0.070", "2")])
+            .with_received_at(Some(started))
+            .message();
+        assert!(detector.apply(&binance).is_empty());
+        // Touching books aren't an opportunity
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_bids(&[("0.070", "1")])
+            .with_asks(&[("0.071", "1")])
+            .with_received_at(Some(started))
+            .message();
+        assert!(detector.apply(&bitstamp).is_empty());
+
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_bids(&[("0.0714", "3")])
+            .with_asks(&[("0.072", "1")])
+            .with_received_at(Some(started))
+            .message();
+        let events = detector.apply(&bitstamp);
+        assert_eq!(events.len(), 1);
+        let opened = &events[0];
+        assert_eq!(opened.state(), ArbitrageState::Opened);
+        assert_eq!(opened.symbol, "ethbtc");
+        assert_eq!(opened.buy_exchange, "Binance");
+        assert_eq!(opened.buy_price, "0.07");
+        assert_eq!(opened.sell_exchange, "Bitstamp");
+        assert_eq!(opened.sell_price, "0.0714");
+        assert_eq!(opened.size, "2");
+        assert_eq!(opened.edge, "0.0014");
+        assert_eq!(opened.edge_bps, 200.0);
+        assert_eq!(opened.gross_profit, "0.0028");
+        assert_eq!(opened.duration_ms, 0);
+
+        // The same prices and size aren't news
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_bids(&[("0.0714", "3")])
+            .with_asks(&[("0.073", "1")])
+            .with_received_at(Some(started))
+            .message();
+        assert!(detector.apply(&bitstamp).is_empty());
+
+        let later = started + Duration::from_millis(250);
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_bids(&[("0.069", "1")])
+            .with_asks(&[("0.0705", "2")])
+            .with_received_at(Some(later))
+            .message();
+        let events = detector.apply(&binance);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Updated);
+        assert_eq!(events[0].buy_price, "0.0705");
+        assert_eq!(events[0].duration_ms, 250);
+
+        let latest = started + Duration::from_millis(400);
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_bids(&[("0.069", "1")])
+            .with_asks(&[("0.0715", "2")])
+            .with_received_at(Some(latest))
+            .message();
+        let events = detector.apply(&binance);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Closed);
+        assert_eq!(events[0].buy_price, "0.0705");
+        assert_eq!(events[0].duration_ms, 400);
+    }
+
+    /// Tests that opportunities need an edge of at least the threshold to open and close once an
+    /// exchange disconnects
+    #[test]
+    fn test_detect_threshold_and_disconnect() {
+        let mut detector = ArbitrageDetector::new("ethbtc".to_string(), Decimal::from(100));
+
+        detector.apply(
+            &OrdersBuilder::new(Exchange::Binance)
+                .with_bids(&[("0.069", "1")])
+                .with_asks(&[("0.070", "1")])
+                .message(),
+        );
+        // 50 bps isn't enough
+        let kraken = OrdersBuilder::new(Exchange::Kraken)
+            .with_bids(&[("0.07035", "1")])
+            .with_asks(&[("0.072", "1")])
+            .message();
+        assert!(detector.apply(&kraken).is_empty());
+        // 100 bps is
+        let kraken = OrdersBuilder::new(Exchange::Kraken)
+            .with_bids(&[("0.0707", "1")])
+            .with_asks(&[("0.072", "1")])
+            .message();
+        let events = detector.apply(&kraken);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Opened);
+        assert_eq!(events[0].edge_bps, 100.0);
+
+        let events = detector.apply(&OrderbookMessage::Status {
+            exchange: Exchange::Kraken,
+            status: ConnectionStatus::Disconnected,
+        });
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Closed);
+        assert_eq!(events[0].sell_exchange, "Kraken");
+    }
+
+    /// Tests that the top of an exchange that went stale opens no opportunity, and closes the ones
+    /// it had open, until it sends an orderbook again
+    #[test]
+    fn test_detect_stale_exchange() {
+        let mut detector = ArbitrageDetector::new("ethbtc".to_string(), Decimal::ZERO)
+            .with_stale_after(Some(Duration::from_millis(100)));
+        let started = Instant::now();
+        let binance = |received_at| {
+            OrdersBuilder::new(Exchange::Binance)
+                .with_bids(&[("0.069", "1")])
+                .with_asks(&[("0.070", "1")])
+                .with_received_at(Some(received_at))
+                .message()
+        };
+        let bitstamp = |received_at| {
+            OrdersBuilder::new(Exchange::Bitstamp)
+                .with_bids(&[("0.0714", "1")])
+                .with_asks(&[("0.072", "1")])
+                .with_received_at(Some(received_at))
+                .message()
+        };
+
+        detector.apply(&binance(started));
+        let events = detector.apply(&bitstamp(started));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Opened);
+
+        let events = detector.refresh(started + Duration::from_millis(150));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Closed);
+        assert_eq!(events[0].duration_ms, 150);
+
+        // Bitstamp is fresh again but Binance's book is still too old to trade against
+        assert!(detector
+            .apply(&bitstamp(started + Duration::from_millis(200)))
+            .is_empty());
+
+        let events = detector.apply(&binance(started + Duration::from_millis(250)));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state(), ArbitrageState::Opened);
+    }
+}
@@ -0,0 +1,215 @@
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::Value;
+    use tokio::{sync::broadcast, time::timeout};
+    use tonic::transport::Server;
+
+    use crate::client::{
+        grpc_client::{orderbook::Source, stream_summaries},
+        output::{OutputFormat, SummaryWriter},
+    };
+    use crate::models::{
+        config::{ClientConfig, ServerConfig},
+        mapper::{Exchange, SummaryOutput},
+        messages::SymbolChannels,
+    };
+    use crate::server::{
+        grpc_server::{
+            orderbook::orderbook_aggregator_server::OrderbookAggregatorServer, OrderbookService,
+        },
+        metrics::Metrics,
+        shutdown::Shutdown,
+    };
+    use crate::tests::fixtures::{OrdersBuilder, SummaryBuilder};
+
+    /// Helper to build a numbered ethbtc summary with two asks and a bid
+    fn summary() -> SummaryOutput {
+        SummaryBuilder::new()
+            .with_sequence(7, 1666000000123456)
+            .with_source(Source {
+                exchange: "Binance".to_string(),
+                event_time_us: None,
+                update_id: Some(160),
+            })
+            .build()
+    }
+
+    /// Helper to write `summaries` in `format` and get the output back
+    fn write(format: OutputFormat, summaries: usize) -> String {
+        let mut writer = SummaryWriter::new(format, Vec::new());
+        for _ in 0..summaries {
+            writer.write(summary()).unwrap();
+        }
+
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    /// Tests that JSON lines carry one summary per line with its exact decimals
+    #[test]
+    fn test_output_json() {
+        let output = write(OutputFormat::Json, 2);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let json: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(json["symbol"], "ethbtc");
+        assert_eq!(json["spread"], "0.00001");
+        assert_eq!(json["asks"][1]["exchange"], "Kraken");
+        assert_eq!(json["asks"][1]["price"], "0.06802");
+        assert_eq!(json["bids"][0]["amount"], "0.25");
+        assert_eq!(json["sequence"], 7);
+        assert_eq!(json["server_timestamp_us"], 1666000000123456u64);
+        assert_eq!(json["source"]["update_id"], 160);
+        assert!(json["source"].get("event_time_us").is_none());
+    }
+
+    /// Tests that CSV has a single header and a ranked row per level
+    #[test]
+    fn test_output_csv() {
+        let output = write(OutputFormat::Csv, 2);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "symbol,spread,side,rank,exchange,price,amount");
+        assert_eq!(lines[1], "ethbtc,0.00001,ask,1,Binance,0.06801,1.5");
+        assert_eq!(lines[2], "ethbtc,0.00001,ask,2,Kraken,0.06802,12");
+        assert_eq!(lines[3], "ethbtc,0.00001,bid,1,Bitstamp,0.068,0.25");
+        assert_eq!(lines[4], lines[1]);
+    }
+
+    /// Tests that the table aligns its columns and that the debug view is the SummaryOutput one
+    #[test]
+    fn test_output_table_and_debug() {
+        let output = write(OutputFormat::Table, 1);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "ethbtc spread: 0.00001");
+        assert_eq!(lines[1], "SIDE  EXCHANGE    PRICE  AMOUNT");
+        assert_eq!(lines[2], "ask   Binance   0.06801     1.5");
+        assert_eq!(lines[3], "ask   Kraken    0.06802      12");
+        assert_eq!(lines[4], "bid   Bitstamp    0.068    0.25");
+
+        let output = write(OutputFormat::Debug, 1);
+        assert_eq!(output.trim_end(), format!("{:#?}", summary()));
+    }
+
+    /// Tests that the client stops after `count` summaries from a running server
+    #[tokio::test]
+    async fn test_stream_summaries_count() {
+        // Grab a free port for the server
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (chan_send, _) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send.clone())]);
+        let service = OrderbookService::new(
+            channels,
+            &ServerConfig::default(),
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        );
+        tokio::spawn(
+            Server::builder()
+                .add_service(OrderbookAggregatorServer::new(service))
+                .serve(addr),
+        );
+
+        // Keep publishing until the client got what it asked for
+        tokio::spawn(async move {
+            loop {
+                let _ = chan_send.send(
+                    OrdersBuilder::new(Exchange::Binance)
+                        .with_asks(&[("0.06801", "1")])
+                        .with_bids(&[("0.068", "1")])
+                        .message(),
+                );
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let config = ClientConfig {
+            url: Some(format!("http://{}", addr)),
+            count: 3,
+            output: OutputFormat::Json,
+            ..ClientConfig::default()
+        };
+        let mut writer = SummaryWriter::new(config.output, Vec::new());
+
+        // The server may take a moment to start listening
+        let mut received = None;
+        for _ in 0..50 {
+            match timeout(
+                Duration::from_secs(5),
+                stream_summaries(&config, &mut writer),
+            )
+            .await
+            .expect("timed out waiting for summaries")
+            {
+                Ok(count) => {
+                    received = Some(count);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+
+        assert_eq!(received, Some(3));
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output
+            .lines()
+            .all(|line| line.contains(r#""spread":"0.00001""#)));
+    }
+
+    /// Tests that the client stops once its duration elapsed even without summaries
+    #[tokio::test]
+    async fn test_stream_summaries_duration() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let service = OrderbookService::new(
+            channels,
+            &ServerConfig::default(),
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        );
+        tokio::spawn(
+            Server::builder()
+                .add_service(OrderbookAggregatorServer::new(service))
+                .serve(addr),
+        );
+
+        let config = ClientConfig {
+            url: Some(format!("http://{}", addr)),
+            duration_secs: 1,
+            ..ClientConfig::default()
+        };
+        let mut writer = SummaryWriter::new(config.output, Vec::new());
+
+        let mut received = None;
+        for _ in 0..50 {
+            match timeout(
+                Duration::from_secs(5),
+                stream_summaries(&config, &mut writer),
+            )
+            .await
+            .expect("duration didn't stop the client")
+            {
+                Ok(count) => {
+                    received = Some(count);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+
+        assert_eq!(received, Some(0));
+    }
+}
@@ -0,0 +1,243 @@
+#[cfg(test)]
+// figment::Jail closures return figment's own large error type
+#[allow(clippy::result_large_err)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::path::Path;
+
+    use figment::Jail;
+
+    use crate::models::{
+        config::{ClientConfig, ServerConfig},
+        consts::{BINANCE_WS_API, SERVER_PORT},
+        errors::ConfigError,
+        mapper::Exchange,
+    };
+
+    /// Helper to get the key of an invalid configuration
+    fn invalid_key(error: ConfigError) -> &'static str {
+        match error {
+            ConfigError::Invalid { key, .. } => key,
+            error => panic!("Expected an invalid configuration, got {:?}", error),
+        }
+    }
+
+    /// Tests that the defaults are the constants, which need at least one symbol to be valid
+    #[test]
+    fn test_server_config_defaults() {
+        Jail::expect_with(|_| {
+            let mut config = ServerConfig::load(None).expect("ok");
+            assert_eq!(config, ServerConfig::default());
+            assert_eq!(config.port, SERVER_PORT);
+            assert_eq!(config.binance.ws_api, BINANCE_WS_API);
+            assert_eq!(invalid_key(config.validate().unwrap_err()), "symbols");
+
+            config.symbols = vec!["ethbtc".to_string()];
+            config.validate().expect("ok");
+            Ok(())
+        });
+    }
+
+    /// Tests that the configuration file overrides the defaults and environment variables override both
+    #[test]
+    fn test_server_config_layers() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "orderbook.toml",
+                r#"
+                address = "127.0.0.1"
+                port = 50506
+                symbols = ["ethbtc", "btcusdt"]
+                exchanges = ["binance", "kraken"]
+                max_book_depth = 50
+
+                [binance]
+                ws_api = "ws://127.0.0.1:9000"
+                full_depth = true
+
+                [kraken]
+                ws_api = "ws://127.0.0.1:9001"
+                "#,
+            )?;
+            jail.set_env("ORDERBOOK_PORT", "50507");
+            jail.set_env("ORDERBOOK_EXCHANGES", "coinbase,okx");
+            jail.set_env("ORDERBOOK_BINANCE__REST_API", "http://127.0.0.1:9002");
+
+            let config = ServerConfig::load(Some(Path::new("orderbook.toml"))).expect("ok");
+            config.validate().expect("ok");
+
+            assert_eq!(config.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+            assert_eq!(config.port, 50507);
+            assert_eq!(config.symbols, vec!["ethbtc", "btcusdt"]);
+            assert_eq!(config.exchanges, vec![Exchange::Coinbase, Exchange::Okx]);
+            assert_eq!(config.max_book_depth, 50);
+            assert_eq!(config.binance.ws_api, "ws://127.0.0.1:9000");
+            assert_eq!(config.binance.rest_api, "http://127.0.0.1:9002");
+            assert!(config.binance.full_depth);
+            assert_eq!(config.kraken.ws_api, "ws://127.0.0.1:9001");
+            // Untouched values keep their defaults
+            assert_eq!(config.okx, ServerConfig::default().okx);
+            Ok(())
+        });
+    }
+
+    /// Tests that ORDERBOOK_SYMBOL still sets the symbols
+    #[test]
+    fn test_server_config_symbol_env() {
+        Jail::expect_with(|jail| {
+            jail.set_env("ORDERBOOK_SYMBOL", "ethbtc, btcusdt");
+
+            let config = ServerConfig::load(None).expect("ok");
+            assert_eq!(config.symbols, vec!["ethbtc", "btcusdt"]);
+            Ok(())
+        });
+    }
+
+    /// Tests that values that can't be used fail with the key that has to be fixed
+    #[test]
+    fn test_server_config_invalid() {
+        let valid = ServerConfig {
+            symbols: vec!["ethbtc".to_string()],
+            ..ServerConfig::default()
+        };
+        valid.validate().expect("ok");
+
+        let mut config = valid.clone();
+        config.default_depth = config.max_book_depth + 1;
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "default_depth");
+
+        let mut config = valid.clone();
+        config.backoff_max_ms = config.backoff_initial_ms - 1;
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "backoff_max_ms"
+        );
+
+        let mut config = valid.clone();
+        config.bitstamp.ws_api = "https://ws.bitstamp.net".to_string();
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "bitstamp.ws_api"
+        );
+
+        let mut config = valid.clone();
+        config.binance.depth_level = "depth50".to_string();
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "binance.depth_level"
+        );
+
+        let mut config = valid.clone();
+        config.okx.heartbeat_secs = 30;
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "okx.heartbeat_secs"
+        );
+
+        let mut config = valid.clone();
+        config.shutdown_timeout_ms = 0;
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "shutdown_timeout_ms"
+        );
+
+        let mut config = valid.clone();
+        config.stale_feed_ms = 0;
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "stale_feed_ms");
+
+        let mut config = valid.clone();
+        config.recorder.flush_secs = 0;
+        assert_eq!(
+            invalid_key(config.validate().unwrap_err()),
+            "recorder.flush_secs"
+        );
+
+        let mut config = valid.clone();
+        config.metrics_port = Some(config.port);
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "metrics_port");
+
+        let mut config = valid.clone();
+        config.replay.files = vec!["missing.jsonl.gz".into()];
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "replay.files");
+
+        let mut config = valid.clone();
+        config.tls.cert = Some("Cargo.toml".into());
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "tls.key");
+
+        let mut config = valid.clone();
+        config.tls.client_ca = Some("Cargo.toml".into());
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "tls.client_ca");
+
+        let mut config = valid;
+        config.tls.cert = Some("missing.pem".into());
+        config.tls.key = Some("Cargo.toml".into());
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "tls.cert");
+    }
+
+    /// Tests that files that are missing or can't be parsed fail to load
+    #[test]
+    fn test_server_config_load_errors() {
+        Jail::expect_with(|jail| {
+            let error = ServerConfig::load(Some(Path::new("missing.toml"))).unwrap_err();
+            assert_eq!(invalid_key(error), "config");
+
+            jail.create_file("orderbook.toml", r#"exchanges = ["ftx"]"#)?;
+            let error = ServerConfig::load(Some(Path::new("orderbook.toml"))).unwrap_err();
+            assert!(matches!(error, ConfigError::Load(_)));
+            assert!(error.to_string().contains("ftx"));
+
+            jail.create_file("orderbook.toml", "port = 70000")?;
+            let error = ServerConfig::load(Some(Path::new("orderbook.toml"))).unwrap_err();
+            assert!(matches!(error, ConfigError::Load(_)));
+            Ok(())
+        });
+    }
+
+    /// Tests that the client reads its own prefixed environment variables
+    #[test]
+    fn test_client_config_layers() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "client.toml",
+                r#"
+                address = "127.0.0.1"
+                symbols = "ethbtc,btcusdt"
+                depth = 20
+                "#,
+            )?;
+            jail.set_env("ORDERBOOK_CLIENT_PORT", "50506");
+            jail.set_env("ORDERBOOK_CLIENT_EXCHANGES", "binance");
+            // Meant for the server
+            jail.set_env("ORDERBOOK_PORT", "50507");
+
+            let config = ClientConfig::load(Some(Path::new("client.toml"))).expect("ok");
+            config.validate().expect("ok");
+
+            assert_eq!(config.server_url(), "http://127.0.0.1:50506");
+            assert_eq!(config.symbols, vec!["ethbtc", "btcusdt"]);
+            assert_eq!(config.depth, 20);
+            assert_eq!(config.exchanges, vec![Exchange::Binance]);
+            assert_eq!(config.throttle_ms, 0);
+            Ok(())
+        });
+    }
+
+    /// Tests that setting TLS options connects over https and that plaintext URLs are rejected
+    #[test]
+    fn test_client_config_tls() {
+        let mut config = ClientConfig {
+            address: "127.0.0.1".parse().unwrap(),
+            ..ClientConfig::default()
+        };
+        config.tls.domain = Some("localhost".to_string());
+        config.validate().expect("ok");
+        assert_eq!(config.server_url(), "https://127.0.0.1:50505");
+
+        config.url = Some("http://127.0.0.1:50505".to_string());
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "url");
+
+        config.url = Some("https://127.0.0.1:50505".to_string());
+        config.tls.cert = Some("Cargo.toml".into());
+        assert_eq!(invalid_key(config.validate().unwrap_err()), "tls.key");
+    }
+}
@@ -0,0 +1,733 @@
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use rust_decimal::{prelude::ToPrimitive, Decimal};
+    use serde_json::{json, Value};
+    use tokio::{net::TcpListener, sync::broadcast};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use crate::models::{
+        config::ServerConfig,
+        connectors::{
+            connector_for, normalize_symbol, split_symbol, BinanceConnector, BitstampConnector,
+            CoinbaseConnector, ExchangeConnector, KrakenConnector, OkxConnector,
+        },
+        errors::OrderbookError,
+        mapper::{Exchange, OfferData},
+        messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+        stream::listen,
+        subscribers::Subscribers,
+    };
+    use crate::server::{metrics::Metrics, shutdown::Shutdown};
+    use crate::tests::stubs::http_json_stub;
+
+    /// Helper to turn levels into (price, quantity) pairs for easier comparison
+    fn levels(offers: &[OfferData]) -> Vec<(f64, f64)> {
+        offers
+            .iter()
+            .map(|offer| {
+                (
+                    offer.price.to_f64().unwrap(),
+                    offer.quantity.to_f64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Helper to build exact decimals out of literals
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    /// Helper to build a list of symbols
+    fn symbols(symbols: &[&str]) -> Vec<String> {
+        symbols.iter().map(|symbol| symbol.to_string()).collect()
+    }
+
+    /// Tests that the Binance combined stream URL carries the depth stream of every symbol
+    #[test]
+    fn test_binance_url() {
+        let connector = BinanceConnector::new("ws://localhost:9443".to_string());
+
+        assert_eq!(
+            connector.url(&symbols(&["ethbtc"])),
+            "ws://localhost:9443/stream?streams=ethbtc@depth20@100ms"
+        );
+        assert_eq!(
+            connector.url(&symbols(&["ethbtc", "btcusdt"])),
+            "ws://localhost:9443/stream?streams=ethbtc@depth20@100ms/btcusdt@depth20@100ms"
+        );
+        assert!(connector
+            .subscribe_messages(&symbols(&["ethbtc"]))
+            .is_empty());
+    }
+
+    /// Tests that a Binance partial depth payload is parsed into the Orders of its stream's symbol
+    #[test]
+    fn test_binance_parse() {
+        let mut connector = BinanceConnector::default();
+        let payload = r#"{
+            "stream": "ethbtc@depth20@100ms",
+            "data": {
+                "lastUpdateId": 160,
+                "bids": [["0.0024", "10"], ["0.0023", "5.5"]],
+                "asks": [["0.0026", "100"]]
+            }
+        }"#;
+
+        let orders = connector.parse(payload).unwrap().expect("orders");
+
+        assert_eq!(orders.exchange, Exchange::Binance);
+        assert_eq!(orders.symbol, "ethbtc");
+        assert_eq!(orders.bids.len(), 2);
+        assert_eq!(orders.asks.len(), 1);
+        assert_eq!(orders.bids[1].price, dec("0.0023"));
+        assert_eq!(orders.bids[1].quantity, dec("5.5"));
+        assert_eq!(orders.asks[0].price, dec("0.0026"));
+        assert_eq!(orders.update_id, Some(160));
+        assert_eq!(orders.event_time_us, None);
+
+        assert!(connector.parse(r#"{"result": null, "id": 1}"#).is_err());
+    }
+
+    /// Tests the Bitstamp subscription messages
+    #[test]
+    fn test_bitstamp_subscribe_messages() {
+        let connector = BitstampConnector::default();
+
+        let subscribe = connector.subscribe_messages(&symbols(&["ethbtc", "btcusd"]));
+        assert_eq!(subscribe.len(), 2);
+        let subscribe: Vec<Value> = subscribe
+            .iter()
+            .map(|message| serde_json::from_str(message.to_text().unwrap()).unwrap())
+            .collect();
+        assert_eq!(subscribe[0]["event"], "bts:subscribe");
+        assert_eq!(subscribe[0]["data"]["channel"], "order_book_ethbtc");
+        assert_eq!(subscribe[1]["data"]["channel"], "order_book_btcusd");
+
+        let unsubscribe = connector.unsubscribe_messages(&symbols(&["ethbtc"]));
+        let unsubscribe: Value = serde_json::from_str(unsubscribe[0].to_text().unwrap()).unwrap();
+        assert_eq!(unsubscribe["event"], "bts:unsubscribe");
+    }
+
+    /// Tests that Bitstamp orderbooks are parsed and subscription acknowledgements are skipped
+    #[test]
+    fn test_bitstamp_parse() {
+        let mut connector = BitstampConnector::default();
+        let subscription_succeeded = r#"{"event": "bts:subscription_succeeded", "channel": "order_book_ethbtc", "data": {}}"#;
+        let payload = r#"{
+            "data": {
+                "timestamp": "1666000000",
+                "microtimestamp": "1666000000000000",
+                "bids": [["0.07000000", "1.50000000"]],
+                "asks": [["0.07100000", "2.00000000"], ["0.07200000", "0.40000000"]]
+            },
+            "channel": "order_book_ethbtc",
+            "event": "data"
+        }"#;
+
+        assert!(connector.parse(subscription_succeeded).unwrap().is_none());
+
+        let orders = connector.parse(payload).unwrap().expect("orders");
+        assert_eq!(orders.exchange, Exchange::Bitstamp);
+        assert_eq!(orders.symbol, "ethbtc");
+        assert_eq!(orders.bids.len(), 1);
+        assert_eq!(orders.asks.len(), 2);
+        assert_eq!(orders.asks[1].price, dec("0.072"));
+        assert_eq!(orders.asks[1].quantity, dec("0.4"));
+        assert_eq!(orders.event_time_us, Some(1666000000000000));
+        assert_eq!(orders.update_id, None);
+    }
+
+    /// Tests that exchanges selected by name get their own connector
+    #[test]
+    fn test_connector_for() {
+        let exchanges: Vec<Exchange> = ["binance", "Bitstamp", "COINBASE", "kraken", "okx"]
+            .iter()
+            .map(|name| name.parse().unwrap())
+            .collect();
+
+        for exchange in exchanges {
+            let connector = connector_for(exchange, &ServerConfig::default());
+            assert_eq!(connector.exchange(), exchange);
+        }
+        assert!("ftx".parse::<Exchange>().is_err());
+    }
+
+    /// Tests that connectors point at the endpoints of the configuration, e.g. a local mock exchange
+    #[test]
+    fn test_connector_for_config() {
+        let mut config = ServerConfig::default();
+        config.coinbase.ws_api = "ws://127.0.0.1:9000".to_string();
+        config.kraken.ws_api = "ws://127.0.0.1:9001".to_string();
+        config.okx.heartbeat_secs = 5;
+
+        let symbols = symbols(&["ethbtc"]);
+        let coinbase = connector_for(Exchange::Coinbase, &config);
+        assert_eq!(coinbase.url(&symbols), "ws://127.0.0.1:9000");
+        let kraken = connector_for(Exchange::Kraken, &config);
+        assert_eq!(kraken.url(&symbols), "ws://127.0.0.1:9001");
+        let okx = connector_for(Exchange::Okx, &config);
+        assert_eq!(okx.heartbeat().unwrap().0.as_secs(), 5);
+    }
+
+    /// Tests splitting symbols into base and quote assets
+    #[test]
+    fn test_split_symbol() {
+        let quote_assets = ServerConfig::default().quote_assets;
+        let split = |symbol| split_symbol(symbol, &quote_assets).unwrap();
+
+        assert_eq!(split("ethbtc"), ("ETH".to_string(), "BTC".to_string()));
+        assert_eq!(split("btcusdt"), ("BTC".to_string(), "USDT".to_string()));
+        assert_eq!(split("eth-btc"), ("ETH".to_string(), "BTC".to_string()));
+        assert_eq!(split("ETH/USD"), ("ETH".to_string(), "USD".to_string()));
+        assert!(split_symbol("btc", &quote_assets).is_none());
+        assert!(split_symbol("foobar", &quote_assets).is_none());
+        assert_eq!(
+            split_symbol("ethdai", &["dai".to_string()]).unwrap(),
+            ("ETH".to_string(), "DAI".to_string())
+        );
+    }
+
+    /// Tests that symbols are normalized to lower case without separators
+    #[test]
+    fn test_normalize_symbol() {
+        assert_eq!(normalize_symbol("ethbtc"), "ethbtc");
+        assert_eq!(normalize_symbol("ETH-BTC"), "ethbtc");
+        assert_eq!(normalize_symbol("eth/usd"), "ethusd");
+        assert_eq!(normalize_symbol("BTC_USDT"), "btcusdt");
+    }
+
+    /// Tests the Coinbase level2 subscription
+    #[test]
+    fn test_coinbase_subscribe_messages() {
+        let connector = CoinbaseConnector::default();
+
+        let subscribe = connector.subscribe_messages(&symbols(&["ethbtc", "btcusd"]));
+        assert_eq!(subscribe.len(), 1);
+        let subscribe: Value = serde_json::from_str(subscribe[0].to_text().unwrap()).unwrap();
+        assert_eq!(subscribe["type"], "subscribe");
+        assert_eq!(subscribe["product_ids"], json!(["ETH-BTC", "BTC-USD"]));
+        assert_eq!(subscribe["channels"][0], "level2_batch");
+    }
+
+    /// Tests that Coinbase updates are applied on top of the snapshot
+    #[test]
+    fn test_coinbase_parse() {
+        let mut connector = CoinbaseConnector::default();
+
+        assert!(connector
+            .parse(include_str!("fixtures/coinbase_subscriptions.json"))
+            .unwrap()
+            .is_none());
+        assert!(connector
+            .parse(include_str!("fixtures/coinbase_l2update.json"))
+            .is_err());
+
+        let orders = connector
+            .parse(include_str!("fixtures/coinbase_snapshot.json"))
+            .unwrap()
+            .expect("orders");
+        assert_eq!(orders.exchange, Exchange::Coinbase);
+        assert_eq!(orders.symbol, "ethbtc");
+        assert_eq!(
+            levels(&orders.asks),
+            vec![(0.0702, 2.0), (0.0703, 0.8), (0.0704, 6.3)]
+        );
+        assert_eq!(
+            levels(&orders.bids),
+            vec![(0.07015, 1.1), (0.0701, 4.2), (0.07, 12.5)]
+        );
+
+        let orders = connector
+            .parse(include_str!("fixtures/coinbase_l2update.json"))
+            .unwrap()
+            .expect("orders");
+        assert_eq!(
+            levels(&orders.asks),
+            vec![(0.0702, 1.5), (0.07025, 0.25), (0.0703, 0.8), (0.0704, 6.3)]
+        );
+        assert_eq!(
+            levels(&orders.bids),
+            vec![(0.07012, 3.0), (0.0701, 4.2), (0.07, 12.5)]
+        );
+    }
+
+    /// Tests the Kraken book subscription
+    #[test]
+    fn test_kraken_subscribe_messages() {
+        let connector = KrakenConnector::default();
+
+        let subscribe = connector.subscribe_messages(&symbols(&["ethbtc", "btcusd"]));
+        assert_eq!(subscribe.len(), 1);
+        let subscribe: Value = serde_json::from_str(subscribe[0].to_text().unwrap()).unwrap();
+        assert_eq!(subscribe["event"], "subscribe");
+        assert_eq!(subscribe["pair"], json!(["ETH/XBT", "XBT/USD"]));
+        assert_eq!(subscribe["subscription"]["name"], "book");
+        assert_eq!(subscribe["subscription"]["depth"], 10);
+    }
+
+    /// Tests that Kraken updates with both sides are applied on top of the snapshot and
+    /// heartbeats are skipped
+    #[test]
+    fn test_kraken_parse() {
+        let mut connector = KrakenConnector::default();
+
+        assert!(connector
+            .parse(include_str!("fixtures/kraken_heartbeat.json"))
+            .unwrap()
+            .is_none());
+        let error = connector
+            .parse(include_str!("fixtures/kraken_subscription_error.json"))
+            .expect_err("subscription rejected");
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(OrderbookError::SubscriptionRejected {
+                exchange: Exchange::Kraken,
+                ..
+            })
+        ));
+
+        let orders = connector
+            .parse(include_str!("fixtures/kraken_snapshot.json"))
+            .unwrap()
+            .expect("orders");
+        assert_eq!(orders.exchange, Exchange::Kraken);
+        assert_eq!(orders.symbol, "ethbtc");
+        assert_eq!(
+            levels(&orders.asks),
+            vec![(0.0702, 2.0), (0.0703, 0.8), (0.0704, 6.3)]
+        );
+        assert_eq!(levels(&orders.bids), vec![(0.07015, 1.1), (0.0701, 4.2)]);
+
+        let orders = connector
+            .parse(include_str!("fixtures/kraken_update.json"))
+            .unwrap()
+            .expect("orders");
+        assert_eq!(
+            levels(&orders.asks),
+            vec![(0.07025, 0.5), (0.0703, 0.8), (0.0704, 6.3)]
+        );
+        assert_eq!(
+            levels(&orders.bids),
+            vec![(0.07016, 3.0), (0.07015, 1.1), (0.0701, 4.2)]
+        );
+    }
+
+    /// Tests that listeners give up on connections whose subscription was rejected
+    #[tokio::test]
+    async fn test_listen_subscription_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+            ws_stream.next().await.unwrap().unwrap();
+
+            let rejected = include_str!("fixtures/kraken_subscription_error.json");
+            ws_stream
+                .send(Message::Text(rejected.to_string()))
+                .await
+                .unwrap();
+            // Keep the connection open until the client hangs up
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let error = listen(
+            &mut KrakenConnector::new(api_url),
+            &symbols(&["ethbtc"]),
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &Metrics::new(),
+            &Shutdown::default(),
+        )
+        .await
+        .expect_err("subscription rejected");
+        assert!(matches!(
+            error,
+            OrderbookError::SubscriptionRejected {
+                exchange: Exchange::Kraken,
+                ..
+            }
+        ));
+    }
+
+    /// Tests the OKX books5 subscription and heartbeat
+    #[test]
+    fn test_okx_subscribe_messages() {
+        let connector = OkxConnector::default();
+
+        let subscribe = connector.subscribe_messages(&symbols(&["ethbtc", "btcusd"]));
+        assert_eq!(subscribe.len(), 1);
+        let subscribe: Value = serde_json::from_str(subscribe[0].to_text().unwrap()).unwrap();
+        assert_eq!(subscribe["op"], "subscribe");
+        assert_eq!(subscribe["args"][0]["channel"], "books5");
+        assert_eq!(subscribe["args"][0]["instId"], "ETH-BTC");
+        assert_eq!(subscribe["args"][1]["instId"], "BTC-USD");
+
+        let (_, heartbeat) = connector.heartbeat().expect("heartbeat");
+        assert_eq!(heartbeat.to_text().unwrap(), "ping");
+    }
+
+    /// Tests that OKX books5 snapshots are parsed and events are skipped or reported
+    #[test]
+    fn test_okx_parse() {
+        let mut connector = OkxConnector::default();
+
+        assert!(connector.parse("pong").unwrap().is_none());
+        assert!(connector
+            .parse(include_str!("fixtures/okx_subscribe.json"))
+            .unwrap()
+            .is_none());
+        let error = connector
+            .parse(include_str!("fixtures/okx_error.json"))
+            .expect_err("subscription rejected");
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(OrderbookError::SubscriptionRejected {
+                exchange: Exchange::Okx,
+                ..
+            })
+        ));
+
+        let orders = connector
+            .parse(include_str!("fixtures/okx_books5.json"))
+            .unwrap()
+            .expect("orders");
+        assert_eq!(orders.exchange, Exchange::Okx);
+        assert_eq!(orders.symbol, "ethbtc");
+        assert_eq!(levels(&orders.asks), vec![(0.0702, 2.1), (0.0703, 0.8)]);
+        assert_eq!(levels(&orders.bids), vec![(0.0701, 4.2), (0.07, 12.5)]);
+    }
+
+    /// Helper to build a Binance diff depth event
+    fn binance_diff(first: u64, last: u64, bids: Value, asks: Value) -> Message {
+        let event = json!({
+            "e": "depthUpdate",
+            "E": 1667401445123u64,
+            "s": "ETHBTC",
+            "U": first,
+            "u": last,
+            "b": bids,
+            "a": asks
+        });
+        let payload = json!({
+            "stream": "ethbtc@depth@100ms",
+            "data": event
+        });
+
+        Message::Text(payload.to_string())
+    }
+
+    /// Tests that the Binance full depth mode syncs the snapshot with the diff stream, drops
+    /// events already in the snapshot and bails out on a sequence gap
+    #[tokio::test]
+    async fn test_binance_full_depth_sync() {
+        let snapshot = json!({
+            "lastUpdateId": 100,
+            "bids": [["0.07000", "1.0"], ["0.06990", "2.0"]],
+            "asks": [["0.07010", "1.0"], ["0.07020", "3.0"]]
+        });
+        let snapshot_url = http_json_stub(snapshot.to_string()).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+
+            let events = [
+                // Already part of the snapshot
+                binance_diff(95, 100, json!([["0.07000", "0.5"]]), json!([])),
+                // Overlaps the snapshot
+                binance_diff(
+                    99,
+                    102,
+                    json!([["0.07000", "0"]]),
+                    json!([["0.07010", "1.5"]]),
+                ),
+                binance_diff(103, 105, json!([["0.06995", "4.0"]]), json!([])),
+                // Update 106 went missing
+                binance_diff(107, 110, json!([]), json!([["0.07030", "1.0"]])),
+            ];
+            for event in events {
+                ws_stream.send(event).await.unwrap();
+            }
+            // Keep the connection open until the client hangs up
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let (chan_send, mut chan_recv) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send)]);
+        let mut connector = BinanceConnector::full_depth(api_url.clone(), snapshot_url);
+        assert_eq!(
+            connector.url(&symbols(&["ethbtc"])),
+            format!("{}/stream?streams=ethbtc@depth@100ms", api_url)
+        );
+
+        let error = listen(
+            &mut connector,
+            &symbols(&["ethbtc"]),
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &Metrics::new(),
+            &Shutdown::default(),
+        )
+        .await
+        .expect_err("sequence gap");
+        match &error {
+            OrderbookError::SequenceGap {
+                exchange,
+                expected,
+                received,
+            } => {
+                assert_eq!(*exchange, Exchange::Binance);
+                assert_eq!(*expected, 106);
+                assert_eq!(*received, 107);
+            }
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+        drop(connector);
+
+        assert!(matches!(
+            chan_recv.recv().await.unwrap(),
+            OrderbookMessage::Status {
+                status: ConnectionStatus::Connected,
+                ..
+            }
+        ));
+
+        let mut books = Vec::new();
+        let mut sources = Vec::new();
+        while let Ok(OrderbookMessage::Message { message }) = chan_recv.try_recv() {
+            books.push((levels(&message.asks), levels(&message.bids)));
+            sources.push((message.event_time_us, message.update_id));
+        }
+        assert_eq!(
+            books,
+            vec![
+                (vec![(0.0701, 1.5), (0.0702, 3.0)], vec![(0.0699, 2.0)]),
+                (
+                    vec![(0.0701, 1.5), (0.0702, 3.0)],
+                    vec![(0.06995, 4.0), (0.0699, 2.0)]
+                ),
+            ]
+        );
+        // Event times are in milliseconds on Binance
+        assert_eq!(
+            sources,
+            vec![
+                (Some(1667401445123000), Some(102)),
+                (Some(1667401445123000), Some(105)),
+            ]
+        );
+
+        server.abort();
+    }
+
+    /// Helper to build a Bitstamp diff_order_book update
+    fn bitstamp_diff(microtimestamp: u64, bids: Value, asks: Value) -> Message {
+        let event = json!({
+            "data": {
+                "timestamp": (microtimestamp / 1_000_000).to_string(),
+                "microtimestamp": microtimestamp.to_string(),
+                "bids": bids,
+                "asks": asks
+            },
+            "channel": "diff_order_book_ethbtc",
+            "event": "data"
+        });
+
+        Message::Text(event.to_string())
+    }
+
+    /// Tests that the Bitstamp diff mode applies updates newer than the snapshot and bails out
+    /// on an update that arrives out of order
+    #[tokio::test]
+    async fn test_bitstamp_diff_sync() {
+        let snapshot = json!({
+            "timestamp": "1667401445",
+            "microtimestamp": "1667401445000100",
+            "bids": [["0.07000", "1.0"], ["0.06990", "2.0"]],
+            "asks": [["0.07010", "1.0"], ["0.07020", "3.0"]]
+        });
+        let snapshot_url = http_json_stub(snapshot.to_string()).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+
+            let subscribe = ws_stream.next().await.unwrap().unwrap();
+            let subscribe: Value = serde_json::from_str(subscribe.to_text().unwrap()).unwrap();
+            assert_eq!(subscribe["data"]["channel"], "diff_order_book_ethbtc");
+
+            let events = [
+                Message::Text(
+                    r#"{"event": "bts:subscription_succeeded", "channel": "diff_order_book_ethbtc", "data": {}}"#
+                        .to_string(),
+                ),
+                // Already part of the snapshot
+                bitstamp_diff(1667401445000050, json!([["0.07000", "0"]]), json!([])),
+                bitstamp_diff(1667401445000200, json!([["0.07000", "0"]]), json!([["0.07010", "1.5"]])),
+                bitstamp_diff(1667401445000300, json!([["0.06995", "4.0"]]), json!([])),
+                // Older than the last update
+                bitstamp_diff(1667401445000250, json!([]), json!([["0.07030", "1.0"]])),
+            ];
+            for event in events {
+                ws_stream.send(event).await.unwrap();
+            }
+            // Keep the connection open until the client hangs up
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let (chan_send, mut chan_recv) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send)]);
+        let mut connector = BitstampConnector::diff(api_url, snapshot_url);
+
+        let error = listen(
+            &mut connector,
+            &symbols(&["ethbtc"]),
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &Metrics::new(),
+            &Shutdown::default(),
+        )
+        .await
+        .expect_err("out of order");
+        match &error {
+            OrderbookError::OutOfOrder {
+                exchange,
+                last,
+                received,
+            } => {
+                assert_eq!(*exchange, Exchange::Bitstamp);
+                assert_eq!(*last, 1667401445000300);
+                assert_eq!(*received, 1667401445000250);
+            }
+            _ => panic!("Unexpected error: {:?}", error),
+        }
+        drop(connector);
+
+        assert!(matches!(
+            chan_recv.recv().await.unwrap(),
+            OrderbookMessage::Status {
+                status: ConnectionStatus::Connected,
+                ..
+            }
+        ));
+
+        let mut books = Vec::new();
+        let mut event_times = Vec::new();
+        while let Ok(OrderbookMessage::Message { message }) = chan_recv.try_recv() {
+            books.push((levels(&message.asks), levels(&message.bids)));
+            event_times.push(message.event_time_us);
+        }
+        assert_eq!(
+            books,
+            vec![
+                (vec![(0.0701, 1.5), (0.0702, 3.0)], vec![(0.0699, 2.0)]),
+                (
+                    vec![(0.0701, 1.5), (0.0702, 3.0)],
+                    vec![(0.06995, 4.0), (0.0699, 2.0)]
+                ),
+            ]
+        );
+        assert_eq!(
+            event_times,
+            vec![Some(1667401445000200), Some(1667401445000300)]
+        );
+
+        server.abort();
+    }
+
+    /// Tests that a single connection subscribes to every symbol and routes each orderbook to
+    /// the channel of its symbol
+    #[tokio::test]
+    async fn test_listen_routes_symbols() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+
+            for channel in ["order_book_ethbtc", "order_book_btcusd"] {
+                let subscribe = ws_stream.next().await.unwrap().unwrap();
+                let subscribe: Value = serde_json::from_str(subscribe.to_text().unwrap()).unwrap();
+                assert_eq!(subscribe["data"]["channel"], channel);
+            }
+
+            for (channel, price, ask) in [
+                ("order_book_btcusd", "20000.0", "20001.0"),
+                ("order_book_ethbtc", "0.07", "0.071"),
+            ] {
+                let event = json!({
+                    "data": {
+                        "timestamp": "1666000000",
+                        "microtimestamp": "1666000000000000",
+                        "bids": [[price, "1.0"]],
+                        "asks": [[ask, "2.0"]]
+                    },
+                    "channel": channel,
+                    "event": "data"
+                });
+                ws_stream
+                    .send(Message::Text(event.to_string()))
+                    .await
+                    .unwrap();
+            }
+            ws_stream.close(None).await.unwrap();
+        });
+
+        let (ethbtc_send, mut ethbtc_recv) = broadcast::channel(16);
+        let (btcusd_send, mut btcusd_recv) = broadcast::channel(16);
+        let channels = SymbolChannels::from([
+            ("ethbtc".to_string(), ethbtc_send),
+            ("btcusd".to_string(), btcusd_send),
+        ]);
+        let mut connector = BitstampConnector::new(api_url);
+
+        listen(
+            &mut connector,
+            &symbols(&["ethbtc", "btcusd"]),
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &Metrics::new(),
+            &Shutdown::default(),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+
+        for (chan_recv, price) in [(&mut ethbtc_recv, "0.07"), (&mut btcusd_recv, "20000.0")] {
+            assert!(matches!(
+                chan_recv.try_recv().unwrap(),
+                OrderbookMessage::Status {
+                    status: ConnectionStatus::Connected,
+                    ..
+                }
+            ));
+            match chan_recv.try_recv().unwrap() {
+                OrderbookMessage::Message { message } => {
+                    assert_eq!(message.bids[0].price, dec(price));
+                }
+                msg => panic!("Unexpected message: {:?}", msg),
+            }
+            assert!(chan_recv.try_recv().is_err());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use tonic::{Code, Status};
+
+    use crate::models::{book::Side, errors::OrderbookError, mapper::Exchange};
+
+    /// Tests that connector errors keep their kind and anything else is a parse failure
+    #[test]
+    fn test_parse_classifies_connector_errors() {
+        let error = OrderbookError::parse(Exchange::Okx, anyhow!("expected value"));
+        assert_eq!(error.kind(), "parse");
+        assert_eq!(error.exchange(), Some(Exchange::Okx));
+
+        let rejected = OrderbookError::SubscriptionRejected {
+            exchange: Exchange::Kraken,
+            reason: "Currency pair not supported".to_string(),
+        };
+        let error = OrderbookError::parse(Exchange::Kraken, rejected.into());
+        assert_eq!(error.kind(), "subscription_rejected");
+        assert!(!error.requires_resync());
+
+        let gap = OrderbookError::SequenceGap {
+            exchange: Exchange::Binance,
+            expected: 2,
+            received: 3,
+        };
+        let error = OrderbookError::parse(Exchange::Binance, gap.into());
+        assert_eq!(error.kind(), "sequence_gap");
+        assert!(error.requires_resync());
+    }
+
+    /// Tests that errors reach clients with the status code of their kind
+    #[test]
+    fn test_errors_to_status() {
+        let cases = [
+            (
+                OrderbookError::connection(Exchange::Binance, anyhow!("reset")),
+                Code::Unavailable,
+            ),
+            (
+                OrderbookError::EmptyBook {
+                    exchange: Exchange::Bitstamp,
+                    symbol: "ethbtc".to_string(),
+                    side: Side::Bid,
+                },
+                Code::NotFound,
+            ),
+            (
+                OrderbookError::OutOfOrder {
+                    exchange: Exchange::Bitstamp,
+                    last: 2,
+                    received: 1,
+                },
+                Code::DataLoss,
+            ),
+            (
+                OrderbookError::ClientLag {
+                    symbol: "ethbtc".to_string(),
+                    skipped: 5,
+                },
+                Code::ResourceExhausted,
+            ),
+        ];
+
+        for (error, code) in cases {
+            let message = error.to_string();
+            let status = Status::from(error);
+            assert_eq!(status.code(), code);
+            assert_eq!(status.message(), message);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Instant;
+
+use rust_decimal::Decimal;
+
+use crate::client::grpc_client::orderbook::{
+    ConnectionStatus, ExchangeStatus, Level, Source, Summary,
+};
+use crate::models::{
+    mapper::{Exchange, OfferData, SummaryOutput},
+    messages::{OrderbookMessage, Orders},
+};
+
+/// Builder of the ethbtc orderbooks exchanges send, starting off as a valid book received just now
+/// with a bid at 0.07 and an ask at 0.071
+pub struct OrdersBuilder {
+    orders: Orders,
+}
+
+impl OrdersBuilder {
+    pub fn new(exchange: Exchange) -> Self {
+        OrdersBuilder {
+            orders: Orders {
+                exchange,
+                symbol: "ethbtc".to_string(),
+                bids: levels(&[("0.07", "1")]),
+                asks: levels(&[("0.071", "1")]),
+                received_at: Some(Instant::now()),
+                event_time_us: None,
+                update_id: None,
+            },
+        }
+    }
+
+    /// Replaces the bids with (price, quantity) levels
+    pub fn with_bids<S: AsRef<str>>(mut self, bids: &[(S, S)]) -> Self {
+        self.orders.bids = levels(bids);
+        self
+    }
+
+    /// Replaces the asks with (price, quantity) levels
+    pub fn with_asks<S: AsRef<str>>(mut self, asks: &[(S, S)]) -> Self {
+        self.orders.asks = levels(asks);
+        self
+    }
+
+    pub fn with_received_at(mut self, received_at: Option<Instant>) -> Self {
+        self.orders.received_at = received_at;
+        self
+    }
+
+    pub fn with_event(mut self, event_time_us: u64, update_id: u64) -> Self {
+        self.orders.event_time_us = Some(event_time_us);
+        self.orders.update_id = Some(update_id);
+        self
+    }
+
+    pub fn build(self) -> Orders {
+        self.orders
+    }
+
+    /// Builds the message listeners send over the channel of the symbol
+    pub fn message(self) -> OrderbookMessage {
+        OrderbookMessage::Message {
+            message: Box::new(self.orders),
+        }
+    }
+}
+
+/// Helper to parse (price, quantity) pairs into levels
+fn levels<S: AsRef<str>>(levels: &[(S, S)]) -> Vec<OfferData> {
+    levels
+        .iter()
+        .map(|(price, quantity)| OfferData {
+            price: price.as_ref().parse::<Decimal>().unwrap(),
+            quantity: quantity.as_ref().parse::<Decimal>().unwrap(),
+        })
+        .collect()
+}
+
+/// Builder of the ethbtc summaries clients receive, starting off with asks of Binance at 0.06801
+/// and Kraken at 0.06802 and a bid of Bitstamp at 0.068
+pub struct SummaryBuilder {
+    summary: Summary,
+}
+
+impl SummaryBuilder {
+    pub fn new() -> Self {
+        SummaryBuilder {
+            summary: Summary {
+                symbol: "ethbtc".to_string(),
+                spread_decimal: "0.00001".to_string(),
+                asks: vec![
+                    level("Binance", "0.06801", "1.5"),
+                    level("Kraken", "0.06802", "12"),
+                ],
+                bids: vec![level("Bitstamp", "0.068", "0.25")],
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.summary.symbol = symbol.to_string();
+        self
+    }
+
+    /// Adds the connection status of `exchange` and the age of its book
+    pub fn with_exchange(
+        mut self,
+        exchange: &str,
+        status: ConnectionStatus,
+        last_update_age_ms: Option<u64>,
+    ) -> Self {
+        self.summary.exchanges.push(ExchangeStatus {
+            exchange: exchange.to_string(),
+            status: status as i32,
+            last_update_age_ms,
+        });
+        self
+    }
+
+    /// Numbers and stamps the summary the way the server does
+    pub fn with_sequence(mut self, sequence: u64, server_timestamp_us: u64) -> Self {
+        self.summary.sequence = sequence;
+        self.summary.server_timestamp_us = server_timestamp_us;
+        self
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.summary.source = Some(source);
+        self
+    }
+
+    pub fn build(self) -> SummaryOutput {
+        SummaryOutput::from(self.summary)
+    }
+}
+
+/// Helper to build a level with exact decimals
+fn level(exchange: &str, price: &str, amount: &str) -> Level {
+    Level {
+        exchange: exchange.to_string(),
+        price_decimal: price.to_string(),
+        amount_decimal: amount.to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,244 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use tokio::sync::broadcast;
+    use tokio_stream::StreamExt;
+    use tonic::transport::{Channel, Server};
+    use tonic_health::{
+        pb::{
+            health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+        },
+        server::health_reporter,
+    };
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest,
+    };
+
+    use crate::models::{
+        config::ServerConfig,
+        mapper::Exchange,
+        messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+    };
+    use crate::server::{
+        grpc_server::serve,
+        health::report_health,
+        metrics::Metrics,
+        shutdown::{Shutdown, ShutdownController},
+    };
+    use crate::tests::fixtures::OrdersBuilder;
+    use crate::tests::mock_exchange::{binance_depth, MockExchange, Step};
+
+    /// Helper to grab a free port on loopback
+    fn free_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    /// Helper to connect to the server at `addr`, which may take a moment to start listening
+    async fn connect(addr: SocketAddr) -> Channel {
+        let url = format!("http://{}", addr);
+        for _ in 0..50 {
+            match Channel::from_shared(url.clone()).unwrap().connect().await {
+                Ok(channel) => return channel,
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        panic!("server never started listening on {}", addr);
+    }
+
+    /// Helper to wait until both the overall status and the one of the orderbook service are `expected`
+    async fn wait_for_status(client: &mut HealthClient<Channel>, expected: ServingStatus) {
+        let started = Instant::now();
+        loop {
+            let mut statuses = Vec::new();
+            for service in ["", "orderbook.OrderbookAggregator"] {
+                let response = client
+                    .check(HealthCheckRequest {
+                        service: service.to_string(),
+                    })
+                    .await
+                    .unwrap();
+                statuses.push(response.into_inner().status());
+            }
+            if statuses == [expected, expected] {
+                return;
+            }
+
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "timed out waiting for {:?}, got {:?}",
+                expected,
+                statuses
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Tests that the server is only serving while a feed is connected and sent an orderbook recently
+    #[tokio::test]
+    async fn test_health_follows_feeds() {
+        let (chan_send, _) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send.clone())]);
+        let (reporter, health) = health_reporter();
+        let metrics = Arc::new(Metrics::new());
+        report_health(
+            &channels,
+            reporter,
+            Duration::from_millis(200),
+            metrics.clone(),
+            Shutdown::default(),
+        )
+        .await;
+
+        let addr = free_addr();
+        tokio::spawn(Server::builder().add_service(health).serve(addr));
+        let mut client = HealthClient::new(connect(addr).await);
+
+        // Connected isn't enough until an orderbook comes through
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+        chan_send
+            .send(OrderbookMessage::Status {
+                exchange: Exchange::Binance,
+                status: ConnectionStatus::Connected,
+            })
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        wait_for_status(&mut client, ServingStatus::Serving).await;
+
+        // Quiet feeds go stale, which is counted once
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(metrics
+            .encode()
+            .unwrap()
+            .contains(r#"orderbook_errors_total{exchange="Binance",kind="stale_book"} 1"#));
+
+        // A single live feed is enough, and disconnected ones stop counting right away
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Bitstamp).message())
+            .unwrap();
+        wait_for_status(&mut client, ServingStatus::Serving).await;
+        for exchange in [Exchange::Binance, Exchange::Bitstamp] {
+            chan_send
+                .send(OrderbookMessage::Status {
+                    exchange,
+                    status: ConnectionStatus::Disconnected,
+                })
+                .unwrap();
+        }
+        let started = Instant::now();
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+
+    /// Tests that fresh feeds don't bring the server back to SERVING once shutdown is requested
+    #[tokio::test]
+    async fn test_health_stops_on_shutdown() {
+        let (chan_send, _) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send.clone())]);
+        let (reporter, health) = health_reporter();
+        let controller = ShutdownController::new();
+        report_health(
+            &channels,
+            reporter,
+            Duration::from_millis(200),
+            Arc::new(Metrics::new()),
+            controller.subscribe(),
+        )
+        .await;
+
+        let addr = free_addr();
+        tokio::spawn(Server::builder().add_service(health).serve(addr));
+        let mut client = HealthClient::new(connect(addr).await);
+
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        wait_for_status(&mut client, ServingStatus::Serving).await;
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+        assert!(controller.shutdown(deadline).await);
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        wait_for_status(&mut client, ServingStatus::NotServing).await;
+    }
+
+    /// Tests that the server answers health checks for the feeds of the mock exchange and lists
+    /// its services over reflection
+    #[tokio::test]
+    async fn test_serve_health_and_reflection() {
+        let frames = (0..500)
+            .flat_map(|_| {
+                [
+                    Step::Send(binance_depth(
+                        "ethbtc",
+                        &[("0.0700", "1")],
+                        &[("0.0710", "1")],
+                    )),
+                    Step::Sleep(Duration::from_millis(10)),
+                ]
+            })
+            .collect();
+        let binance = MockExchange::binance(vec![frames]).await;
+
+        let addr = free_addr();
+        let mut config = ServerConfig {
+            symbols: vec!["ethbtc".to_string()],
+            exchanges: vec![Exchange::Binance],
+            address: addr.ip(),
+            port: addr.port(),
+            ..ServerConfig::default()
+        };
+        config.binance.ws_api = binance.url();
+        config.validate().expect("ok");
+        let server = tokio::spawn(async move {
+            let _ = serve(config).await;
+        });
+        let channel = connect(addr).await;
+
+        let mut health = HealthClient::new(channel.clone());
+        wait_for_status(&mut health, ServingStatus::Serving).await;
+
+        let mut reflection = ServerReflectionClient::new(channel);
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut responses = reflection
+            .server_reflection_info(tokio_stream::iter([request]))
+            .await
+            .unwrap()
+            .into_inner();
+        let services = match responses.next().await.unwrap().unwrap().message_response {
+            Some(MessageResponse::ListServicesResponse(list)) => list
+                .service
+                .into_iter()
+                .map(|service| service.name)
+                .collect::<Vec<_>>(),
+            other => panic!("unexpected reflection response {:?}", other),
+        };
+        assert!(services.contains(&"orderbook.OrderbookAggregator".to_string()));
+        assert!(services.contains(&"grpc.health.v1.Health".to_string()));
+
+        server.abort();
+    }
+}
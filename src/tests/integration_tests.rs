@@ -0,0 +1,366 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use flate2::read::MultiGzDecoder;
+    use serde_json::Value;
+    use tokio::{
+        sync::{broadcast::Receiver, oneshot},
+        time::timeout,
+    };
+
+    use crate::client::{
+        grpc_client::{stream_summaries, subscribe},
+        output::{OutputFormat, SummaryWriter},
+    };
+    use crate::models::{
+        config::{ClientConfig, ServerConfig},
+        connectors::connector_for,
+        mapper::Exchange,
+        messages::{ConnectionStatus, OrderbookMessage},
+        stream_service::StreamService,
+    };
+    use crate::server::{
+        grpc_server::{serve, serve_with_shutdown},
+        metrics::Metrics,
+        shutdown::Shutdown,
+    };
+    use crate::tests::mock_exchange::{binance_depth, bitstamp_order_book, MockExchange, Step};
+
+    /// Helper to build a server streaming ethbtc from the mocks, reconnecting right away
+    fn server_config(binance: &MockExchange, bitstamp: &MockExchange) -> ServerConfig {
+        let mut config = ServerConfig {
+            symbols: vec!["ethbtc".to_string()],
+            backoff_initial_ms: 10,
+            backoff_max_ms: 50,
+            ..ServerConfig::default()
+        };
+        config.binance.ws_api = binance.url();
+        config.bitstamp.ws_api = bitstamp.url();
+        config.validate().expect("ok");
+
+        config
+    }
+
+    /// Helper to keep sending the same book, so that clients joining at any time get it
+    fn repeat(frame: String, times: usize) -> Vec<Step> {
+        (0..times)
+            .flat_map(|_| {
+                [
+                    Step::Send(frame.clone()),
+                    Step::Sleep(Duration::from_millis(10)),
+                ]
+            })
+            .collect()
+    }
+
+    /// Helper to get the next message of the ethbtc channel as either a status or the best ask
+    async fn next(
+        chan_recv: &mut Receiver<OrderbookMessage>,
+    ) -> (Exchange, Result<String, ConnectionStatus>) {
+        let msg = timeout(Duration::from_secs(5), chan_recv.recv())
+            .await
+            .expect("timed out waiting for the feeds")
+            .unwrap();
+
+        match msg {
+            OrderbookMessage::Message { message } => {
+                (message.exchange, Ok(message.asks[0].price.to_string()))
+            }
+            OrderbookMessage::Status { exchange, status } => (exchange, Err(status)),
+        }
+    }
+
+    /// Tests that the client gets the books of both exchanges merged, from mock exchanges through
+    /// the listeners, the gRPC server and the client over loopback
+    #[tokio::test]
+    async fn test_server_and_client_end_to_end() {
+        let binance = MockExchange::binance(vec![repeat(
+            binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+            500,
+        )])
+        .await;
+        let bitstamp = MockExchange::bitstamp(
+            &["ethbtc"],
+            vec![repeat(
+                bitstamp_order_book("ethbtc", &[("0.0701", "2")], &[("0.0711", "2")]),
+                500,
+            )],
+        )
+        .await;
+
+        let mut config = server_config(&binance, &bitstamp);
+        // Grab a free port for the server
+        config.port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        config.address = "127.0.0.1".parse().unwrap();
+        let client_config = ClientConfig {
+            url: Some(format!("http://{}", config.socket_addr())),
+            count: 20,
+            output: OutputFormat::Json,
+            ..ClientConfig::default()
+        };
+        let server = tokio::spawn(async move {
+            let _ = serve(config).await;
+        });
+
+        // The server may take a moment to start listening
+        let mut writer = SummaryWriter::new(client_config.output, Vec::new());
+        let mut received = None;
+        for _ in 0..50 {
+            match timeout(
+                Duration::from_secs(5),
+                stream_summaries(&client_config, &mut writer),
+            )
+            .await
+            .expect("timed out waiting for summaries")
+            {
+                Ok(count) => {
+                    received = Some(count);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        assert_eq!(received, Some(20));
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        let last: Value = serde_json::from_str(output.lines().last().unwrap()).unwrap();
+        assert_eq!(last["symbol"], "ethbtc");
+        assert_eq!(last["spread"], "0.0009");
+        assert_eq!(last["asks"][0]["exchange"], "Binance");
+        assert_eq!(last["asks"][0]["price"], "0.0710");
+        assert_eq!(last["bids"][0]["exchange"], "Bitstamp");
+        assert_eq!(last["bids"][0]["price"], "0.0701");
+
+        assert_eq!(
+            binance.requests(),
+            vec!["/stream?streams=ethbtc@depth20@100ms"]
+        );
+        let subscribe: Value = serde_json::from_str(&bitstamp.received()[0]).unwrap();
+        assert_eq!(subscribe["event"], "bts:subscribe");
+        assert_eq!(subscribe["data"]["channel"], "order_book_ethbtc");
+
+        server.abort();
+    }
+
+    /// Tests that malformed frames are dropped without affecting the feed and that both a closed
+    /// and a dropped connection are reconnected and subscribed again
+    #[tokio::test]
+    async fn test_feeds_survive_malformed_frames_and_disconnects() {
+        let binance = MockExchange::binance(vec![
+            vec![
+                Step::Sleep(Duration::from_millis(100)),
+                Step::Send("not json".to_string()),
+                Step::Send(binance_depth(
+                    "ethbtc",
+                    &[("0.0700", "1")],
+                    &[("0.0710", "1")],
+                )),
+                Step::Close,
+            ],
+            vec![Step::Send(binance_depth(
+                "ethbtc",
+                &[("0.0700", "1")],
+                &[("0.0712", "1")],
+            ))],
+        ])
+        .await;
+        let bitstamp = MockExchange::bitstamp(&["ethbtc"], Vec::new()).await;
+
+        let mut config = server_config(&binance, &bitstamp);
+        config.exchanges = vec![Exchange::Binance];
+        let connectors = vec![connector_for(Exchange::Binance, &config)];
+        let channels = StreamService::new(
+            &config,
+            connectors,
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        )
+        .run()
+        .await
+        .unwrap();
+        let mut chan_recv = channels["ethbtc"].subscribe();
+
+        let mut received = Vec::new();
+        for _ in 0..5 {
+            received.push(next(&mut chan_recv).await);
+        }
+        assert_eq!(
+            received,
+            vec![
+                (Exchange::Binance, Err(ConnectionStatus::Connected)),
+                (Exchange::Binance, Ok("0.0710".to_string())),
+                (Exchange::Binance, Err(ConnectionStatus::Disconnected)),
+                (Exchange::Binance, Err(ConnectionStatus::Connected)),
+                (Exchange::Binance, Ok("0.0712".to_string())),
+            ]
+        );
+        assert_eq!(binance.requests().len(), 2);
+
+        // Same over Bitstamp, whose connection goes away without a close frame
+        let bitstamp = MockExchange::bitstamp(
+            &["ethbtc"],
+            vec![
+                vec![
+                    Step::Sleep(Duration::from_millis(100)),
+                    Step::Send(r#"{"event": "data", "channel": "order_book_ethbtc"}"#.to_string()),
+                    Step::Send(bitstamp_order_book(
+                        "ethbtc",
+                        &[("0.0701", "2")],
+                        &[("0.0711", "2")],
+                    )),
+                    Step::Drop,
+                ],
+                vec![Step::Send(bitstamp_order_book(
+                    "ethbtc",
+                    &[("0.0701", "2")],
+                    &[("0.0713", "2")],
+                ))],
+            ],
+        )
+        .await;
+        let mut config = server_config(&binance, &bitstamp);
+        config.exchanges = vec![Exchange::Bitstamp];
+        let connectors = vec![connector_for(Exchange::Bitstamp, &config)];
+        let channels = StreamService::new(
+            &config,
+            connectors,
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        )
+        .run()
+        .await
+        .unwrap();
+        let mut chan_recv = channels["ethbtc"].subscribe();
+
+        let mut received = Vec::new();
+        for _ in 0..5 {
+            received.push(next(&mut chan_recv).await);
+        }
+        assert_eq!(
+            received,
+            vec![
+                (Exchange::Bitstamp, Err(ConnectionStatus::Connected)),
+                (Exchange::Bitstamp, Ok("0.0711".to_string())),
+                (Exchange::Bitstamp, Err(ConnectionStatus::Disconnected)),
+                (Exchange::Bitstamp, Err(ConnectionStatus::Connected)),
+                (Exchange::Bitstamp, Ok("0.0713".to_string())),
+            ]
+        );
+        let subscriptions = bitstamp
+            .received()
+            .iter()
+            .filter(|frame| frame.contains("bts:subscribe"))
+            .count();
+        assert_eq!(subscriptions, 2);
+    }
+
+    /// Tests that once told to stop the server ends the streams of its clients cleanly,
+    /// unsubscribes from the exchanges, completes the recordings and returns
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let binance = MockExchange::binance(vec![repeat(
+            binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+            500,
+        )])
+        .await;
+        // Done with its steps, Bitstamp reads what the server sends until it hangs up
+        let bitstamp = MockExchange::bitstamp(
+            &["ethbtc"],
+            vec![vec![Step::Send(bitstamp_order_book(
+                "ethbtc",
+                &[("0.0701", "2")],
+                &[("0.0711", "2")],
+            ))]],
+        )
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = server_config(&binance, &bitstamp);
+        config.port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        config.address = "127.0.0.1".parse().unwrap();
+        config.recorder.dir = Some(dir.path().to_path_buf());
+        let client_config = ClientConfig {
+            url: Some(format!("http://{}", config.socket_addr())),
+            ..ClientConfig::default()
+        };
+        let (stop, stopped) = oneshot::channel::<()>();
+        // Errors aren't Send, so only their description makes it out of the task
+        let server = tokio::spawn(async move {
+            serve_with_shutdown(config, async {
+                let _ = stopped.await;
+            })
+            .await
+            .map_err(|error| error.to_string())
+        });
+
+        // The server may take a moment to start listening
+        let mut stream = None;
+        for _ in 0..50 {
+            match subscribe(&client_config).await {
+                Ok(ok) => {
+                    stream = Some(ok);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        let mut stream = stream.expect("server");
+        timeout(Duration::from_secs(5), stream.message())
+            .await
+            .expect("timed out waiting for summaries")
+            .unwrap()
+            .expect("summary");
+
+        stop.send(()).unwrap();
+
+        // Whatever was queued for the client comes before a clean end of the stream
+        let ended = timeout(Duration::from_secs(5), async {
+            while let Some(summary) = stream.message().await.transpose() {
+                summary.expect("no error status");
+            }
+        })
+        .await;
+        assert!(ended.is_ok(), "timed out waiting for the stream to end");
+        // Well within the shutdown timeout, so everything wrapped up
+        timeout(Duration::from_secs(2), server)
+            .await
+            .expect("timed out waiting for the server to stop")
+            .unwrap()
+            .unwrap();
+
+        let unsubscribe = bitstamp
+            .received()
+            .iter()
+            .filter_map(|frame| serde_json::from_str::<Value>(frame).ok())
+            .find(|frame| frame["event"] == "bts:unsubscribe")
+            .expect("unsubscribed from Bitstamp");
+        assert_eq!(unsubscribe["data"]["channel"], "order_book_ethbtc");
+
+        // Every recording is a complete gzip stream
+        let recordings: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(recordings.len(), 2);
+        for path in recordings {
+            let mut frames = String::new();
+            MultiGzDecoder::new(File::open(&path).unwrap())
+                .read_to_string(&mut frames)
+                .unwrap();
+            assert!(frames.ends_with('\n'), "{}", path.display());
+        }
+    }
+}
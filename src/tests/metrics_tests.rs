@@ -0,0 +1,228 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::{sync::broadcast, time::timeout};
+    use tokio_stream::StreamExt;
+    use tonic::Request;
+    use tonic_health::server::health_reporter;
+
+    use crate::models::{
+        config::ServerConfig, connectors::BitstampConnector, mapper::Exchange,
+        messages::SymbolChannels, stream::listen, subscribers::Subscribers,
+    };
+    use crate::server::{
+        grpc_server::{
+            orderbook::{orderbook_aggregator_server::OrderbookAggregator, BookSummaryRequest},
+            OrderbookService,
+        },
+        health::report_health,
+        metrics::{serve_metrics, Metrics},
+        shutdown::Shutdown,
+    };
+    use crate::tests::fixtures::OrdersBuilder;
+    use crate::tests::mock_exchange::{bitstamp_order_book, MockExchange, Step};
+
+    /// Helper to read the value of the sample named `sample`, labels included, out of `metrics`
+    fn sample(metrics: &str, sample: &str) -> Option<f64> {
+        metrics.lines().find_map(|line| {
+            line.strip_prefix(sample)
+                .and_then(|value| value.strip_prefix(' '))
+                .map(|value| value.parse().unwrap())
+        })
+    }
+
+    /// Tests that listeners count the frames they receive, the ones they can't parse, the books
+    /// they quarantine and the orderbooks nobody listened to
+    #[tokio::test]
+    async fn test_listen_metrics() {
+        let book = bitstamp_order_book("ethbtc", &[("0.07", "1")], &[("0.071", "1")]);
+        let bitstamp = MockExchange::bitstamp(
+            &["ethbtc"],
+            vec![vec![
+                Step::Send(book.clone()),
+                Step::Send("not json".to_string()),
+                Step::Send(book),
+                Step::Send(bitstamp_order_book("ethbtc", &[], &[])),
+                Step::Close,
+            ]],
+        )
+        .await;
+
+        // Nobody is subscribed to the channel
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let metrics = Metrics::new();
+        listen(
+            &mut BitstampConnector::new(bitstamp.url()),
+            &["ethbtc".to_string()],
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &metrics,
+            &Shutdown::default(),
+        )
+        .await
+        .unwrap();
+
+        let encoded = metrics.encode().unwrap();
+        // The subscription acknowledgement counts as a frame too
+        assert_eq!(
+            sample(
+                &encoded,
+                r#"orderbook_messages_received_total{exchange="Bitstamp"}"#
+            ),
+            Some(5.0)
+        );
+        assert_eq!(
+            sample(
+                &encoded,
+                r#"orderbook_errors_total{exchange="Bitstamp",kind="parse"}"#
+            ),
+            Some(1.0)
+        );
+        assert_eq!(
+            sample(
+                &encoded,
+                r#"orderbook_errors_total{exchange="Bitstamp",kind="empty_book"}"#
+            ),
+            Some(1.0)
+        );
+        // Empty books are quarantined rather than broadcast
+        assert_eq!(
+            sample(
+                &encoded,
+                r#"orderbook_broadcast_dropped_total{exchange="Bitstamp"}"#
+            ),
+            Some(2.0)
+        );
+    }
+
+    /// Tests that orderbooks count as dropped while no client is subscribed, even though the health
+    /// reporter keeps a receiver of its own on the channel
+    #[tokio::test]
+    async fn test_listen_dropped_with_health() {
+        let book = bitstamp_order_book("ethbtc", &[("0.07", "1")], &[("0.071", "1")]);
+        let bitstamp =
+            MockExchange::bitstamp(&["ethbtc"], vec![vec![Step::Send(book), Step::Close]]).await;
+
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let metrics = Arc::new(Metrics::new());
+        let (reporter, _) = health_reporter();
+        report_health(
+            &channels,
+            reporter,
+            Duration::from_secs(1),
+            metrics.clone(),
+            Shutdown::default(),
+        )
+        .await;
+
+        listen(
+            &mut BitstampConnector::new(bitstamp.url()),
+            &["ethbtc".to_string()],
+            &channels,
+            &Subscribers::default(),
+            100,
+            None,
+            &metrics,
+            &Shutdown::default(),
+        )
+        .await
+        .unwrap();
+
+        let encoded = metrics.encode().unwrap();
+        assert_eq!(
+            sample(
+                &encoded,
+                r#"orderbook_broadcast_dropped_total{exchange="Bitstamp"}"#
+            ),
+            Some(1.0)
+        );
+    }
+
+    /// Tests that connected clients, their queues and the latency of their summaries are served
+    /// over HTTP, and that clients are counted out once they hang up
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let addr: SocketAddr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let metrics = Arc::new(Metrics::new());
+        tokio::spawn(serve_metrics(addr, metrics.clone()));
+
+        let (chan_send, _) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send.clone())]);
+        let service = OrderbookService::new(
+            channels,
+            &ServerConfig::default(),
+            metrics.clone(),
+            Shutdown::default(),
+        );
+        let mut stream = service
+            .book_summary(Request::new(BookSummaryRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for summary")
+            .unwrap()
+            .unwrap();
+
+        // The server may take a moment to start listening
+        let url = format!("http://{}/metrics", addr);
+        let mut response = None;
+        for _ in 0..50 {
+            match reqwest::get(&url).await {
+                Ok(ok) => {
+                    response = Some(ok);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        let body = response.expect("metrics server").text().await.unwrap();
+        assert_eq!(sample(&body, "orderbook_grpc_clients"), Some(1.0));
+        assert!(sample(&body, r#"orderbook_client_queue_depth{client="0"}"#).is_some());
+        assert_eq!(
+            sample(
+                &body,
+                r#"orderbook_latency_seconds_count{exchange="Binance"}"#
+            ),
+            Some(1.0)
+        );
+
+        let not_found = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(not_found.status(), reqwest::StatusCode::NOT_FOUND);
+
+        // The client is only known to be gone once a Summary can't be sent
+        drop(stream);
+        chan_send
+            .send(OrdersBuilder::new(Exchange::Binance).message())
+            .unwrap();
+        let mut clients = None;
+        for _ in 0..50 {
+            clients = sample(&metrics.encode().unwrap(), "orderbook_grpc_clients");
+            if clients == Some(0.0) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(clients, Some(0.0));
+        assert_eq!(
+            sample(
+                &metrics.encode().unwrap(),
+                r#"orderbook_client_queue_depth{client="0"}"#
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+    time::sleep,
+};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{Request, Response},
+        Message,
+    },
+    WebSocketStream,
+};
+
+/// What a mock exchange does next over a connection
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Sends a text frame, canned or malformed
+    Send(String),
+    /// Waits before the next step
+    Sleep(Duration),
+    /// Closes the connection with a close frame
+    Close,
+    /// Drops the TCP connection without a close frame, like a network failure
+    Drop,
+}
+
+/// Frames sent by the clients of a mock, shared with the tasks serving them
+type Received = Arc<Mutex<Vec<String>>>;
+
+/// Exchange Web Socket server on loopback, scripted with the steps of each connection it accepts
+pub struct MockExchange {
+    url: String,
+    /// Path and query each connection was opened with, e.g. "/stream?streams=ethbtc@depth20@100ms"
+    requests: Received,
+    /// Text frames sent by the clients, in the order they were received
+    received: Received,
+    handle: JoinHandle<()>,
+}
+
+impl MockExchange {
+    /// Mock of Binance's combined partial depth streams, which take their symbols from the URL
+    pub async fn binance(connections: Vec<Vec<Step>>) -> Self {
+        MockExchange::start(0, connections).await
+    }
+
+    /// Mock of Bitstamp's order_book channels. Every connection acknowledges the subscriptions to
+    /// `symbols` before running its steps
+    pub async fn bitstamp(symbols: &[&str], connections: Vec<Vec<Step>>) -> Self {
+        MockExchange::start(symbols.len(), connections).await
+    }
+
+    /// Web Socket URL of the mock, e.g. "ws://127.0.0.1:1234"
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Path and query each connection was opened with
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Text frames sent by the clients, in the order they were received
+    pub fn received(&self) -> Vec<String> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Helper to accept the scripted connections one after the other
+    async fn start(subscriptions: usize, connections: Vec<Vec<Step>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let requests = Received::default();
+        let received = Received::default();
+
+        let (task_requests, task_received) = (requests.clone(), received.clone());
+        let handle = tokio::spawn(async move {
+            let mut sessions = Vec::new();
+            for steps in connections {
+                let (tcp_stream, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(_) => return,
+                };
+                let requests = task_requests.clone();
+                // The error type of the handshake callback is set by tungstenite
+                #[allow(clippy::result_large_err)]
+                let ws_stream = accept_hdr_async(tcp_stream, |request: &Request, response| {
+                    requests.lock().unwrap().push(request.uri().to_string());
+                    Ok::<Response, _>(response)
+                })
+                .await
+                .unwrap();
+
+                // Keep serving previous connections while the next one is awaited
+                sessions.push(tokio::spawn(MockExchange::session(
+                    ws_stream,
+                    subscriptions,
+                    steps,
+                    task_received.clone(),
+                )));
+            }
+
+            drop(listener);
+            for session in sessions {
+                let _ = session.await;
+            }
+        });
+
+        MockExchange {
+            url,
+            requests,
+            received,
+            handle,
+        }
+    }
+
+    /// Helper to run the steps of a single connection
+    async fn session(
+        mut ws_stream: WebSocketStream<TcpStream>,
+        subscriptions: usize,
+        steps: Vec<Step>,
+        received: Received,
+    ) {
+        for _ in 0..subscriptions {
+            let subscribe = match MockExchange::read(&mut ws_stream, &received).await {
+                Some(subscribe) => subscribe,
+                None => return,
+            };
+            let subscribe: Value = serde_json::from_str(&subscribe).unwrap();
+            let ack = json!({
+                "event": "bts:subscription_succeeded",
+                "channel": subscribe["data"]["channel"],
+                "data": {}
+            });
+            if ws_stream
+                .send(Message::Text(ack.to_string()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        for step in steps {
+            match step {
+                Step::Send(frame) => {
+                    if ws_stream.send(Message::Text(frame)).await.is_err() {
+                        return;
+                    }
+                }
+                Step::Sleep(duration) => sleep(duration).await,
+                Step::Close => {
+                    let _ = ws_stream.close(None).await;
+                    return;
+                }
+                Step::Drop => return,
+            }
+        }
+
+        while MockExchange::read(&mut ws_stream, &received)
+            .await
+            .is_some()
+        {}
+    }
+
+    /// Helper to read the next text frame of a client. None once the client is gone
+    async fn read(
+        ws_stream: &mut WebSocketStream<TcpStream>,
+        received: &Received,
+    ) -> Option<String> {
+        loop {
+            match ws_stream.next().await? {
+                Ok(Message::Text(frame)) => {
+                    received.lock().unwrap().push(frame.clone());
+                    return Some(frame);
+                }
+                Ok(Message::Close(_)) | Err(_) => return None,
+                Ok(_) => continue,
+            }
+        }
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Binance partial depth frame of `symbol`, with levels given as (price, quantity)
+pub fn binance_depth(symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    json!({
+        "stream": format!("{}@depth20@100ms", symbol),
+        "data": {"lastUpdateId": 1, "bids": bids, "asks": asks}
+    })
+    .to_string()
+}
+
+/// Bitstamp order_book frame of `symbol`, with levels given as (price, quantity)
+pub fn bitstamp_order_book(symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    json!({
+        "data": {
+            "timestamp": "1666000000",
+            "microtimestamp": "1666000000000000",
+            "bids": bids,
+            "asks": asks
+        },
+        "channel": format!("order_book_{}", symbol),
+        "event": "data"
+    })
+    .to_string()
+}
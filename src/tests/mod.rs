@@ -1,2 +1,38 @@
 #[cfg(test)]
+mod arbitrage_tests;
+#[cfg(test)]
+mod client_tests;
+#[cfg(test)]
+mod config_tests;
+#[cfg(test)]
+mod connector_tests;
+#[cfg(test)]
+mod errors_tests;
+#[cfg(test)]
+mod fixtures;
+#[cfg(test)]
+mod health_tests;
+#[cfg(test)]
+mod integration_tests;
+#[cfg(test)]
+mod metrics_tests;
+#[cfg(test)]
+mod mock_exchange;
+#[cfg(test)]
+mod recorder_tests;
+#[cfg(test)]
+mod replay_tests;
+#[cfg(test)]
+mod server_tests;
+#[cfg(test)]
 mod stream_tests;
+#[cfg(test)]
+mod stubs;
+#[cfg(test)]
+mod supervisor_tests;
+#[cfg(test)]
+mod tls_tests;
+#[cfg(test)]
+mod tui_tests;
+#[cfg(test)]
+mod validation_tests;
@@ -0,0 +1,240 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{BufRead, BufReader};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use flate2::read::MultiGzDecoder;
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::json;
+    use tokio::{net::TcpListener, sync::broadcast};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use crate::models::{
+        config::RecorderConfig,
+        connectors::BitstampConnector,
+        mapper::Exchange,
+        messages::SymbolChannels,
+        recorder::{RecordedFrame, Recorder, RecorderHandle},
+        stream::listen,
+        subscribers::Subscribers,
+    };
+    use crate::server::{metrics::Metrics, shutdown::Shutdown};
+
+    /// Helper to build a recorder writing to `dir`
+    fn recorder(dir: &Path, max_file_bytes: u64) -> Recorder {
+        let config = RecorderConfig {
+            dir: Some(dir.to_path_buf()),
+            max_file_bytes,
+            ..RecorderConfig::default()
+        };
+
+        Recorder::from_config(&config, Exchange::Bitstamp).unwrap()
+    }
+
+    /// Helper to list the recordings in `dir` in the order they were started
+    fn recordings(dir: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        // Files share the millisecond they were started in tests, so order by sequence
+        paths.sort_by_key(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let sequence = name.trim_end_matches(".jsonl.gz").rsplit('-').next();
+            sequence.unwrap().parse::<u64>().unwrap()
+        });
+
+        paths
+    }
+
+    /// Helper to read back every frame of a recording
+    fn read(path: &Path) -> Vec<RecordedFrame> {
+        BufReader::new(MultiGzDecoder::new(File::open(path).unwrap()))
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect()
+    }
+
+    /// Tests that frames are written as gzipped JSON lines that rotate once a file is large enough
+    #[test]
+    fn test_recorder_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = recorder(dir.path(), 200);
+
+        let received_at = UNIX_EPOCH + Duration::from_micros(1_666_000_000_123_456);
+        for i in 0..5 {
+            let frame = format!(r#"{{"event":"data","n":{}}}"#, i);
+            recorder
+                .record(received_at, Some("ethbtc"), &frame)
+                .unwrap();
+        }
+        recorder.record(received_at, None, "pong").unwrap();
+        drop(recorder);
+
+        let paths = recordings(dir.path());
+        assert!(paths.len() > 1, "expected a rotation, got {:?}", paths);
+        assert!(paths.iter().all(|path| path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("bitstamp-")));
+
+        let frames: Vec<RecordedFrame> = paths.iter().flat_map(|path| read(path)).collect();
+        assert_eq!(frames.len(), 6);
+        assert_eq!(
+            frames[0],
+            RecordedFrame {
+                received_at_us: 1_666_000_000_123_456,
+                exchange: Exchange::Bitstamp,
+                symbol: Some("ethbtc".to_string()),
+                frame: r#"{"event":"data","n":0}"#.to_string(),
+            }
+        );
+        assert_eq!(frames[4].frame, r#"{"event":"data","n":4}"#);
+        assert_eq!(frames[5].symbol, None);
+        assert_eq!(frames[5].frame, "pong");
+    }
+
+    /// Tests that flushed frames can be read before the recording is completed, e.g. after a crash
+    #[test]
+    fn test_recorder_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = recorder(dir.path(), u64::MAX);
+
+        recorder
+            .record(SystemTime::now(), Some("ethbtc"), "{}")
+            .unwrap();
+        recorder.flush().unwrap();
+
+        let path = &recordings(dir.path())[0];
+        let mut lines = BufReader::new(MultiGzDecoder::new(File::open(path).unwrap())).lines();
+        let frame: RecordedFrame = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(frame.frame, "{}");
+    }
+
+    /// Tests that listen records every frame, including the ones without an orderbook or that fail to parse
+    #[tokio::test]
+    async fn test_listen_records_frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let ack = json!({
+            "event": "bts:subscription_succeeded",
+            "channel": "order_book_ethbtc",
+            "data": {}
+        })
+        .to_string();
+        let book = json!({
+            "data": {
+                "timestamp": "1666000000",
+                "microtimestamp": "1666000000000000",
+                "bids": [["0.07", "1.0"]],
+                "asks": [["0.071", "2.0"]]
+            },
+            "channel": "order_book_ethbtc",
+            "event": "data"
+        })
+        .to_string();
+        let frames = vec![ack, "not json".to_string(), book];
+
+        let sent = frames.clone();
+        let server = tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+            ws_stream.next().await.unwrap().unwrap();
+
+            for frame in sent {
+                ws_stream.send(Message::Text(frame)).await.unwrap();
+            }
+            ws_stream.close(None).await.unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = RecorderHandle::spawn(recorder(dir.path(), u64::MAX));
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let mut connector = BitstampConnector::new(api_url);
+
+        listen(
+            &mut connector,
+            &["ethbtc".to_string()],
+            &channels,
+            &Subscribers::default(),
+            100,
+            Some(&recorder),
+            &Metrics::new(),
+            &Shutdown::default(),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        recorder.finish().await.unwrap();
+
+        let recorded = read(&recordings(dir.path())[0]);
+        let recorded_frames: Vec<String> =
+            recorded.iter().map(|frame| frame.frame.clone()).collect();
+        assert_eq!(recorded_frames, frames);
+        let symbols: Vec<Option<&str>> = recorded
+            .iter()
+            .map(|frame| frame.symbol.as_deref())
+            .collect();
+        assert_eq!(symbols, vec![None, None, Some("ethbtc")]);
+        assert!(recorded
+            .windows(2)
+            .all(|pair| pair[0].received_at_us <= pair[1].received_at_us));
+    }
+
+    /// Tests that listen flushes the recording while the feed is quiet, so that the last frames
+    /// can be read without waiting for the next one
+    #[tokio::test]
+    async fn test_listen_flushes_quiet_feed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+            ws_stream.next().await.unwrap().unwrap();
+
+            ws_stream
+                .send(Message::Text("not json".to_string()))
+                .await
+                .unwrap();
+            // Keep the connection open and quiet until the client hangs up
+            while ws_stream.next().await.is_some() {}
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = RecorderConfig {
+            dir: Some(dir.path().to_path_buf()),
+            flush_secs: 1,
+            ..RecorderConfig::default()
+        };
+        let recorder =
+            RecorderHandle::spawn(Recorder::from_config(&config, Exchange::Bitstamp).unwrap());
+        let listener = tokio::spawn(async move {
+            let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+            let _ = listen(
+                &mut BitstampConnector::new(api_url),
+                &["ethbtc".to_string()],
+                &channels,
+                &Subscribers::default(),
+                100,
+                Some(&recorder),
+                &Metrics::new(),
+                &Shutdown::default(),
+            )
+            .await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let path = &recordings(dir.path())[0];
+        let mut lines = BufReader::new(MultiGzDecoder::new(File::open(path).unwrap())).lines();
+        let frame: RecordedFrame = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(frame.frame, "not json");
+
+        listener.abort();
+    }
+}
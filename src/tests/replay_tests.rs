@@ -0,0 +1,380 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use serde_json::json;
+    use tokio::{
+        sync::broadcast,
+        time::{timeout, Instant},
+    };
+    use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+    use tonic::Request;
+
+    use crate::client::grpc_client::subscribe;
+    use crate::models::{
+        config::{ClientConfig, RecorderConfig, ReplayConfig, ServerConfig},
+        connectors::connector_for,
+        mapper::Exchange,
+        messages::{OrderbookMessage, SymbolChannels},
+        recorder::Recorder,
+        replay::{self, ReplaySpeed},
+        stream_service::{LagPolicy, StreamService},
+        subscribers::Subscribers,
+    };
+    use crate::server::grpc_server::{
+        orderbook::{
+            orderbook_aggregator_server::OrderbookAggregator, BookSummaryRequest, Summary,
+        },
+        serve_with_shutdown, OrderbookService, ResultSummary,
+    };
+    use crate::server::{metrics::Metrics, shutdown::Shutdown};
+    use crate::tests::mock_exchange::{binance_depth, bitstamp_order_book};
+
+    /// Microseconds since the UNIX epoch at which the recordings start
+    const STARTED_AT_US: u64 = 1_666_000_000_000_000;
+
+    /// Helper to record `frames` of `exchange`, each received some milliseconds after the start,
+    /// and get the recording back
+    fn record(dir: &Path, exchange: Exchange, frames: &[(u64, String)]) -> PathBuf {
+        let exchange_dir = dir.join(exchange.to_string());
+        let config = RecorderConfig {
+            dir: Some(exchange_dir.clone()),
+            ..RecorderConfig::default()
+        };
+        let mut recorder = Recorder::from_config(&config, exchange).unwrap();
+        for (offset_ms, frame) in frames {
+            let received_at = UNIX_EPOCH + Duration::from_micros(STARTED_AT_US + offset_ms * 1000);
+            recorder.record(received_at, None, frame).unwrap();
+        }
+        drop(recorder);
+
+        fs::read_dir(exchange_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path()
+    }
+
+    /// Helper to replay `files` through the server and subscribe to ethbtc
+    async fn replay(files: Vec<PathBuf>, speed: ReplaySpeed) -> ReceiverStream<ResultSummary> {
+        let config = ServerConfig {
+            symbols: vec!["ethbtc".to_string()],
+            replay: ReplayConfig {
+                files,
+                speed,
+                wait_for_clients: true,
+            },
+            ..ServerConfig::default()
+        };
+        config.validate().expect("ok");
+
+        let connectors = config
+            .exchanges
+            .iter()
+            .map(|&exchange| connector_for(exchange, &config))
+            .collect();
+        let metrics = Arc::new(Metrics::new());
+        let stream_service =
+            StreamService::new(&config, connectors, metrics.clone(), Shutdown::default());
+        let subscribers = stream_service.subscribers();
+        let channels = stream_service.run().await.unwrap();
+        let service = OrderbookService::new(channels, &config, metrics, Shutdown::default())
+            .with_subscribers(subscribers);
+
+        service
+            .book_summary(Request::new(BookSummaryRequest {
+                symbols: vec!["ethbtc".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    /// Helper to get the next `count` summaries
+    async fn summaries(stream: &mut ReceiverStream<ResultSummary>, count: usize) -> Vec<Summary> {
+        let mut summaries = Vec::new();
+        for _ in 0..count {
+            let summary = timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("timed out waiting for summary")
+                .unwrap()
+                .unwrap();
+            summaries.push(summary);
+        }
+
+        summaries
+    }
+
+    /// Tests the replay speeds accepted in configuration files and flags
+    #[test]
+    fn test_replay_speed() {
+        assert_eq!("realtime".parse(), Ok(ReplaySpeed::RealTime));
+        assert_eq!("MAX".parse(), Ok(ReplaySpeed::Max));
+        assert_eq!("10x".parse(), Ok(ReplaySpeed::Accelerated(10)));
+        assert!("0x".parse::<ReplaySpeed>().is_err());
+        assert!("fast".parse::<ReplaySpeed>().is_err());
+        assert_eq!(ReplaySpeed::Accelerated(10).to_string(), "10x");
+    }
+
+    /// Tests that the recordings of every exchange are merged in the order they were received and
+    /// go through the parsers and aggregation of the live server, frame by frame
+    #[tokio::test]
+    async fn test_replay_end_to_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let ack = json!({"event": "bts:subscription_succeeded", "channel": "order_book_ethbtc", "data": {}});
+        let files = vec![
+            record(
+                dir.path(),
+                Exchange::Binance,
+                &[
+                    (
+                        0,
+                        binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+                    ),
+                    (
+                        20,
+                        binance_depth("ethbtc", &[("0.0702", "1")], &[("0.0712", "1")]),
+                    ),
+                ],
+            ),
+            record(
+                dir.path(),
+                Exchange::Bitstamp,
+                &[
+                    (5, ack.to_string()),
+                    (
+                        10,
+                        bitstamp_order_book("ethbtc", &[("0.0701", "1")], &[("0.0711", "1")]),
+                    ),
+                    (15, "not json".to_string()),
+                    (
+                        30,
+                        bitstamp_order_book("ethbtc", &[("0.0699", "1")], &[("0.0713", "1")]),
+                    ),
+                ],
+            ),
+        ];
+
+        let mut stream = replay(files, ReplaySpeed::Max).await;
+
+        // Binance connects, Binance book, Bitstamp connects, then every book in order
+        let summaries = summaries(&mut stream, 6).await;
+        let best = |summary: &Summary| {
+            (
+                summary.asks[0].exchange.clone(),
+                summary.asks[0].price_decimal.clone(),
+                summary.bids[0].exchange.clone(),
+                summary.bids[0].price_decimal.clone(),
+            )
+        };
+        assert!(summaries[0].asks.is_empty());
+        assert_eq!(summaries[1].asks.len(), 1);
+        assert_eq!(summaries[2].exchanges.len(), 2);
+        assert_eq!(
+            best(&summaries[3]),
+            (
+                "Binance".to_string(),
+                "0.0710".to_string(),
+                "Bitstamp".to_string(),
+                "0.0701".to_string()
+            )
+        );
+        assert_eq!(
+            best(&summaries[4]),
+            (
+                "Bitstamp".to_string(),
+                "0.0711".to_string(),
+                "Binance".to_string(),
+                "0.0702".to_string()
+            )
+        );
+        assert_eq!(
+            best(&summaries[5]),
+            (
+                "Binance".to_string(),
+                "0.0712".to_string(),
+                "Binance".to_string(),
+                "0.0702".to_string()
+            )
+        );
+        assert_eq!(summaries[5].spread_decimal, "0.0010");
+
+        // Nothing is left to replay
+        let next = timeout(Duration::from_millis(100), stream.next()).await;
+        assert!(!matches!(next, Ok(Some(_))));
+    }
+
+    /// Tests that a recording cut short by a crash, with a truncated gzip tail, is replayed up to
+    /// its last flush
+    #[tokio::test]
+    async fn test_replay_truncated_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RecorderConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..RecorderConfig::default()
+        };
+        // Never completed, like the recorder of a crashed server
+        let mut recorder = Recorder::from_config(&config, Exchange::Binance).unwrap();
+        for (offset_ms, frame) in [
+            (
+                0,
+                binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+            ),
+            (
+                10,
+                binance_depth("ethbtc", &[("0.0702", "1")], &[("0.0712", "1")]),
+            ),
+        ] {
+            let received_at = UNIX_EPOCH + Duration::from_micros(STARTED_AT_US + offset_ms * 1000);
+            recorder.record(received_at, None, &frame).unwrap();
+        }
+        recorder.flush().unwrap();
+        let files = vec![fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path()];
+        let server_config = ServerConfig {
+            symbols: vec!["ethbtc".to_string()],
+            exchanges: vec![Exchange::Binance],
+            ..ServerConfig::default()
+        };
+        let (chan_send, mut chan_recv) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send)]);
+
+        let replayed = replay::replay(
+            &files,
+            vec![connector_for(Exchange::Binance, &server_config)],
+            &server_config.symbols,
+            &channels,
+            ReplaySpeed::Max,
+            &Subscribers::default(),
+            false,
+            &Metrics::new(),
+        )
+        .await;
+
+        assert!(replayed.is_ok(), "{:?}", replayed);
+        let mut asks = Vec::new();
+        while let Ok(message) = chan_recv.try_recv() {
+            if let OrderbookMessage::Message { message } = message {
+                asks.push(message.asks[0].price.to_string());
+            }
+        }
+        assert_eq!(asks, ["0.0710", "0.0712"]);
+        drop(recorder);
+    }
+
+    /// Tests that accelerated replays keep the time between frames, divided by the factor
+    #[tokio::test]
+    async fn test_replay_accelerated() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![record(
+            dir.path(),
+            Exchange::Binance,
+            &[
+                (
+                    0,
+                    binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+                ),
+                (
+                    2000,
+                    binance_depth("ethbtc", &[("0.0702", "1")], &[("0.0712", "1")]),
+                ),
+            ],
+        )];
+
+        let mut stream = replay(files, ReplaySpeed::Accelerated(10)).await;
+
+        summaries(&mut stream, 2).await;
+        let first = Instant::now();
+        summaries(&mut stream, 1).await;
+        let elapsed = first.elapsed();
+        assert!(elapsed >= Duration::from_millis(150), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+    }
+
+    /// Tests that a replay held for clients by the whole server, health checking and snapshots
+    /// included, only starts once a client subscribes
+    #[tokio::test]
+    async fn test_replay_waits_for_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![record(
+            dir.path(),
+            Exchange::Binance,
+            &[
+                (
+                    0,
+                    binance_depth("ethbtc", &[("0.0700", "1")], &[("0.0710", "1")]),
+                ),
+                (
+                    10,
+                    binance_depth("ethbtc", &[("0.0702", "1")], &[("0.0712", "1")]),
+                ),
+            ],
+        )];
+
+        let config = ServerConfig {
+            address: "127.0.0.1".parse().unwrap(),
+            port: TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port(),
+            symbols: vec!["ethbtc".to_string()],
+            exchanges: vec![Exchange::Binance],
+            lag_policy: LagPolicy::Snapshot,
+            replay: ReplayConfig {
+                files,
+                speed: ReplaySpeed::Max,
+                wait_for_clients: true,
+            },
+            ..ServerConfig::default()
+        };
+        config.validate().expect("ok");
+        let client_config = ClientConfig {
+            url: Some(format!("http://{}", config.socket_addr())),
+            ..ClientConfig::default()
+        };
+        // Errors aren't Send, so only their description makes it out of the task
+        tokio::spawn(async move {
+            serve_with_shutdown(config, std::future::pending())
+                .await
+                .map_err(|error| error.to_string())
+        });
+
+        // The server may take a moment to start listening, and the replay is given time to start
+        // if it doesn't wait
+        let mut stream = None;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if let Ok(ok) = subscribe(&client_config).await {
+                stream = Some(ok);
+                break;
+            }
+        }
+        let mut stream = stream.expect("server");
+
+        // Binance connects, then both books in order
+        let mut asks = Vec::new();
+        for _ in 0..3 {
+            let summary = timeout(Duration::from_secs(5), stream.message())
+                .await
+                .expect("timed out waiting for summary")
+                .unwrap()
+                .expect("summary");
+            asks.push(summary.asks.first().map(|ask| ask.price_decimal.clone()));
+        }
+        assert_eq!(
+            asks,
+            vec![None, Some("0.0710".to_string()), Some("0.0712".to_string())]
+        );
+    }
+}
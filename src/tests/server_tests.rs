@@ -0,0 +1,519 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rust_decimal::Decimal;
+    use tokio::{sync::broadcast, time::timeout};
+    use tokio_stream::StreamExt;
+    use tonic::{Code, Request};
+
+    use crate::models::{
+        config::ServerConfig,
+        mapper::Exchange,
+        messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+        stream_service::LagPolicy,
+    };
+    use crate::server::{
+        grpc_server::{
+            orderbook::{
+                self, orderbook_aggregator_server::OrderbookAggregator, ArbitrageRequest,
+                ArbitrageState, BookSummaryRequest, Lag,
+            },
+            OrderbookService,
+        },
+        metrics::Metrics,
+        shutdown::Shutdown,
+    };
+    use crate::tests::fixtures::OrdersBuilder;
+
+    /// Helper to build a service streaming `symbols`
+    fn service(symbols: &[&str]) -> OrderbookService {
+        service_with_policy(symbols, LagPolicy::default())
+    }
+
+    /// Helper to build a service streaming `symbols` over channels of 16 messages, handling
+    /// clients that fall behind them with `lag_policy`
+    fn service_with_policy(symbols: &[&str], lag_policy: LagPolicy) -> OrderbookService {
+        let channels: SymbolChannels = symbols
+            .iter()
+            .map(|symbol| (symbol.to_string(), broadcast::channel(16).0))
+            .collect();
+        let config = ServerConfig {
+            lag_policy,
+            ..ServerConfig::default()
+        };
+
+        OrderbookService::new(
+            channels,
+            &config,
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        )
+    }
+
+    /// Helper to request the summaries of `symbols`
+    fn request(symbols: &[&str]) -> Request<BookSummaryRequest> {
+        Request::new(BookSummaryRequest {
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Tests that asking for a symbol the server doesn't stream is rejected
+    #[tokio::test]
+    async fn test_book_summary_unknown_symbol() {
+        let service = service(&["ethbtc", "btcusdt"]);
+
+        let status = service
+            .book_summary(request(&["ethbtc", "xrpbtc"]))
+            .await
+            .expect_err("unknown symbol");
+
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(status.message().contains("xrpbtc"));
+    }
+
+    /// Tests that clients only get the summaries of the symbols they asked for, tagged with the
+    /// symbol, and that symbols are normalized
+    #[tokio::test]
+    async fn test_book_summary_symbols() {
+        let service = service(&["ethbtc", "btcusdt"]);
+
+        let mut stream = service
+            .book_summary(request(&["ETH-BTC"]))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // Nobody asked for btcusdt so only ethbtc has a subscriber
+        for symbol in ["btcusdt", "ethbtc"] {
+            let _ = service.channels[symbol].send(OrderbookMessage::Status {
+                exchange: Exchange::Binance,
+                status: ConnectionStatus::Connected,
+            });
+        }
+
+        let summary = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for summary")
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.symbol, "ethbtc");
+        assert!(timeout(Duration::from_millis(100), stream.next())
+            .await
+            .is_err());
+    }
+
+    /// Tests that summaries of every symbol of a stream are numbered in the order they're sent,
+    /// stamped with the time they're sent and tagged with the orderbook that produced them
+    #[tokio::test]
+    async fn test_book_summary_sequence() {
+        let service = service(&["ethbtc", "btcusdt"]);
+
+        let mut stream = service
+            .book_summary(request(&["ethbtc", "btcusdt"]))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let _ = service.channels["btcusdt"].send(OrderbookMessage::Status {
+            exchange: Exchange::Binance,
+            status: ConnectionStatus::Connected,
+        });
+        let message = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_event(1666000000000000, 42)
+            .message();
+        let _ = service.channels["ethbtc"].send(message);
+
+        let mut summaries = Vec::new();
+        for _ in 0..2 {
+            let summary = timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("timed out waiting for summary")
+                .unwrap()
+                .unwrap();
+            summaries.push(summary);
+        }
+
+        // Either symbol may be sent first, but the stream is numbered in order without gaps
+        assert_eq!(summaries[0].sequence, 1);
+        assert_eq!(summaries[1].sequence, 2);
+        assert!(summaries[0].server_timestamp_us <= summaries[1].server_timestamp_us);
+        for summary in &summaries {
+            assert!(summary.server_timestamp_us > 0);
+            match summary.symbol.as_str() {
+                // Produced by a status change
+                "btcusdt" => assert_eq!(summary.source, None),
+                _ => assert_eq!(
+                    summary.source,
+                    Some(orderbook::Source {
+                        exchange: "Bitstamp".to_string(),
+                        event_time_us: Some(1666000000000000),
+                        update_id: Some(42),
+                    })
+                ),
+            }
+        }
+    }
+
+    /// Tests that requests with an unknown exchange or a depth we don't keep are rejected
+    #[tokio::test]
+    async fn test_book_summary_invalid_view() {
+        let service = service(&["ethbtc"]);
+
+        let status = service
+            .book_summary(Request::new(BookSummaryRequest {
+                exchanges: vec!["binance".to_string(), "ftx".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .expect_err("unknown exchange");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("ftx"));
+
+        let status = service
+            .book_summary(Request::new(BookSummaryRequest {
+                depth: 1000,
+                ..Default::default()
+            }))
+            .await
+            .expect_err("depth too large");
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    /// Tests that a client gets the depth and exchanges it asked for, and that a throttled client
+    /// gets a single summary with the latest books out of a burst of updates
+    #[tokio::test]
+    async fn test_book_summary_client_view() {
+        let service = service(&["ethbtc"]);
+
+        let mut stream = service
+            .book_summary(Request::new(BookSummaryRequest {
+                depth: 1,
+                exchanges: vec!["binance".to_string()],
+                throttle_ms: 200,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let chan_send = &service.channels["ethbtc"];
+        chan_send
+            .send(
+                OrdersBuilder::new(Exchange::Bitstamp)
+                    .with_asks(&[("0.072", "1")])
+                    .with_bids(&[("0.0695", "1")])
+                    .message(),
+            )
+            .unwrap();
+        for bid in ["0.068", "0.0685", "0.069"] {
+            chan_send
+                .send(
+                    OrdersBuilder::new(Exchange::Binance)
+                        .with_asks(&[("0.07", "1")])
+                        .with_bids(&[(bid, "1")])
+                        .message(),
+                )
+                .unwrap();
+        }
+
+        let summary = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for summary")
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.asks.len(), 1);
+        assert_eq!(summary.bids.len(), 1);
+        assert_eq!(summary.bids[0].exchange, "Binance");
+        assert_eq!(summary.bids[0].price_decimal, "0.069");
+        assert!(timeout(Duration::from_millis(300), stream.next())
+            .await
+            .is_err());
+    }
+
+    /// Tests that the Skip policy still applies the queued updates it sends no Summary for
+    #[tokio::test]
+    async fn test_book_summary_skip_applies_backlog() {
+        let service = service_with_policy(&["ethbtc"], LagPolicy::Skip);
+        let chan_send = &service.channels["ethbtc"];
+        let mut stream = service
+            .book_summary(request(&["ethbtc"]))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // The client doesn't get to run before the channel overflows, and Bitstamp is only queued
+        for i in 0..20 {
+            let message = match i {
+                18 => OrdersBuilder::new(Exchange::Bitstamp)
+                    .with_asks(&[("0.069", "1")])
+                    .with_bids(&[("0.068", "1")])
+                    .message(),
+                _ => OrdersBuilder::new(Exchange::Binance)
+                    .with_asks(&[(Decimal::new(700 + i, 4).to_string().as_str(), "1")])
+                    .with_bids(&[("0.06", "1")])
+                    .message(),
+            };
+            chan_send.send(message).unwrap();
+        }
+        assert!(timeout(Duration::from_millis(100), stream.next())
+            .await
+            .is_err());
+
+        chan_send
+            .send(
+                OrdersBuilder::new(Exchange::Binance)
+                    .with_asks(&[("0.072", "1")])
+                    .with_bids(&[("0.06", "1")])
+                    .message(),
+            )
+            .unwrap();
+        let summary = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for summary")
+            .unwrap()
+            .unwrap();
+        let asks: Vec<_> = summary
+            .asks
+            .iter()
+            .map(|level| (level.exchange.as_str(), level.price_decimal.as_str()))
+            .collect();
+        assert_eq!(asks, vec![("Bitstamp", "0.069"), ("Binance", "0.072")]);
+    }
+
+    /// Tests that a client that falls behind the broadcast channel is caught up with the latest
+    /// updates and told so, according to the lag policy of the server
+    #[tokio::test]
+    async fn test_book_summary_lag_policies() {
+        for policy in [LagPolicy::Skip, LagPolicy::Snapshot, LagPolicy::Terminate] {
+            let service = service_with_policy(&["ethbtc"], policy);
+            // A book sent before the client subscribed, which only a snapshot has
+            // Nobody but the snapshot keeper may be listening yet
+            let chan_send = &service.channels["ethbtc"];
+            let _ = chan_send.send(
+                OrdersBuilder::new(Exchange::Bitstamp)
+                    .with_asks(&[("0.069", "1")])
+                    .with_bids(&[("0.068", "1")])
+                    .message(),
+            );
+            tokio::task::yield_now().await;
+
+            let mut stream = service
+                .book_summary(request(&["ethbtc"]))
+                .await
+                .unwrap()
+                .into_inner();
+
+            // The client doesn't get to run before the channel overflows
+            for i in 0..20 {
+                let ask = Decimal::new(700 + i, 4).to_string();
+                chan_send
+                    .send(
+                        OrdersBuilder::new(Exchange::Binance)
+                            .with_asks(&[(ask.as_str(), "1")])
+                            .with_bids(&[("0.06", "1")])
+                            .message(),
+                    )
+                    .unwrap();
+            }
+            let expected_lag = |policy: orderbook::LagPolicy| Lag {
+                skipped: 20,
+                total_skipped: 20,
+                policy: policy as i32,
+            };
+
+            match policy {
+                LagPolicy::Skip => {
+                    assert!(timeout(Duration::from_millis(100), stream.next())
+                        .await
+                        .is_err());
+                    chan_send
+                        .send(
+                            OrdersBuilder::new(Exchange::Binance)
+                                .with_asks(&[("0.072", "1")])
+                                .with_bids(&[("0.06", "1")])
+                                .message(),
+                        )
+                        .unwrap();
+                    let summary = timeout(Duration::from_secs(5), stream.next())
+                        .await
+                        .expect("timed out waiting for summary")
+                        .unwrap()
+                        .unwrap();
+                    assert_eq!(summary.lag, Some(expected_lag(orderbook::LagPolicy::Skip)));
+                    assert_eq!(summary.asks.len(), 1);
+                    assert_eq!(summary.asks[0].price_decimal, "0.072");
+
+                    // Only the first Summary after falling behind carries the lag
+                    chan_send
+                        .send(
+                            OrdersBuilder::new(Exchange::Binance)
+                                .with_asks(&[("0.0721", "1")])
+                                .with_bids(&[("0.06", "1")])
+                                .message(),
+                        )
+                        .unwrap();
+                    let summary = stream.next().await.unwrap().unwrap();
+                    assert_eq!(summary.lag, None);
+                }
+                LagPolicy::Snapshot => {
+                    let summary = timeout(Duration::from_secs(5), stream.next())
+                        .await
+                        .expect("timed out waiting for summary")
+                        .unwrap()
+                        .unwrap();
+                    assert_eq!(
+                        summary.lag,
+                        Some(expected_lag(orderbook::LagPolicy::Snapshot))
+                    );
+                    // The latest book of both exchanges
+                    let asks: Vec<_> = summary
+                        .asks
+                        .iter()
+                        .map(|level| (level.exchange.as_str(), level.price_decimal.as_str()))
+                        .collect();
+                    assert_eq!(asks, vec![("Bitstamp", "0.069"), ("Binance", "0.0719")]);
+                }
+                LagPolicy::Terminate => {
+                    let status = timeout(Duration::from_secs(5), stream.next())
+                        .await
+                        .expect("timed out waiting for status")
+                        .unwrap()
+                        .expect_err("stream terminated");
+                    assert_eq!(status.code(), Code::ResourceExhausted);
+                    assert!(status.message().contains("ethbtc"));
+                    assert!(stream.next().await.is_none());
+                }
+            }
+        }
+    }
+
+    /// Tests that arbitrage clients get the opportunities of their symbols above their threshold,
+    /// and that invalid thresholds and unknown symbols are rejected
+    #[tokio::test]
+    async fn test_arbitrage_events() {
+        let service = service(&["ethbtc", "btcusd"]);
+
+        for (symbols, min_edge_bps, code) in [
+            (vec![], -1.0, Code::InvalidArgument),
+            (vec![], f64::NAN, Code::InvalidArgument),
+            (vec!["ltcbtc".to_string()], 0.0, Code::NotFound),
+        ] {
+            let request = Request::new(ArbitrageRequest {
+                symbols,
+                min_edge_bps,
+            });
+            let status = service.arbitrage_events(request).await.unwrap_err();
+            assert_eq!(status.code(), code);
+        }
+
+        let request = Request::new(ArbitrageRequest {
+            symbols: vec!["ETH-BTC".to_string()],
+            min_edge_bps: 100.0,
+        });
+        let mut stream = service
+            .arbitrage_events(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        let chan_send = &service.channels["ethbtc"];
+        for msg in [
+            OrdersBuilder::new(Exchange::Binance)
+                .with_asks(&[("0.5", "1")])
+                .with_bids(&[("0.4", "1")])
+                .message(),
+            // 20 bps isn't enough
+            OrdersBuilder::new(Exchange::Bitstamp)
+                .with_asks(&[("0.6", "1")])
+                .with_bids(&[("0.501", "1")])
+                .message(),
+            OrdersBuilder::new(Exchange::Bitstamp)
+                .with_asks(&[("0.6", "1")])
+                .with_bids(&[("0.51", "1")])
+                .message(),
+        ] {
+            chan_send.send(msg).unwrap();
+        }
+
+        let event = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for event")
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.state(), ArbitrageState::Opened);
+        assert_eq!(event.symbol, "ethbtc");
+        assert_eq!(event.buy_exchange, "Binance");
+        assert_eq!(event.sell_exchange, "Bitstamp");
+        assert_eq!(event.edge, "0.01");
+        assert_eq!(event.edge_bps, 200.0);
+
+        chan_send
+            .send(OrderbookMessage::Status {
+                exchange: Exchange::Binance,
+                status: ConnectionStatus::Disconnected,
+            })
+            .unwrap();
+        let event = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for event")
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.state(), ArbitrageState::Closed);
+    }
+
+    /// Tests that clients are sent a Summary flagging an exchange as stale as soon as it is, even
+    /// though nothing else comes through
+    #[tokio::test]
+    async fn test_book_summary_stale_exchange() {
+        let channels = SymbolChannels::from([("ethbtc".to_string(), broadcast::channel(16).0)]);
+        let config = ServerConfig {
+            stale_feed_ms: 200,
+            ..ServerConfig::default()
+        };
+        let service = OrderbookService::new(
+            channels,
+            &config,
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        );
+        let mut stream = service
+            .book_summary(request(&["ethbtc"]))
+            .await
+            .unwrap()
+            .into_inner();
+
+        service.channels["ethbtc"]
+            .send(
+                OrdersBuilder::new(Exchange::Binance)
+                    .with_asks(&[("0.5", "1")])
+                    .with_bids(&[("0.4", "1")])
+                    .message(),
+            )
+            .unwrap();
+        let summary = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for summary")
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.asks.len(), 1);
+        assert_eq!(
+            summary.exchanges[0].status(),
+            orderbook::ConnectionStatus::Connected
+        );
+
+        let summary = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for stale summary")
+            .unwrap()
+            .unwrap();
+        assert!(summary.asks.is_empty());
+        assert_eq!(
+            summary.exchanges[0].status(),
+            orderbook::ConnectionStatus::Stale
+        );
+        assert!(summary.exchanges[0].last_update_age_ms.unwrap() > 200);
+    }
+}
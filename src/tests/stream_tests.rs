@@ -1,47 +1,64 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
     use crate::models::{
+        aggregator::Aggregator,
+        consts::MAX_PAIR_EXCHANGE,
         mapper::{Exchange, OfferData},
-        messages::{OrderbookMessage, Orders},
+        messages::{ConnectionStatus, OrderbookMessage, Orders},
         stream_service::StreamService,
     };
+    use crate::server::grpc_server::orderbook;
+    use crate::tests::fixtures::OrdersBuilder;
     use approx::assert_relative_eq;
+    use rust_decimal::Decimal;
+
+    /// Helper to build exact decimals out of literals
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
 
     /// Tests that we sort and convert the list of bids accordingly
     #[tokio::test]
     async fn test_sort_and_convert() {
         let mut asks = vec![
             OfferData {
-                price: 50.0,
-                quantity: 0.8,
+                price: dec("50.0"),
+                quantity: dec("0.8"),
             },
             OfferData {
-                price: 30.0,
-                quantity: 5.5,
+                price: dec("30.0"),
+                quantity: dec("5.5"),
             },
             OfferData {
-                price: 100.0,
-                quantity: 2.6,
+                price: dec("100.0"),
+                quantity: dec("2.6"),
             },
         ];
 
         let mut bids = vec![
             OfferData {
-                price: 20.0,
-                quantity: 1.2,
+                price: dec("20.0"),
+                quantity: dec("1.2"),
             },
             OfferData {
-                price: 70.0,
-                quantity: 7.1,
+                price: dec("70.0"),
+                quantity: dec("7.1"),
             },
             OfferData {
-                price: 65.0,
-                quantity: 1.5,
+                price: dec("65.0"),
+                quantity: dec("1.5"),
             },
         ];
 
-        let (converted_asks, converted_bids) =
-            StreamService::sort_and_convert(&mut asks, &mut bids, &Exchange::Binance);
+        let (converted_asks, converted_bids) = Aggregator::sort_and_convert(
+            &mut asks,
+            &mut bids,
+            &Exchange::Binance,
+            MAX_PAIR_EXCHANGE,
+        );
 
         assert_eq!(converted_asks.len(), 3);
         assert_eq!(converted_bids.len(), 3);
@@ -66,16 +83,16 @@ mod tests {
     async fn test_sort_and_convert_max_ten() {
         let mut asks = vec![
             OfferData {
-                price: 50.0,
-                quantity: 0.8
+                price: dec("50.0"),
+                quantity: dec("0.8")
             };
             100
         ];
 
         let mut bids = vec![
             OfferData {
-                price: 20.0,
-                quantity: 1.2
+                price: dec("20.0"),
+                quantity: dec("1.2")
             };
             100
         ];
@@ -83,8 +100,12 @@ mod tests {
         assert_eq!(asks.len(), 100);
         assert_eq!(bids.len(), 100);
 
-        let (converted_asks, converted_bids) =
-            StreamService::sort_and_convert(&mut asks, &mut bids, &Exchange::Binance);
+        let (converted_asks, converted_bids) = Aggregator::sort_and_convert(
+            &mut asks,
+            &mut bids,
+            &Exchange::Binance,
+            MAX_PAIR_EXCHANGE,
+        );
 
         assert_eq!(converted_asks.len(), 10);
         assert_eq!(converted_bids.len(), 10);
@@ -95,39 +116,44 @@ mod tests {
     async fn test_handle_message() {
         let msg = OrderbookMessage::Message {
             message: Box::new(Orders {
+                symbol: "ethbtc".to_string(),
                 asks: vec![
                     OfferData {
-                        price: 75.0,
-                        quantity: 0.8,
+                        price: dec("75.0"),
+                        quantity: dec("0.8"),
                     },
                     OfferData {
-                        price: 60.0,
-                        quantity: 5.5,
+                        price: dec("60.0"),
+                        quantity: dec("5.5"),
                     },
                     OfferData {
-                        price: 65.0,
-                        quantity: 2.1,
+                        price: dec("65.0"),
+                        quantity: dec("2.1"),
                     },
                 ],
                 bids: vec![
                     OfferData {
-                        price: 49.0,
-                        quantity: 7.1,
+                        price: dec("49.0"),
+                        quantity: dec("7.1"),
                     },
                     OfferData {
-                        price: 53.0,
-                        quantity: 1.5,
+                        price: dec("53.0"),
+                        quantity: dec("1.5"),
                     },
                     OfferData {
-                        price: 51.0,
-                        quantity: 7.2,
+                        price: dec("51.0"),
+                        quantity: dec("7.2"),
                     },
                 ],
                 exchange: Exchange::Binance,
+                received_at: None,
+                event_time_us: None,
+                update_id: None,
             }),
         };
 
-        let summary = StreamService::handle_message(&msg).expect("ok");
+        let mut aggregator = Aggregator::new();
+        let summary = StreamService::handle_message(&mut aggregator, &msg);
 
         // The spread should be (60.0 - 53.0) == 7.0. That's because the best ask price is 60.0
         // and the best big price is 53.0
@@ -150,4 +176,275 @@ mod tests {
         assert_relative_eq!(summary.bids[2].price, 49.0);
         assert_relative_eq!(summary.bids[2].amount, 7.1, max_relative = 0.000001);
     }
+
+    /// Tests that books from different exchanges are merged into a single ladder and the
+    /// spread is calculated across venues
+    #[tokio::test]
+    async fn test_handle_message_merges_exchanges() {
+        let mut aggregator = Aggregator::new();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("60.0", "1.0"), ("62.0", "2.0")])
+            .with_bids(&[("50.0", "1.0"), ("48.0", "3.0")])
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&[("59.0", "4.0"), ("61.0", "1.5")])
+            .with_bids(&[("51.0", "0.5"), ("49.0", "2.0")])
+            .message();
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        let summary = StreamService::handle_message(&mut aggregator, &bitstamp);
+
+        // Best ask is Bitstamp's 59.0 and best bid is Bitstamp's 51.0
+        assert_relative_eq!(summary.spread, 8.0);
+        assert_eq!(summary.asks.len(), 4);
+        assert_eq!(summary.bids.len(), 4);
+
+        let ask_prices: Vec<f64> = summary.asks.iter().map(|level| level.price).collect();
+        let ask_exchanges: Vec<&str> = summary
+            .asks
+            .iter()
+            .map(|level| level.exchange.as_str())
+            .collect();
+        assert_eq!(ask_prices, vec![59.0, 60.0, 61.0, 62.0]);
+        assert_eq!(
+            ask_exchanges,
+            vec!["Bitstamp", "Binance", "Bitstamp", "Binance"]
+        );
+
+        let bid_prices: Vec<f64> = summary.bids.iter().map(|level| level.price).collect();
+        assert_eq!(bid_prices, vec![51.0, 50.0, 49.0, 48.0]);
+    }
+
+    /// Tests that a new message from an exchange replaces its previous book instead of adding to it
+    #[tokio::test]
+    async fn test_handle_message_replaces_exchange_book() {
+        let mut aggregator = Aggregator::new();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("60.0", "1.0")])
+            .with_bids(&[("50.0", "1.0")])
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&[("61.0", "1.0")])
+            .with_bids(&[("49.0", "1.0")])
+            .message();
+        let binance_update = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("62.0", "1.0")])
+            .with_bids(&[("48.0", "1.0")])
+            .message();
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        StreamService::handle_message(&mut aggregator, &bitstamp);
+        let summary = StreamService::handle_message(&mut aggregator, &binance_update);
+
+        assert_eq!(summary.asks.len(), 2);
+        assert_eq!(summary.bids.len(), 2);
+        assert_eq!(summary.asks[0].exchange, "Bitstamp");
+        assert_relative_eq!(summary.asks[0].price, 61.0);
+        assert_relative_eq!(summary.asks[1].price, 62.0);
+        assert_eq!(summary.bids[0].exchange, "Bitstamp");
+        assert_relative_eq!(summary.bids[0].price, 49.0);
+        assert_relative_eq!(summary.spread, 12.0);
+    }
+
+    /// Tests that the merged ladder never exceeds 10 levels per side
+    #[tokio::test]
+    async fn test_handle_message_merged_max_ten() {
+        let mut aggregator = Aggregator::new();
+
+        let asks: Vec<_> = (0..8)
+            .map(|i| ((100 + i).to_string(), "1".to_string()))
+            .collect();
+        let bids: Vec<_> = (0..8)
+            .map(|i| ((90 - i).to_string(), "1".to_string()))
+            .collect();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&asks)
+            .with_bids(&bids)
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&asks)
+            .with_bids(&bids)
+            .message();
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        let summary = StreamService::handle_message(&mut aggregator, &bitstamp);
+
+        assert_eq!(summary.asks.len(), 10);
+        assert_eq!(summary.bids.len(), 10);
+        assert_relative_eq!(summary.asks[9].price, 104.0);
+        assert_relative_eq!(summary.bids[9].price, 86.0);
+    }
+
+    /// Tests that a disconnected exchange's levels are dropped and its status is reported
+    #[tokio::test]
+    async fn test_handle_message_disconnected_exchange() {
+        let mut aggregator = Aggregator::new();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("60.0", "1.0")])
+            .with_bids(&[("50.0", "1.0")])
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&[("61.0", "1.0")])
+            .with_bids(&[("49.0", "1.0")])
+            .message();
+        let disconnected = OrderbookMessage::Status {
+            exchange: Exchange::Binance,
+            status: ConnectionStatus::Disconnected,
+        };
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        StreamService::handle_message(&mut aggregator, &bitstamp);
+        let summary = StreamService::handle_message(&mut aggregator, &disconnected);
+
+        assert_eq!(summary.asks.len(), 1);
+        assert_eq!(summary.bids.len(), 1);
+        assert_eq!(summary.asks[0].exchange, "Bitstamp");
+        assert_eq!(summary.bids[0].exchange, "Bitstamp");
+        assert_relative_eq!(summary.spread, 12.0);
+
+        assert_eq!(summary.exchanges.len(), 2);
+        assert_eq!(summary.exchanges[0].exchange, "Binance");
+        assert_eq!(
+            summary.exchanges[0].status,
+            orderbook::ConnectionStatus::Disconnected as i32
+        );
+        assert_eq!(summary.exchanges[1].exchange, "Bitstamp");
+        assert_eq!(
+            summary.exchanges[1].status,
+            orderbook::ConnectionStatus::Connected as i32
+        );
+    }
+
+    /// Tests that a connected exchange that sent nothing for a while is flagged stale, along with
+    /// the age of its book, and its levels are left out until it sends a book again
+    #[tokio::test]
+    async fn test_handle_message_stale_exchange() {
+        let mut aggregator = Aggregator::new().with_stale_after(Some(Duration::from_millis(500)));
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("60.0", "1.0")])
+            .with_bids(&[("50.0", "1.0")])
+            .with_received_at(Instant::now().checked_sub(Duration::from_secs(1)))
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&[("61.0", "1.0")])
+            .with_bids(&[("49.0", "1.0")])
+            .message();
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        let summary = StreamService::handle_message(&mut aggregator, &bitstamp);
+
+        assert_eq!(
+            aggregator.stale_exchanges(Instant::now()),
+            HashSet::from([Exchange::Binance])
+        );
+        assert_eq!(summary.asks.len(), 1);
+        assert_eq!(summary.asks[0].exchange, "Bitstamp");
+        assert_eq!(summary.bids[0].exchange, "Bitstamp");
+        assert_eq!(
+            summary.exchanges[0].status,
+            orderbook::ConnectionStatus::Stale as i32
+        );
+        assert!(summary.exchanges[0].last_update_age_ms.unwrap() >= 1000);
+        assert_eq!(
+            summary.exchanges[1].status,
+            orderbook::ConnectionStatus::Connected as i32
+        );
+        assert!(summary.exchanges[1].last_update_age_ms.unwrap() < 500);
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&[("60.0", "1.0")])
+            .with_bids(&[("50.0", "1.0")])
+            .message();
+        let summary = StreamService::handle_message(&mut aggregator, &binance);
+        assert_eq!(summary.asks[0].exchange, "Binance");
+        assert_eq!(
+            summary.exchanges[0].status,
+            orderbook::ConnectionStatus::Connected as i32
+        );
+    }
+
+    /// Tests that an aggregator built for a client's view merges only the exchanges it asked for,
+    /// down to the depth it asked for
+    #[tokio::test]
+    async fn test_handle_message_client_view() {
+        let mut aggregator = Aggregator::with_view(
+            15,
+            Some(HashSet::from([Exchange::Binance, Exchange::Kraken])),
+        );
+
+        let asks: Vec<_> = (0..10)
+            .map(|i| ((100 + i).to_string(), "1".to_string()))
+            .collect();
+        let bids: Vec<_> = (0..10)
+            .map(|i| ((90 - i).to_string(), "1".to_string()))
+            .collect();
+
+        let binance = OrdersBuilder::new(Exchange::Binance)
+            .with_asks(&asks)
+            .with_bids(&bids)
+            .message();
+        let kraken = OrdersBuilder::new(Exchange::Kraken)
+            .with_asks(&asks)
+            .with_bids(&bids)
+            .message();
+        let bitstamp = OrdersBuilder::new(Exchange::Bitstamp)
+            .with_asks(&[("50.0", "1.0")])
+            .with_bids(&[("95.0", "1.0")])
+            .message();
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        StreamService::handle_message(&mut aggregator, &kraken);
+        let summary = StreamService::handle_message(&mut aggregator, &bitstamp);
+
+        assert_eq!(summary.asks.len(), 15);
+        assert_eq!(summary.bids.len(), 15);
+        assert!(summary
+            .asks
+            .iter()
+            .chain(summary.bids.iter())
+            .all(|level| level.exchange != "Bitstamp"));
+        assert_relative_eq!(summary.asks[14].price, 107.0);
+        assert_relative_eq!(summary.spread, 10.0);
+
+        let exchanges: Vec<&str> = summary
+            .exchanges
+            .iter()
+            .map(|status| status.exchange.as_str())
+            .collect();
+        assert_eq!(exchanges, vec!["Binance", "Kraken"]);
+    }
+
+    /// Tests that prices and amounts reach clients exactly, the merged ladder is sorted on the
+    /// exact prices even when their doubles are equal, and the spread is exact
+    #[tokio::test]
+    async fn test_handle_message_exact_decimals() {
+        let mut aggregator = Aggregator::new();
+        let orders_message = |exchange, ask: &str, bid: &str| {
+            OrdersBuilder::new(exchange)
+                .with_asks(&[(ask, "12.34567891")])
+                .with_bids(&[(bid, "0.00000001")])
+                .message()
+        };
+
+        let binance = orders_message(Exchange::Binance, "0.069123450000000001", "0.06912339");
+        let bitstamp = orders_message(Exchange::Bitstamp, "0.06912345", "0.06912338");
+
+        StreamService::handle_message(&mut aggregator, &binance);
+        let summary = StreamService::handle_message(&mut aggregator, &bitstamp);
+
+        assert_eq!(summary.asks[0].price, summary.asks[1].price);
+        assert_eq!(summary.asks[0].exchange, "Bitstamp");
+        assert_eq!(summary.asks[0].price_decimal, "0.06912345");
+        assert_eq!(summary.asks[1].price_decimal, "0.069123450000000001");
+        assert_eq!(summary.asks[0].amount_decimal, "12.34567891");
+        assert_eq!(summary.bids[0].price_decimal, "0.06912339");
+        assert_eq!(summary.bids[0].amount_decimal, "0.00000001");
+        assert_eq!(summary.spread_decimal, "0.00000006");
+        assert_relative_eq!(summary.spread, 0.00000006, max_relative = 0.000001);
+    }
 }
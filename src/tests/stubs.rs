@@ -0,0 +1,28 @@
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Minimal HTTP server answering every request with `body` as JSON.
+/// Returns the base URL to reach it, e.g. "http://127.0.0.1:1234"
+pub async fn http_json_stub(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((mut tcp_stream, _)) = listener.accept().await {
+            // Requests are tiny GETs so a single read is enough to consume them
+            let mut request = [0; 4096];
+            let _ = tcp_stream.read(&mut request).await;
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = tcp_stream.write_all(response.as_bytes()).await;
+        }
+    });
+
+    url
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::Value;
+    use tokio::{net::TcpListener, sync::broadcast, time::timeout};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use crate::models::{
+        connectors::BitstampConnector,
+        mapper::Exchange,
+        messages::{ConnectionStatus, OrderbookMessage, SymbolChannels},
+        subscribers::Subscribers,
+        supervisor::{supervise, Backoff},
+    };
+    use crate::server::{metrics::Metrics, shutdown::Shutdown};
+
+    const BITSTAMP_ORDER_BOOK: &str = r#"{
+        "data": {
+            "timestamp": "1666000000",
+            "microtimestamp": "1666000000000000",
+            "bids": [["0.07000000", "1.50000000"]],
+            "asks": [["0.07100000", "2.00000000"]]
+        },
+        "channel": "order_book_ethbtc",
+        "event": "data"
+    }"#;
+
+    /// Tests that delays grow exponentially, stay within the jitter bounds and never exceed the max
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(1000));
+
+        for cap in [100, 200, 400, 800, 1000, 1000] {
+            let delay = backoff.next_delay();
+            assert!(delay >= Duration::from_millis(cap / 2), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(cap), "{:?}", delay);
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_millis(100));
+    }
+
+    /// Tests that the supervisor reconnects and subscribes again after Bitstamp closes the
+    /// connection, and that clients are told about the outage
+    #[tokio::test]
+    async fn test_bitstamp_reconnects_and_resubscribes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Every connection expects a subscription, sends a single orderbook and hangs up
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (tcp_stream, _) = listener.accept().await.unwrap();
+                let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+
+                let subscribe = ws_stream.next().await.unwrap().unwrap();
+                let subscribe: Value = serde_json::from_str(subscribe.to_text().unwrap()).unwrap();
+                assert_eq!(subscribe["event"], "bts:subscribe");
+                assert_eq!(subscribe["data"]["channel"], "order_book_ethbtc");
+
+                ws_stream
+                    .send(Message::Text(BITSTAMP_ORDER_BOOK.to_string()))
+                    .await
+                    .unwrap();
+                ws_stream.close(None).await.unwrap();
+            }
+        });
+
+        let (chan_send, mut chan_recv) = broadcast::channel(16);
+        let supervisor = tokio::spawn(supervise(
+            Box::new(BitstampConnector::new(api_url)),
+            vec!["ethbtc".to_string()],
+            SymbolChannels::from([("ethbtc".to_string(), chan_send)]),
+            Subscribers::default(),
+            100,
+            None,
+            Backoff::new(Duration::from_millis(10), Duration::from_millis(50)),
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        ));
+
+        let mut received = Vec::new();
+        while received.len() < 5 {
+            let msg = timeout(Duration::from_secs(5), chan_recv.recv())
+                .await
+                .expect("timed out waiting for supervisor")
+                .unwrap();
+            received.push(match msg {
+                OrderbookMessage::Message { message } => {
+                    assert_eq!(message.exchange, Exchange::Bitstamp);
+                    assert_eq!(message.asks.len(), 1);
+                    assert_eq!(message.bids.len(), 1);
+                    None
+                }
+                OrderbookMessage::Status { exchange, status } => {
+                    assert_eq!(exchange, Exchange::Bitstamp);
+                    Some(status)
+                }
+            });
+        }
+
+        assert_eq!(
+            received,
+            vec![
+                Some(ConnectionStatus::Connected),
+                None,
+                Some(ConnectionStatus::Disconnected),
+                Some(ConnectionStatus::Connected),
+                None,
+            ]
+        );
+
+        server.await.unwrap();
+        supervisor.abort();
+    }
+}
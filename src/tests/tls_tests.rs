@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use tempfile::TempDir;
+    use tokio::{
+        sync::broadcast::{self, Sender},
+        time::timeout,
+    };
+
+    use crate::client::grpc_client::subscribe;
+    use crate::models::{
+        config::{ClientConfig, ClientTlsConfig, ServerConfig, ServerTlsConfig},
+        mapper::Exchange,
+        messages::{OrderbookMessage, SymbolChannels},
+    };
+    use crate::server::{
+        grpc_server::{
+            orderbook::orderbook_aggregator_server::OrderbookAggregatorServer, server_builder,
+            OrderbookService,
+        },
+        metrics::Metrics,
+        shutdown::Shutdown,
+    };
+    use crate::tests::fixtures::OrdersBuilder;
+
+    /// PEM files of a CA, a server certificate for "localhost" and a client certificate, both
+    /// signed by the CA, generated for each test
+    struct Certs {
+        dir: TempDir,
+    }
+
+    impl Certs {
+        fn generate() -> Self {
+            let dir = TempDir::new().unwrap();
+
+            let mut ca_params = CertificateParams::new(Vec::new());
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = Certificate::from_params(ca_params).unwrap();
+            std::fs::write(dir.path().join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+
+            for (name, subject) in [("server", "localhost"), ("client", "client")] {
+                let cert =
+                    Certificate::from_params(CertificateParams::new(vec![subject.to_string()]))
+                        .unwrap();
+                std::fs::write(
+                    dir.path().join(format!("{}.pem", name)),
+                    cert.serialize_pem_with_signer(&ca).unwrap(),
+                )
+                .unwrap();
+                std::fs::write(
+                    dir.path().join(format!("{}.key", name)),
+                    cert.serialize_private_key_pem(),
+                )
+                .unwrap();
+            }
+
+            Certs { dir }
+        }
+
+        fn path(&self, file: &str) -> PathBuf {
+            self.dir.path().join(file)
+        }
+    }
+
+    /// Helper to serve ethbtc over `tls` and get its address and channel back once it's listening
+    async fn serve(tls: &ServerTlsConfig) -> (SocketAddr, Sender<OrderbookMessage>) {
+        // Grab a free port for the server
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (chan_send, _) = broadcast::channel(16);
+        let channels = SymbolChannels::from([("ethbtc".to_string(), chan_send.clone())]);
+        let service = OrderbookService::new(
+            channels,
+            &ServerConfig::default(),
+            Arc::new(Metrics::new()),
+            Shutdown::default(),
+        );
+        tokio::spawn(
+            server_builder(tls)
+                .unwrap()
+                .add_service(OrderbookAggregatorServer::new(service))
+                .serve(addr),
+        );
+
+        // The server may take a moment to start listening
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        (addr, chan_send)
+    }
+
+    /// Helper to build a client of the server at `addr` over `tls`
+    fn client(addr: SocketAddr, tls: ClientTlsConfig) -> ClientConfig {
+        ClientConfig {
+            address: addr.ip(),
+            port: addr.port(),
+            tls,
+            ..ClientConfig::default()
+        }
+    }
+
+    /// Helper to build a client that verifies the server against the CA of `certs`, with the
+    /// client certificate if `with_cert`
+    fn client_tls(certs: &Certs, with_cert: bool) -> ClientTlsConfig {
+        ClientTlsConfig {
+            ca: Some(certs.path("ca.pem")),
+            cert: with_cert.then(|| certs.path("client.pem")),
+            key: with_cert.then(|| certs.path("client.key")),
+            domain: Some("localhost".to_string()),
+        }
+    }
+
+    /// Helper to tell whether the client of `config` gets a Summary from the server of `chan_send`
+    async fn receives_summary(config: &ClientConfig, chan_send: &Sender<OrderbookMessage>) -> bool {
+        let subscribed = timeout(Duration::from_secs(5), subscribe(config))
+            .await
+            .expect("timed out subscribing");
+        let mut stream = match subscribed {
+            Ok(stream) => stream,
+            Err(_) => return false,
+        };
+
+        let _ = chan_send.send(OrdersBuilder::new(Exchange::Binance).message());
+
+        let summary = timeout(Duration::from_secs(5), stream.message())
+            .await
+            .expect("timed out waiting for summary");
+        matches!(summary, Ok(Some(summary)) if summary.symbol == "ethbtc")
+    }
+
+    /// Helper to serve with the certificate of `certs`, requiring client certificates if `mutual`
+    fn server_tls(certs: &Certs, mutual: bool) -> ServerTlsConfig {
+        ServerTlsConfig {
+            cert: Some(certs.path("server.pem")),
+            key: Some(certs.path("server.key")),
+            client_ca: mutual.then(|| certs.path("ca.pem")),
+        }
+    }
+
+    /// Tests that clients trusting the CA of the server stream over TLS, and that neither
+    /// plaintext clients nor clients that don't trust it get anything
+    #[tokio::test]
+    async fn test_tls() {
+        let certs = Certs::generate();
+        let (addr, chan_send) = serve(&server_tls(&certs, false)).await;
+
+        assert!(receives_summary(&client(addr, client_tls(&certs, false)), &chan_send).await);
+
+        let plaintext = client(addr, ClientTlsConfig::default());
+        assert!(plaintext.server_url().starts_with("http://"));
+        assert!(!receives_summary(&plaintext, &chan_send).await);
+
+        // Verified against the system's roots, which don't know the CA
+        let untrusted = ClientTlsConfig {
+            domain: Some("localhost".to_string()),
+            ..ClientTlsConfig::default()
+        };
+        assert!(!receives_summary(&client(addr, untrusted), &chan_send).await);
+    }
+
+    /// Tests that servers requiring client certificates only stream to clients presenting one
+    /// signed by their CA
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let certs = Certs::generate();
+        let (addr, chan_send) = serve(&server_tls(&certs, true)).await;
+
+        assert!(!receives_summary(&client(addr, client_tls(&certs, false)), &chan_send).await);
+        assert!(receives_summary(&client(addr, client_tls(&certs, true)), &chan_send).await);
+
+        // Signed by a CA the server doesn't know
+        let other = Certs::generate();
+        let mut tls = client_tls(&certs, true);
+        tls.cert = Some(other.path("client.pem"));
+        tls.key = Some(other.path("client.key"));
+        assert!(!receives_summary(&client(addr, tls), &chan_send).await);
+    }
+
+    /// Tests that certificate files that can't be read fail to build the server
+    #[test]
+    fn test_server_builder_missing_files() {
+        let tls = ServerTlsConfig {
+            cert: Some(PathBuf::from("missing.pem")),
+            key: Some(PathBuf::from("missing.key")),
+            client_ca: None,
+        };
+
+        match server_builder(&tls) {
+            Ok(_) => panic!("Built a server out of missing files"),
+            Err(error) => assert!(error.to_string().contains("missing.pem")),
+        }
+    }
+}
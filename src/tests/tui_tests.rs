@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ratatui::{
+        backend::TestBackend,
+        buffer::Buffer,
+        crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+        style::Color,
+        Terminal,
+    };
+    use tokio::time::Instant;
+
+    use crate::client::{
+        grpc_client::orderbook::ConnectionStatus,
+        tui::{exchange_color, LadderApp, UpdateRate},
+    };
+    use crate::models::mapper::SummaryOutput;
+    use crate::tests::fixtures::SummaryBuilder;
+
+    /// Helper to build a summary of `symbol` with Bitstamp disconnected
+    fn summary(symbol: &str) -> SummaryOutput {
+        SummaryBuilder::new()
+            .with_symbol(symbol)
+            .with_exchange("Binance", ConnectionStatus::Connected, Some(120))
+            .with_exchange("Bitstamp", ConnectionStatus::Disconnected, None)
+            .with_exchange("Kraken", ConnectionStatus::Stale, Some(45_000))
+            .build()
+    }
+
+    /// Helper to render `app` headless and get the screen back
+    fn render(app: &LadderApp) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        terminal.backend().buffer().clone()
+    }
+
+    /// Helper to get the text of every row of the screen
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Helper to find the position of `text` on the screen
+    fn find(buffer: &Buffer, text: &str) -> (u16, u16) {
+        lines(buffer)
+            .iter()
+            .enumerate()
+            .find_map(|(y, line)| {
+                line.find(text)
+                    .map(|x| (line[..x].chars().count() as u16, y as u16))
+            })
+            .unwrap_or_else(|| panic!("{} not on screen:\n{}", text, lines(buffer).join("\n")))
+    }
+
+    /// Tests that the ladder shows the asks above the spread and the bids below, colored by exchange
+    #[test]
+    fn test_render_ladder() {
+        let mut app = LadderApp::new();
+        app.on_summary(summary("ethbtc"), Instant::now());
+        let buffer = render(&app);
+
+        let (_, worst_ask) = find(&buffer, "0.06802");
+        let (_, best_ask) = find(&buffer, "0.06801");
+        let (_, spread) = find(&buffer, "spread  ");
+        let (_, bid) = find(&buffer, "0.068 ");
+        assert!(worst_ask < best_ask);
+        assert!(best_ask < spread);
+        assert!(spread < bid);
+
+        let (x, y) = find(&buffer, "0.06801");
+        assert_eq!(buffer[(x, y)].fg, exchange_color("Binance"));
+        let (x, y) = find(&buffer, "0.06802");
+        assert_eq!(buffer[(x, y)].fg, exchange_color("Kraken"));
+        let (x, y) = find(&buffer, "0.068 ");
+        assert_eq!(buffer[(x, y)].fg, exchange_color("Bitstamp"));
+    }
+
+    /// Tests that every venue shows its connection status and the age of its book
+    #[test]
+    fn test_render_venues() {
+        let mut app = LadderApp::new();
+        app.on_summary(summary("ethbtc"), Instant::now());
+        let buffer = render(&app);
+
+        let (x, y) = find(&buffer, "CONNECTED");
+        assert_eq!(buffer[(x, y)].fg, Color::Green);
+        let (x, y) = find(&buffer, "DISCONNECTED");
+        assert_eq!(buffer[(x, y)].fg, Color::Red);
+        let (x, y) = find(&buffer, "STALE");
+        assert_eq!(buffer[(x, y)].fg, Color::Yellow);
+        find(&buffer, "45.0s");
+    }
+
+    /// Tests switching between the symbols received and the waiting screen
+    #[test]
+    fn test_render_symbols() {
+        let mut app = LadderApp::new();
+        find(&render(&app), "Waiting for summaries");
+
+        let now = Instant::now();
+        app.on_summary(summary("ethbtc"), now);
+        app.on_summary(summary("btcusdt"), now);
+        assert_eq!(app.selected_symbol(), Some("btcusdt"));
+        find(&render(&app), " btcusdt | spread 0.00001");
+
+        app.next_symbol();
+        assert_eq!(app.selected_symbol(), Some("ethbtc"));
+        find(&render(&app), " ethbtc | spread 0.00001");
+
+        app.next_symbol();
+        assert_eq!(app.selected_symbol(), Some("btcusdt"));
+        app.previous_symbol();
+        assert_eq!(app.selected_symbol(), Some("ethbtc"));
+    }
+
+    /// Tests that q, Esc and Ctrl+C quit and the arrows switch symbols
+    #[test]
+    fn test_on_key() {
+        let mut app = LadderApp::new();
+        let now = Instant::now();
+        app.on_summary(summary("ethbtc"), now);
+        app.on_summary(summary("btcusdt"), now);
+
+        assert!(!app.on_key(KeyEvent::from(KeyCode::Right)));
+        assert_eq!(app.selected_symbol(), Some("ethbtc"));
+        assert!(!app.on_key(KeyEvent::from(KeyCode::Left)));
+        assert_eq!(app.selected_symbol(), Some("btcusdt"));
+
+        assert!(!app.on_key(KeyEvent::from(KeyCode::Char('c'))));
+        assert!(app.on_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(app.on_key(KeyEvent::from(KeyCode::Char('q'))));
+        assert!(app.on_key(KeyEvent::from(KeyCode::Esc)));
+    }
+
+    /// Tests that the update rate is measured per second and drops once updates stop
+    #[test]
+    fn test_update_rate() {
+        let start = Instant::now();
+        let mut rate = UpdateRate::new(start);
+
+        for i in 0..10 {
+            rate.record(start + Duration::from_millis(i * 100));
+        }
+        assert_eq!(rate.total, 10);
+        assert_eq!(rate.per_sec, 0.0);
+
+        rate.record(start + Duration::from_secs(1));
+        assert_eq!(rate.total, 11);
+        assert_eq!(rate.per_sec, 11.0);
+
+        rate.tick(start + Duration::from_secs(3));
+        assert_eq!(rate.per_sec, 0.0);
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+
+    use crate::models::{
+        book::Side, errors::OrderbookError, mapper::Exchange, messages::Orders,
+        validation::validate_book,
+    };
+    use crate::tests::fixtures::OrdersBuilder;
+
+    /// Levels of a side as (price, quantity) pairs
+    type Levels<'a> = &'a [(&'a str, &'a str)];
+
+    /// Helper to build an ethbtc book from Binance out of (price, quantity) pairs
+    fn orders(bids: Levels, asks: Levels) -> Orders {
+        OrdersBuilder::new(Exchange::Binance)
+            .with_bids(bids)
+            .with_asks(asks)
+            .build()
+    }
+
+    /// Tests that sane books pass, whatever the order of their levels
+    #[test]
+    fn test_validate_book() {
+        validate_book(&orders(
+            &[("0.069", "2"), ("0.07", "1")],
+            &[("0.072", "1"), ("0.071", "3")],
+        ))
+        .expect("valid");
+    }
+
+    /// Tests that books missing a side are rejected
+    #[test]
+    fn test_validate_book_empty_side() {
+        match validate_book(&orders(&[("0.07", "1")], &[])) {
+            Err(OrderbookError::EmptyBook { side, .. }) => assert_eq!(side, Side::Ask),
+            other => panic!("Expected an empty book, got {:?}", other),
+        }
+        match validate_book(&orders(&[], &[])) {
+            Err(OrderbookError::EmptyBook { side, .. }) => assert_eq!(side, Side::Bid),
+            other => panic!("Expected an empty book, got {:?}", other),
+        }
+    }
+
+    /// Tests that levels with a price or quantity that isn't positive, or a price that shows up
+    /// twice on the same side, are rejected
+    #[test]
+    fn test_validate_book_invalid_levels() {
+        let cases: [(Levels, Levels, Side); 5] = [
+            (&[("-0.07", "1")], &[("0.071", "1")], Side::Bid),
+            (&[("0", "1")], &[("0.071", "1")], Side::Bid),
+            (&[("0.07", "1")], &[("0.071", "-1")], Side::Ask),
+            (&[("0.07", "0")], &[("0.071", "1")], Side::Bid),
+            (
+                &[("0.07", "1")],
+                &[("0.071", "1"), ("0.0710", "2")],
+                Side::Ask,
+            ),
+        ];
+
+        for (bids, asks, expected) in cases {
+            match validate_book(&orders(bids, asks)) {
+                Err(OrderbookError::InvalidLevel { side, .. }) => assert_eq!(side, expected),
+                other => panic!("Expected an invalid level, got {:?}", other),
+            }
+        }
+
+        // The same price on both sides is crossed rather than duplicated
+        assert!(matches!(
+            validate_book(&orders(&[("0.07", "1")], &[("0.07", "1")])),
+            Err(OrderbookError::CrossedBook { .. })
+        ));
+    }
+
+    /// Tests that books whose best bid is at or above the best ask are rejected
+    #[test]
+    fn test_validate_book_crossed() {
+        match validate_book(&orders(
+            &[("0.07", "1"), ("0.072", "1")],
+            &[("0.073", "1"), ("0.071", "1")],
+        )) {
+            Err(OrderbookError::CrossedBook {
+                best_bid, best_ask, ..
+            }) => {
+                assert_eq!(best_bid, Decimal::from_str("0.072").unwrap());
+                assert_eq!(best_ask, Decimal::from_str("0.071").unwrap());
+            }
+            other => panic!("Expected a crossed book, got {:?}", other),
+        }
+    }
+}